@@ -1,4 +1,4 @@
-use rumax::MaxClient;
+use rumax::{ClientMode, MaxClient, models::{Identity, UserAgent, FetchHistoryOptions}};
 use std::io::{self, Write};
 use std::fs;
 use uuid::Uuid;
@@ -51,6 +51,27 @@ fn get_device() -> (String, String) {
     }
 }
 
+fn make_identity(device_id: String, mt_instance: String) -> Identity {
+    Identity {
+        device_id,
+        mt_instance,
+        user_agent: UserAgent {
+            device_type: "ANDROID".to_string(),
+            app_version: "25.12.1".to_string(),
+            os_version: "Android 14".to_string(),
+            timezone: "Europe/Moscow".to_string(),
+            screen: "xxhdpi 440dpi 1080x2400".to_string(),
+            push_device_type: Some("GCM".to_string()),
+            arch: Some("arm64-v8a".to_string()),
+            locale: "ru".to_string(),
+            build_number: 6498,
+            device_name: "Google Pixel 7".to_string(),
+            device_locale: "ru".to_string(),
+            release: None,
+            header_user_agent: None,
+        },
+    }
+}
 
 #[tokio::main]
 async fn main() {
@@ -58,12 +79,12 @@ async fn main() {
         env_logger::Env::default().default_filter_or("info,max_client_lib=debug")
     ).init();
 
-    let client = Arc::new(MaxClient::new());
+    let client = Arc::new(MaxClient::builder().mode(ClientMode::Mobile).build());
 
     let (device_id, mt) = get_device();
 
     info!("Подключение к MobileSocket...");
-    match client.connect(device_id, mt, true).await {
+    match client.connect(make_identity(device_id, mt)).await {
         Ok(resp) => {
             info!("Handshake успешен!");
             debug!("Ответ Handshake: {:?}", resp.payload);
@@ -80,7 +101,7 @@ async fn main() {
     match client.start_auth(phone).await {
         Ok(resp) => {
             info!("Запрос кода успешен.");
-            debug!("Ответ start_auth: {:?}", resp.payload);
+            debug!("Ответ start_auth: {:?}", resp);
         }
         Err(e) => {
             error!("Ошибка запроса кода: {}", e);
@@ -94,12 +115,9 @@ async fn main() {
     match client.check_code(code).await {
         Ok(resp) => {
             info!("Код принят, логин успешен!");
-            debug!("Ответ check_code: {:?}", resp.payload);
+            debug!("Ответ check_code: {:?}", resp);
             
-            resp.payload.get("tokenAttrs")
-                .and_then(|t| t.get("LOGIN"))
-                .and_then(|l| l.get("token"))
-                .and_then(|t| t.as_str())
+            resp.login_token()
                 .map(|t| t.to_string())
                 .unwrap_or_else(|| {
                     log::error!("token отсутствует в ответе сервера!");
@@ -115,25 +133,15 @@ async fn main() {
     info!("Выполняем синхронизацию...");
     match client.sync().await {
         Ok(sync_resp) => {
-            log::info!("Синхронизация успешна. {:?}", sync_resp.payload);
-            
-            let user_id = sync_resp.payload
-                .get("profile")
-                .and_then(|s| s.get("contact"))
-                .and_then(|s| s.get("id"))
-                .and_then(|id| id.as_u64());
-            
-            log::info!("test {:?}", user_id);
+            log::info!("Синхронизация успешна. {:?}", sync_resp.profile);
             
-            if let Some(id) = user_id {
-                log::info!("Установка user_id: {}", id);
-                client.set_user_id(id).await;
-                
-                log::info!("Запуск фоновой задачи телеметрии...");
-                client.spawn_telemetry_task().await;
-            } else {
-                log::warn!("Не удалось найти user_id в ответе sync. Телеметрия не запущена.");
+            if let Some(user_id) = sync_resp.user_id() {
+                log::info!("Установка user_id: {}", user_id);
+                client.set_user_id(user_id as u64).await;
             }
+            
+            log::info!("Запуск фоновой задачи телеметрии...");
+            client.spawn_telemetry_task().await;
         }
         Err(e) => {
             log::error!("Ошибка sync: {}", e);
@@ -158,16 +166,19 @@ async fn main() {
     match client.send_message(chat_id, message, None).await {
         Ok(resp) => {
             info!("Сообщение успешно отправлено!");
-            info!("Ответ send_message: {:?}", resp.payload);
+            info!("Ответ send_message: {:?}", resp);
         }
         Err(e) => {
             error!("Ошибка отправки сообщения: {}", e);
         }
     }
     
-    match client.fetch_history(chat_id, Option::None, 0, 200).await {
-        Ok(resp) => {
-            info!("Ответ fetch_history: {:?}", resp.payload);
+    match client.fetch_history(chat_id, Some(FetchHistoryOptions::new().forward(0).backward(200))).await {
+        Ok(messages) => {
+            info!("Ответ fetch_history: {} сообщений", messages.len());
+            for message in messages {
+                info!("[{}] {:?}: {}", message.time, message.sender, message.text);
+            }
         }
         Err(e) => {
             error!("Ошибка отправки сообщения: {}", e);
@@ -1,4 +1,4 @@
-use rumax::MaxClient;
+use rumax::{ClientMode, MaxClient, models::{Identity, UserAgent}};
 use std::io::{self, Write};
 use std::fs;
 use uuid::Uuid;
@@ -51,18 +51,40 @@ fn write_tokens(id1: &str, id2: &str) {
     .expect("Не удалось записать .device.id");
 }
 
+fn make_identity(device_id: String, mt_instance: String) -> Identity {
+    Identity {
+        device_id,
+        mt_instance,
+        user_agent: UserAgent {
+            device_type: "ANDROID".to_string(),
+            app_version: "25.12.1".to_string(),
+            os_version: "Android 14".to_string(),
+            timezone: "Europe/Moscow".to_string(),
+            screen: "xxhdpi 440dpi 1080x2400".to_string(),
+            push_device_type: Some("GCM".to_string()),
+            arch: Some("arm64-v8a".to_string()),
+            locale: "ru".to_string(),
+            build_number: 6498,
+            device_name: "Google Pixel 7".to_string(),
+            device_locale: "ru".to_string(),
+            release: None,
+            header_user_agent: None,
+        },
+    }
+}
+
 #[tokio::main]
 async fn main() {
     env_logger::Builder::from_env(
         env_logger::Env::default().default_filter_or("info,max_client_lib=debug")
     ).init();
     
-    let client = Arc::new(MaxClient::new());
+    let client = Arc::new(MaxClient::builder().mode(ClientMode::Mobile).build());
     
     let (device_id, mt) = get_device();
     
     info!("Подключение к MobileSocket...");
-    match client.connect(device_id, mt, true).await {
+    match client.connect(make_identity(device_id, mt)).await {
         Ok(resp) => {
             info!("Handshake успешен!");
             debug!("Ответ Handshake: {:?}", resp.payload);
@@ -79,7 +101,7 @@ async fn main() {
     match client.start_auth(phone).await {
         Ok(resp) => {
             info!("Запрос кода успешен.");
-            debug!("Ответ start_auth: {:?}", resp.payload);
+            debug!("Ответ start_auth: {:?}", resp);
         }
         Err(e) => {
             error!("Ошибка запроса кода: {}", e);
@@ -90,15 +112,10 @@ async fn main() {
     let code = read_line("Введите код из СМС: ");
     info!("Проверяем код...");
     
-    let token: String;
-    
-    match client.check_code(code).await {
+    let token: String = match client.check_code(code).await {
         Ok(resp) => {
             info!("Верный код! Регистрируемся...");
-            token = resp.payload.get("tokenAttrs")
-                .and_then(|t| t.get("REGISTER"))
-                .and_then(|l| l.get("token"))
-                .and_then(|t| t.as_str())
+            resp.register_token()
                 .map(|t| t.to_string())
                 .unwrap_or_else(|| {
                     log::error!("token отсутствует в ответе сервера!");
@@ -109,7 +126,7 @@ async fn main() {
             info!("Ошибка проверки кода! {}", e);
             return;
         }
-    }
+    };
     
     log::info!("token {:?}", token);
     
@@ -122,25 +139,15 @@ async fn main() {
     info!("Выполняем синхронизацию (sync)...");
     match client.sync().await {
         Ok(sync_resp) => {
-            log::info!("Синхронизация успешна. {:?}", sync_resp.payload);
-            
-            let user_id = sync_resp.payload
-                .get("profile")
-                .and_then(|s| s.get("contact"))
-                .and_then(|s| s.get("id"))
-                .and_then(|id| id.as_u64());
+            log::info!("Синхронизация успешна. {:?}", sync_resp.profile);
             
-            log::info!("test {:?}", user_id);
-            
-            if let Some(id) = user_id {
-                log::info!("Установка user_id: {}", id);
-                client.set_user_id(id).await;
-                
-                log::info!("Запуск фоновой задачи телеметрии...");
-                client.spawn_telemetry_task().await;
-            } else {
-                log::warn!("Не удалось найти user_id в ответе sync. Телеметрия не запущена.");
+            if let Some(user_id) = sync_resp.user_id() {
+                log::info!("Установка user_id: {}", user_id);
+                client.set_user_id(user_id as u64).await;
             }
+            
+            log::info!("Запуск фоновой задачи телеметрии...");
+            client.spawn_telemetry_task().await;
         }
         Err(e) => {
             log::error!("Ошибка sync: {}", e);
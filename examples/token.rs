@@ -1,12 +1,12 @@
-use rumax::{MaxClient, models::Response};
+use rumax::{ClientMode, MaxClient, models::{SyncResult, Identity, UserAgent, FetchHistoryOptions}};
+use rumax::errors::Error;
+use rumax::session::{FileSessionStore, SessionStore};
 use std::io::{self, Write};
-use std::fs;
 use std::sync::Arc;
 use uuid::Uuid;
 use log::{info, error, debug, warn};
 
-const DEVICE_ID_FILE: &str = ".device.id";
-const TOKEN_FILE: &str = ".session.token";
+const SESSION_FILE: &str = ".session.json";
 
 fn read_line(prompt: &str) -> String {
     print!("{}", prompt);
@@ -16,125 +16,97 @@ fn read_line(prompt: &str) -> String {
     input.trim().to_string()
 }
 
-fn read_tokens() -> Option<(String, String)> {
-    if let Ok(content) = fs::read_to_string(DEVICE_ID_FILE) {
-        let mut lines = content.lines().map(str::trim).filter(|l| !l.is_empty());
-        if let (Some(first), Some(second)) = (lines.next(), lines.next()) {
-            return Some((first.to_string(), second.to_string()));
-        }
-    }
-    None
-}
-
-fn write_tokens(id1: &str, id2: &str) {
-    let content = format!("{}\n{}", id1, id2);
-    fs::write(DEVICE_ID_FILE, content)
-        .expect("Не удалось записать .device.id");
-}
-
-fn get_device() -> (String, String) {
-    match read_tokens() {
-        Some((id1, id2)) => {
-            info!("Используем существующие device_id из файла {}", DEVICE_ID_FILE);
-            (id1, id2)
-        }
-        None => {
-            info!("Создаем новые device_id...");
-
-            let id1 = Uuid::new_v4().to_string().replace("-", "");
-            let id2 = Uuid::new_v4().to_string();
-
-            write_tokens(&id1, &id2);
+fn new_identity() -> Identity {
+    info!("Создаем новые device_id...");
 
-            info!("Новые device_id сохранены в {}", DEVICE_ID_FILE);
-            (id1, id2)
-        }
-    }
-}
+    let device_id = Uuid::new_v4().to_string().replace("-", "");
+    let mt_instance = Uuid::new_v4().to_string();
 
-fn load_token() -> Option<String> {
-    match fs::read_to_string(TOKEN_FILE) {
-        Ok(token) if !token.is_empty() => {
-            info!("Токен сессии загружен из {}", TOKEN_FILE);
-            Some(token)
-        }
-        _ => {
-            info!("Файл токена {} не найден", TOKEN_FILE);
-            None
-        }
-    }
+    make_identity(device_id, mt_instance)
 }
 
-fn save_token(token: &str) {
-    if let Err(e) = fs::write(TOKEN_FILE, token) {
-        error!("Не удалось сохранить токен в {}: {}", TOKEN_FILE, e);
-    } else {
-        info!("Токен сессии сохранен в {}", TOKEN_FILE);
-    }
-}
-
-fn delete_token() {
-    if fs::remove_file(TOKEN_FILE).is_ok() {
-        info!("Файл токена {} удален", TOKEN_FILE);
+async fn set_user_id_and_spawn_telemetry(client: &MaxClient, sync_resp: &SyncResult) {
+    if let Some(user_id) = sync_resp.user_id() {
+        info!("Установка user_id: {}", user_id);
+        client.set_user_id(user_id as u64).await;
     }
+    
+    info!("Запуск фоновой задачи телеметрии...");
+    client.spawn_telemetry_task().await;
 }
 
-async fn set_user_id_and_spawn_telemetry(client: &MaxClient, sync_resp: &Response) {
-    let user_id = sync_resp.payload
-        .get("profile")
-        .and_then(|s| s.get("contact"))
-        .and_then(|s| s.get("id"))
-        .and_then(|id| id.as_u64());
-    
-    if let Some(id) = user_id {
-        info!("Установка user_id: {}", id);
-        client.set_user_id(id).await;
-        
-        info!("Запуск фоновой задачи телеметрии...");
-        client.spawn_telemetry_task().await;
-    } else {
-        warn!("Не удалось найти user_id в ответе sync. Телеметрия не запущена!");
+fn make_identity(device_id: String, mt_instance: String) -> Identity {
+    Identity {
+        device_id,
+        mt_instance,
+        user_agent: UserAgent {
+            device_type: "ANDROID".to_string(),
+            app_version: "25.12.1".to_string(),
+            os_version: "Android 14".to_string(),
+            timezone: "Europe/Moscow".to_string(),
+            screen: "xxhdpi 440dpi 1080x2400".to_string(),
+            push_device_type: Some("GCM".to_string()),
+            arch: Some("arm64-v8a".to_string()),
+            locale: "ru".to_string(),
+            build_number: 6498,
+            device_name: "Google Pixel 7".to_string(),
+            device_locale: "ru".to_string(),
+            release: None,
+            header_user_agent: None,
+        },
     }
 }
 
-
 #[tokio::main]
-async fn main() -> Result<(), rumax::errors::Error> {
+async fn main() -> Result<(), Error> {
     env_logger::Builder::from_env(
         env_logger::Env::default().default_filter_or("info,max_client_lib=debug")
     ).init();
     
-    let client = Arc::new(MaxClient::new());
-    let (device_id, mt) = get_device();
+    let store = Arc::new(FileSessionStore::new(SESSION_FILE));
+    let client = Arc::new(MaxClient::builder().mode(ClientMode::Mobile).build());
+    client.set_store(store.clone()).await?;
+
+    let identity = match client.get_identity().await {
+        Some(identity) => {
+            info!("Используем сохраненный identity из {}", SESSION_FILE);
+            identity
+        }
+        None => new_identity(),
+    };
     
     info!("Подключение к MobileSocket...");
-    match client.connect(device_id, mt, true).await {
+    match client.connect(identity).await {
         Ok(resp) => {
             info!("Handshake успешен!");
             debug!("Ответ Handshake: {:?}", resp.payload);
         }
         Err(e) => {
             error!("Ошибка подключения: {}", e);
-            return Err(e.into());
+            return Err(e);
         }
     }
 
-    if let Some(token) = load_token() {
+    if client.get_token().await.is_some() {
         info!("Попытка входа по сохраненному токену...");
-        
-        client.set_token(token).await;
 
         match client.sync().await {
             Ok(sync_resp) => {
                 info!("Вход по токену успешен!");
                 set_user_id_and_spawn_telemetry(&client, &sync_resp).await;
             }
-            Err(e) => {
-                warn!("Ошибка входа по токену (возможно, истек): {}. Удаляем токен", e);
-                delete_token();
+            Err(Error::AuthExpired(e)) => {
+                warn!("Токен истек: {}. Удаляем сессию", e);
+                if let Err(e) = store.clear().await {
+                    error!("Не удалось удалить {}: {}", SESSION_FILE, e);
+                }
                 info!("Перезапустите скрипт для входа по номеру телефона");
                 return Ok(());
             }
+            Err(e) => {
+                error!("Ошибка входа по токену: {}", e);
+                return Err(e);
+            }
         }
     } else {
         info!("Токен не найден, запуск входа по номеру телефона...");
@@ -142,30 +114,24 @@ async fn main() -> Result<(), rumax::errors::Error> {
         let phone = read_line("Введите номер телефона (+7...): ");
         if let Err(e) = client.start_auth(phone).await {
             error!("Ошибка запроса кода: {}", e);
-            return Err(e.into());
+            return Err(e);
         }
         
         let code = read_line("Введите код из СМС/звонка: ");
         if let Err(e) = client.check_code(code).await {
             error!("Ошибка проверки кода: {}", e);
-            return Err(e.into());
+            return Err(e);
         }
         
         match client.sync().await {
             Ok(sync_resp) => {
-                info!("Вход по коду и телефону успешен");
-                
-                if let Some(new_token) = client.get_token().await {
-                    save_token(&new_token);
-                } else {
-                    warn!("Не удалось получить токен из клиента для сохранения");
-                }
+                info!("Вход по коду и телефону успешен, сессия сохранена в {}", SESSION_FILE);
 
                 set_user_id_and_spawn_telemetry(&client, &sync_resp).await;
             }
             Err(e) => {
                 error!("Ошибка синхронизации: {}", e);
-                return Err(e.into());
+                return Err(e);
             }
         }
     }
@@ -187,16 +153,19 @@ async fn main() -> Result<(), rumax::errors::Error> {
     match client.send_message(chat_id, message, None).await {
         Ok(resp) => {
             info!("Сообщение успешно отправлено!");
-            info!("Ответ send_message: {:?}", resp.payload);
+            info!("Ответ send_message: {:?}", resp);
         }
         Err(e) => {
             error!("Ошибка отправки сообщения: {}", e);
         }
     }
     
-    match client.fetch_history(chat_id, Option::None, 0, 200).await {
-        Ok(resp) => {
-            info!("Ответ fetch_history: {:?}", resp.payload);
+    match client.fetch_history(chat_id, Some(FetchHistoryOptions::new().forward(0).backward(200))).await {
+        Ok(messages) => {
+            info!("Ответ fetch_history: {} сообщений", messages.len());
+            for message in messages {
+                info!("[{}] {:?}: {}", message.time, message.sender, message.text);
+            }
         }
         Err(e) => {
             error!("Ошибка отправки сообщения: {}", e);
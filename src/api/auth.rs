@@ -1,14 +1,15 @@
 use crate::{errors::ClientResult, MaxClient};
-use crate::models::{Response};
-use serde_json::json;
+use crate::models::{value_as_id, AuthResult, SyncResult, Opcode};
+use crate::session::SyncMarkers;
+use serde_json::{json, Value};
 
 impl MaxClient {
     /**
      * Начало логина
      */
-    pub async fn start_auth(&self, phone: String) -> ClientResult<Response> {
+    pub async fn start_auth(&self, phone: String) -> ClientResult<AuthResult> {
         let payload = json!({ "phone": phone, "type": "START_AUTH", "language": "ru" });
-        let resp = self.send_and_wait(17, payload, 0).await?;
+        let resp = self.send_and_wait(Opcode::AuthRequest, payload, 0).await?;
         
         log::debug!("start_auth response {:?}", resp);
         
@@ -17,13 +18,13 @@ impl MaxClient {
             self.set_temp_token(token.to_string()).await;
         }
 
-        Ok(resp)
+        resp.parse()
     }
     
     /**
      * Завершение логина
      */
-    pub async fn check_code(&self, code: String) -> ClientResult<Response> {
+    pub async fn check_code(&self, code: String) -> ClientResult<AuthResult> {
         let state = self.state.lock().await;
         let token = state.temp_token.as_ref().ok_or("No temporary token found".to_string())?;
         
@@ -31,7 +32,7 @@ impl MaxClient {
         
         drop(state);
         
-        let resp = self.send_and_wait(18, payload, 0).await?;
+        let resp = self.send_and_wait(Opcode::Auth, payload, 0).await?;
         
         log::debug!("check_code response {:?}", resp);
         
@@ -52,20 +53,22 @@ impl MaxClient {
                 }
             }
         }
+
+        self.save_session().await;
         
-        Ok(resp)
+        resp.parse()
     }
 
     /**
      * Проверка облачного пароля (2FA)
      */
-    pub async fn check_password(&self, password: String, track_id: String) -> ClientResult<Response> {
+    pub async fn check_password(&self, password: String, track_id: String) -> ClientResult<AuthResult> {
         let payload = json!({
             "password": password,
             "trackId": track_id,
         });
 
-        let resp = self.send_and_wait(115, payload, 0).await?;
+        let resp = self.send_and_wait(Opcode::AuthLoginCheckPassword, payload, 0).await?;
 
         log::debug!("check_password response {:?}", resp);
 
@@ -84,7 +87,9 @@ impl MaxClient {
             }
         }
 
-        Ok(resp)
+        self.save_session().await;
+
+        resp.parse()
     }
 
     /**
@@ -94,7 +99,7 @@ impl MaxClient {
         &self,
         first_name: String,
         last_name: Option<String>,
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<AuthResult> {
         let payload = json!({
             "firstName": first_name,
             "lastName": last_name,
@@ -103,7 +108,7 @@ impl MaxClient {
             "tokenType": "REGISTER",
         });
         
-        let resp = self.send_and_wait(23, payload, 0).await?;
+        let resp = self.send_and_wait(Opcode::AuthConfirm, payload, 0).await?;
         
         if let Some(token) = resp
             .payload
@@ -113,24 +118,51 @@ impl MaxClient {
             log::info!("Token received! {:?}", token.to_string());
             self.set_token(token.to_string()).await;
         }
+
+        self.save_session().await;
         
-        Ok(resp)
+        resp.parse()
     }
     
     /**
      * Перезаход в мессенджер
      */
-    pub async fn sync(&self) -> ClientResult<Response> {
+    pub async fn sync(&self) -> ClientResult<SyncResult> {
         let state = self.state.lock().await;
         let token = state.token.as_ref().ok_or("No token set".to_string())?;
+        let markers = state.sync_markers;
         
         let payload = json!({
             "interactive": true, "token": token,
-            "chatsSync": 0, "contactsSync": 0, "presenceSync": 0, "draftsSync": 0, "chatsCount": 40,
+            "chatsSync": markers.chats_sync, "contactsSync": markers.contacts_sync,
+            "presenceSync": markers.presence_sync, "draftsSync": markers.drafts_sync,
+            "chatsCount": 40,
         });
 
         drop(state);
         
-        self.send_and_wait(19, payload, 0).await
+        let resp = self.send_and_wait(Opcode::Login, payload, 0).await?;
+
+        /* Вход уже принят сервером: состояние обновляется до разбора в модель */
+        {
+            let mut state = self.state.lock().await;
+            if let Some(user_id) = resp.payload.pointer("/profile/contact/id").and_then(value_as_id) {
+                state.user_id = Some(user_id as u64);
+            }
+            state.logged_in = true;
+            if let Some(time) = resp.payload.get("time").and_then(Value::as_i64) {
+                state.sync_markers = SyncMarkers {
+                    chats_sync: time,
+                    contacts_sync: time,
+                    presence_sync: time,
+                    drafts_sync: time,
+                };
+            }
+        }
+
+        self.save_session().await;
+        self.outbox.wake();
+
+        resp.parse()
     }
 }
@@ -1,5 +1,5 @@
 use crate::{errors::ClientResult, MaxClient};
-use crate::models::Response;
+use crate::models::{Call, Opcode};
 use serde_json::json;
 
 impl MaxClient {
@@ -7,11 +7,11 @@ impl MaxClient {
         &self,
         forward: bool,
         count: i64
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<Vec<Call>> {
         let payload = json!({
             "forward": forward,
             "count": count,
         });
-        self.send_and_wait(79, payload, 0).await
+        self.send_and_wait(Opcode::VideoChatHistory, payload, 0).await?.take("history")
     }
 }
@@ -1,30 +1,30 @@
 use crate::{errors::ClientResult, MaxClient};
 use serde_json::{json, Map, Value};
-use crate::models::Response;
+use crate::models::{Chat, LinkInfo, MembersPage, Opcode};
 
 impl MaxClient {
     pub async fn join_channel(
         &self,
         link: String
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<Chat> {
         self.join_group(link).await
     }
 
     pub async fn leave_channel(
         &self,
         chat_id: i64
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<()> {
         self.leave_group(chat_id).await
     }
 
     pub async fn resolve_channel_by_name(
         &self,
         link: String,
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<LinkInfo> {
         let payload = json!({
             "link": format!("https://max.ru/{link}"),
         });
-        self.send_and_wait(89, payload, 0).await
+        self.send_and_wait(Opcode::LinkInfo, payload, 0).await?.parse()
     }
 
     pub async fn get_members(
@@ -32,7 +32,7 @@ impl MaxClient {
         chat_id: i64,
         count: i64,
         marker: Option<i64>,
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<MembersPage> {
         let mut payload = Map::new();
 
         payload.insert("type".into(), json!("MEMBER"));
@@ -44,19 +44,19 @@ impl MaxClient {
         payload.insert("chatId".into(), json!(chat_id));
         payload.insert("count".into(), json!(count));
 
-        self.send_and_wait(59, Value::Object(payload), 0).await
+        self.send_and_wait(Opcode::ChatMembers, Value::Object(payload), 0).await?.parse()
     }
 
     pub async fn find_members(
         &self,
         chat_id: i64,
         query: String
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<MembersPage> {
         let payload = json!({
             "type": "MEMBER",
             "query": query,
             "chatId": chat_id,
         });
-        self.send_and_wait(59, payload, 0).await
+        self.send_and_wait(Opcode::ChatMembers, payload, 0).await?.parse()
     }
 }
@@ -1,6 +1,6 @@
 use crate::{errors::ClientResult, MaxClient};
 use serde_json::{json, Map, Value};
-use crate::models::Response;
+use crate::models::{Chat, LinkInfo, MembersPage, SearchResult, Opcode};
 use chrono::Utc;
 
 impl MaxClient {
@@ -9,14 +9,14 @@ impl MaxClient {
         query: String,
         count: i32,
         search_type: String,
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<SearchResult> {
         let payload = json!({
             "query": query,
             "count": count,
             "type": search_type,
         });
 
-        self.send_and_wait(60, payload, 0).await
+        self.send_and_wait(Opcode::PublicSearch, payload, 0).await?.parse()
     }
 
     pub async fn search_msg(
@@ -24,7 +24,7 @@ impl MaxClient {
         query: String,
         count: i32,
         marker: Option<String>,
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<SearchResult> {
         let mut payload = Map::new();
 
         payload.insert("query".into(), json!(query));
@@ -34,18 +34,18 @@ impl MaxClient {
             payload.insert("marker".into(), json!(m));
         }
 
-        self.send_and_wait(68, Value::Object(payload), 0).await
+        self.send_and_wait(Opcode::ChatSearch, Value::Object(payload), 0).await?.parse()
     }
 
     pub async fn get_chats(
         &self,
         chat_ids: Vec<i64>
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<Vec<Chat>> {
         let payload = json!({
             "chatIds": chat_ids,
         });
 
-        self.send_and_wait(48, payload, 0).await
+        self.send_and_wait(Opcode::ChatInfo, payload, 0).await?.take("chats")
     }
 
     pub async fn create_group(
@@ -53,7 +53,7 @@ impl MaxClient {
         title: String,
         participant_ids: Option<Vec<i64>>,
         notify: Option<bool>
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<Chat> {
         let payload = json!({
             "message": {
                 "cid": Utc::now().timestamp_millis(),
@@ -68,7 +68,7 @@ impl MaxClient {
             "notify": notify.unwrap_or(true)
         });
 
-        self.send_and_wait(64, payload, 0).await
+        self.send_and_wait(Opcode::MsgSend, payload, 0).await?.take("chat")
     }
 
     pub async fn delete_chat(
@@ -76,33 +76,36 @@ impl MaxClient {
         chat_id: i64,
         last_event_time: Option<i64>,
         for_all: Option<bool>,
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<()> {
         let payload = json!({
             "chatId": chat_id,
             "lastEventTime": last_event_time.unwrap_or(Utc::now().timestamp_millis()),
             "forAll": for_all.unwrap_or(false)
         });
 
-        self.send_and_wait(52, payload, 0).await
+        self.send_and_wait(Opcode::ChatDelete, payload, 0).await?;
+        Ok(())
     }
 
     pub async fn leave_group(
         &self,
         chat_id: i64,
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<()> {
         let payload = json!({
             "chatId": chat_id
         });
 
-        self.send_and_wait(58, payload, 0).await
+        self.send_and_wait(Opcode::ChatLeave, payload, 0).await?;
+        Ok(())
     }
 
+    /* Методы изменения чата возвращают обновленный чат, если сервер прислал его в ответе */
     pub async fn change_group_profile(
         &self,
         chat_id: i64,
         title: Option<String>,
         description: Option<String>,
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<Option<Chat>> {
         let mut payload = Map::new();
 
         payload.insert("chatId".into(), json!(chat_id));
@@ -115,41 +118,41 @@ impl MaxClient {
             payload.insert("description".into(), json!(d));
         }
 
-        self.send_and_wait(55, Value::Object(payload), 0).await
+        self.send_and_wait(Opcode::ChatUpdate, Value::Object(payload), 0).await?.take_opt("chat")
     }
 
     pub async fn join_group(
         &self,
         link: String,
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<Chat> {
         let payload = json!({
             "link": link,
         });
 
-        self.send_and_wait(57, payload, 0).await
+        self.send_and_wait(Opcode::ChatJoin, payload, 0).await?.take("chat")
     }
 
     pub async fn resolve_group_by_link(
         &self,
         link: String,
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<LinkInfo> {
         let payload = json!({
             "link": link,
         });
 
-        self.send_and_wait(89, payload, 0).await
+        self.send_and_wait(Opcode::LinkInfo, payload, 0).await?.parse()
     }
 
     pub async fn refresh_invite_link(
         &self,
         chat_id: i64,
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<Option<Chat>> {
         let payload = json!({
             "revokePrivateLink": true,
             "chatId": chat_id,
         });
 
-        self.send_and_wait(55, payload, 0).await
+        self.send_and_wait(Opcode::ChatUpdate, payload, 0).await?.take_opt("chat")
     }
 
     pub async fn confirm_join_requests(
@@ -157,7 +160,7 @@ impl MaxClient {
         chat_id: i64,
         user_ids: Vec<i64>,
         show_history: Option<bool>,
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<Option<Chat>> {
         let payload = json!({
             "chatId": chat_id,
             "userIds": user_ids,
@@ -166,21 +169,21 @@ impl MaxClient {
             "operation": "add",
         });
 
-        self.send_and_wait(77, payload, 0).await
+        self.send_and_wait(Opcode::ChatMembersUpdate, payload, 0).await?.take_opt("chat")
     }
 
     pub async fn decline_join_requests(
         &self,
         chat_id: i64,
         user_ids: Vec<i64>,
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<Option<Chat>> {
         let payload = json!({
             "chatId": chat_id,
             "userIds": user_ids,
             "operation": "remove",
         });
 
-        self.send_and_wait(77, payload, 0).await
+        self.send_and_wait(Opcode::ChatMembersUpdate, payload, 0).await?.take_opt("chat")
     }
 
     /* TODO: make ChangeGroupSettingsOptions struct! */
@@ -192,7 +195,7 @@ impl MaxClient {
         only_admin_can_add_member: Option<bool>,
         only_admin_can_call: Option<bool>,
         members_can_see_private_link: Option<bool>,
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<Option<Chat>> {
         let mut settings = Map::new();
 
         if let Some(b) = all_can_pin_message {
@@ -220,7 +223,7 @@ impl MaxClient {
             "options": Value::Object(settings),
         });
 
-        self.send_and_wait(55, payload, 0).await
+        self.send_and_wait(Opcode::ChatUpdate, payload, 0).await?.take_opt("chat")
     }
 
     pub async fn remove_users_from_group(
@@ -228,7 +231,7 @@ impl MaxClient {
         chat_id: i64,
         user_ids: Vec<i64>,
         clean_msg_period: i64
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<Option<Chat>> {
         let payload = json!({
             "chatId": chat_id,
             "userIds": user_ids,
@@ -236,7 +239,7 @@ impl MaxClient {
             "cleanMsgPeriod": clean_msg_period
         });
 
-        self.send_and_wait(77, payload, 0).await
+        self.send_and_wait(Opcode::ChatMembersUpdate, payload, 0).await?.take_opt("chat")
     }
 
     pub async fn invite_users_to_group(
@@ -244,7 +247,7 @@ impl MaxClient {
         chat_id: i64,
         user_ids: Vec<i64>,
         show_history: Option<bool>
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<Option<Chat>> {
         let payload = json!({
             "chatId": chat_id,
             "userIds": user_ids,
@@ -252,19 +255,19 @@ impl MaxClient {
             "operation": "add",
         });
 
-        self.send_and_wait(77, payload, 0).await
+        self.send_and_wait(Opcode::ChatMembersUpdate, payload, 0).await?.take_opt("chat")
     }
 
     pub async fn get_join_requests(
         &self,
         chat_id: i64,
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<MembersPage> {
         let payload = json!({
             "chatId": chat_id,
             "type": "JOIN_REQUEST",
             "count": 100
         });
 
-        self.send_and_wait(59, payload, 0).await
+        self.send_and_wait(Opcode::ChatMembers, payload, 0).await?.parse()
     }
 }
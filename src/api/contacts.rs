@@ -1,50 +1,51 @@
 use crate::{errors::ClientResult, MaxClient};
-use crate::models::Response;
+use crate::models::{Contact, Opcode};
 use serde_json::json;
 
 impl MaxClient {
     pub async fn get_by_phone(
         &self,
         phone: String
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<Contact> {
         let payload = json!({
             "phone": phone
         });
-        self.send_and_wait(46, payload, 0).await
+        self.send_and_wait(Opcode::ContactInfoByPhone, payload, 0).await?.take("contact")
     }
     
     pub async fn fetch_contacts(
         &self,
         user_ids: Vec<u64>
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<Vec<Contact>> {
         let payload = json!({
             "contactIds": user_ids
         });
-        self.send_and_wait(32, payload, 0).await
+        self.send_and_wait(Opcode::ContactInfo, payload, 0).await?.take("contacts")
     }
     
     pub async fn add_contact(
         &self,
         user_id: u64,
         first_name: String
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<Contact> {
         let payload = json!({
             "contactId": user_id,
             "firstName": first_name,
             "action": "ADD"
         });
-        self.send_and_wait(34, payload, 0).await
+        self.send_and_wait(Opcode::ContactUpdate, payload, 0).await?.take("contact")
     }
     
     pub async fn delete_contact(
         &self,
         user_id: u64
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<()> {
         let payload = json!({
             "contactId": user_id,
             "action": "REMOVE",
         });
-        self.send_and_wait(34, payload, 0).await
+        self.send_and_wait(Opcode::ContactUpdate, payload, 0).await?;
+        Ok(())
     }
 }
 
@@ -0,0 +1,293 @@
+use crate::{errors::{ClientResult, DownloadError, Error}, MaxClient};
+use crate::models::{Attachment, AttachmentType, DownloadOptions};
+use futures_util::StreamExt;
+use log::{debug, trace, warn};
+use ring::digest::{Context, SHA256};
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::time::sleep;
+use std::path::Path;
+
+use crate::constants::Constants;
+
+impl MaxClient {
+    /**
+     * Ссылка на содержимое вложения: baseUrl фото (оригинал), лучшее качество
+     * видео через VIDEO_PLAY (83) или файл через FILE_DOWNLOAD (88)
+     */
+    pub async fn attachment_url(
+        &self,
+        chat_id: i64,
+        message_id: u64,
+        attach: &Attachment,
+    ) -> ClientResult<String> {
+        let url = match attach.kind {
+            AttachmentType::Photo => attach.base_url.clone(),
+            AttachmentType::Video => match attach.video_id {
+                Some(video_id) => self
+                    .get_video_by_id(chat_id, message_id, video_id)
+                    .await?
+                    .best_url()
+                    .map(str::to_string),
+                None => None,
+            },
+            AttachmentType::File => match attach.file_id {
+                Some(file_id) => Some(self.get_file_by_id(chat_id, message_id, file_id).await?.url),
+                None => None,
+            },
+            _ => None,
+        };
+
+        url.ok_or_else(|| DownloadError::NoUrl(format!("{:?} в сообщении {}", attach.kind, message_id)).into())
+    }
+
+    /**
+     * Скачивает вложение сообщения в файл. Размер из вложения (size) проверяется,
+     * если в options не указан свой
+     *
+     * for attach in &message.attaches {
+     *     client.download_attachment(chat_id, message.id, attach, dir.join(name), &DownloadOptions::new()).await?;
+     * }
+     */
+    pub async fn download_attachment(
+        &self,
+        chat_id: i64,
+        message_id: u64,
+        attach: &Attachment,
+        path: impl AsRef<Path>,
+        options: &DownloadOptions,
+    ) -> ClientResult<u64> {
+        let url = self.attachment_url(chat_id, message_id, attach).await?;
+
+        let mut options = options.clone();
+        if options.expected_size.is_none() && attach.kind == AttachmentType::File {
+            options.expected_size = attach.size;
+        }
+        self.download_to_path(&url, path, &options).await
+    }
+
+    /**
+     * Скачивает url в файл. Существующий файл перезаписывается, а с options.resume
+     * докачивается с его размера (Range). Возвращает итоговый размер
+     */
+    pub async fn download_to_path(
+        &self,
+        url: &str,
+        path: impl AsRef<Path>,
+        options: &DownloadOptions,
+    ) -> ClientResult<u64> {
+        let path = path.as_ref();
+        let mut hasher = options.sha256.as_ref().map(|_| Context::new(&SHA256));
+
+        let mut existing = match tokio::fs::metadata(path).await {
+            Ok(metadata) if options.resume => metadata.len(),
+            _ => 0,
+        };
+        if options.expected_size.is_some_and(|size| existing > size) {
+            existing = 0;
+        }
+
+        let mut file = if existing > 0 {
+            if let Some(hasher) = hasher.as_mut() {
+                hash_prefix(path, existing, hasher).await?;
+            }
+            debug!("Докачиваем {} с {} байт", path.display(), existing);
+            OpenOptions::new().append(true).open(path).await?
+        } else {
+            File::create(path).await?
+        };
+
+        self.download_from(url, &mut file, existing, hasher, options).await
+    }
+
+    /**
+     * Скачивает url в writer начиная с options.offset: запрос идет с Range,
+     * после обрыва скачивание продолжается с последнего записанного байта.
+     * Возвращает итоговый размер (вместе с offset)
+     */
+    pub async fn download<W>(
+        &self,
+        url: &str,
+        writer: &mut W,
+        options: &DownloadOptions,
+    ) -> ClientResult<u64>
+    where
+        W: AsyncWrite + Unpin + ?Sized,
+    {
+        if options.offset > 0 && options.sha256.is_some() {
+            return Err(DownloadError::ChecksumUnavailable.into());
+        }
+
+        let hasher = options.sha256.as_ref().map(|_| Context::new(&SHA256));
+        self.download_from(url, writer, options.offset, hasher, options).await
+    }
+
+    async fn download_from<W>(
+        &self,
+        url: &str,
+        writer: &mut W,
+        mut offset: u64,
+        mut hasher: Option<Context>,
+        options: &DownloadOptions,
+    ) -> ClientResult<u64>
+    where
+        W: AsyncWrite + Unpin + ?Sized,
+    {
+        let client = self.http_client().await?;
+        let mut total = options.expected_size;
+        let mut failures = 0;
+
+        loop {
+            let started = offset;
+            match fetch_range(&client, url, writer, &mut offset, &mut hasher, &mut total, options).await {
+                Ok(()) => break,
+                Err(Error::Download(e)) if is_retryable(&e) => {
+                    if offset > started {
+                        failures = 0;
+                    }
+                    if failures >= options.max_retries {
+                        return Err(DownloadError::Interrupted { offset, source: Box::new(e) }.into());
+                    }
+                    let delay = options.retry_delay.saturating_mul(2u32.saturating_pow(failures)).min(Constants::RETRY_MAX_DELAY);
+                    failures += 1;
+                    warn!("{}, продолжаем с {} байт через {:?}", e, offset, delay);
+                    sleep(delay).await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        writer.flush().await?;
+
+        if let Some(expected) = options.expected_size.or(total) {
+            if offset != expected {
+                return Err(DownloadError::SizeMismatch { expected, actual: offset }.into());
+            }
+        }
+
+        if let (Some(expected), Some(hasher)) = (&options.sha256, hasher) {
+            let actual = hex(hasher.finish().as_ref());
+            if *expected != actual {
+                return Err(DownloadError::ChecksumMismatch { expected: expected.clone(), actual }.into());
+            }
+        }
+
+        Ok(offset)
+    }
+}
+
+/**
+ * Один HTTP-запрос с Range: bytes=offset-. Сервер может проигнорировать Range (200),
+ * тогда уже записанное начало пропускается. Ответ короче заявленного считается обрывом
+ */
+async fn fetch_range<W>(
+    client: &reqwest::Client,
+    url: &str,
+    writer: &mut W,
+    offset: &mut u64,
+    hasher: &mut Option<Context>,
+    total: &mut Option<u64>,
+    options: &DownloadOptions,
+) -> ClientResult<()>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    let mut request = client.get(url);
+    if *offset > 0 {
+        request = request.header(reqwest::header::RANGE, format!("bytes={}-", offset));
+    }
+    let response = request.send().await.map_err(|e| DownloadError::Request(e.to_string()))?;
+
+    let content_range = response
+        .headers()
+        .get(reqwest::header::CONTENT_RANGE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+
+    let mut skip = match response.status().as_u16() {
+        200 => {
+            if let Some(length) = response.content_length() {
+                total.get_or_insert(length);
+            }
+            *offset
+        }
+        206 => {
+            let (start, size) = content_range.as_deref().and_then(parse_content_range).unwrap_or((*offset, None));
+            if start > *offset {
+                return Err(DownloadError::Status { status: 206 }.into());
+            }
+            if let Some(size) = size {
+                total.get_or_insert(size);
+            }
+            *offset - start
+        }
+        /* Range за концом файла: все уже скачано */
+        416 if *offset > 0 => {
+            if let Some(size) = content_range.as_deref().and_then(parse_content_range).and_then(|(_, size)| size) {
+                total.get_or_insert(size);
+            }
+            return Ok(());
+        }
+        status => return Err(DownloadError::Status { status }.into()),
+    };
+
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| DownloadError::Request(e.to_string()))?;
+        let mut data = &chunk[..];
+        if skip > 0 {
+            let n = skip.min(data.len() as u64) as usize;
+            data = &data[n..];
+            skip -= n as u64;
+        }
+        if data.is_empty() {
+            continue;
+        }
+
+        writer.write_all(data).await?;
+        if let Some(hasher) = hasher.as_mut() {
+            hasher.update(data);
+        }
+        *offset += data.len() as u64;
+        trace!("Скачано {} байт", offset);
+        options.report(*offset, *total);
+    }
+
+    match *total {
+        Some(total) if *offset < total => {
+            Err(DownloadError::Request(format!("ответ оборвался на {} из {} байт", offset, total)).into())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn is_retryable(error: &DownloadError) -> bool {
+    match error {
+        DownloadError::Request(_) => true,
+        DownloadError::Status { status } => *status >= 500 || *status == 408 || *status == 429,
+        _ => false,
+    }
+}
+
+/* Content-Range вида "bytes 100-199/1000" (в 416 звездочка вместо диапазона): (начало, размер) */
+fn parse_content_range(value: &str) -> Option<(u64, Option<u64>)> {
+    let value = value.strip_prefix("bytes ")?;
+    let (range, size) = value.split_once('/')?;
+    let start = range.split_once('-').and_then(|(start, _)| start.parse().ok()).unwrap_or(0);
+    Some((start, size.parse().ok()))
+}
+
+async fn hash_prefix(path: &Path, len: u64, hasher: &mut Context) -> ClientResult<()> {
+    let mut file = File::open(path).await?.take(len);
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buffer).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        hasher.update(&buffer[..n]);
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -1,15 +1,19 @@
-use crate::{errors::ClientResult, MaxClient};
-use crate::models::Response;
+use crate::{errors::{ClientResult, Error, UploadError}, MaxClient};
+use crate::constants::Constants;
+use crate::media::{self, PhotoData};
+use crate::events::Event;
+use crate::models::{
+    value_as_id, Message, MessageBuilder, Opcode, UploadOptions, UploadSlot, UploadedFile, UploadedPhoto, UploadedVideo,
+};
+use log::{debug, trace, warn};
 use serde_json::{json, Value};
 use tokio::{fs::File};
-use tokio::io::AsyncReadExt;
-use tokio_util::io::ReaderStream;
-use reqwest::{Client, Body, multipart};
-use futures_util::StreamExt;
-use std::time::Duration;
-use std::fs;
-
-const CHUNK_SIZE: usize = 6 * 1024 * 1024;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::sync::broadcast;
+use tokio::time::{sleep, timeout_at, Instant};
+use reqwest::multipart;
+use std::io::SeekFrom;
+use std::path::Path;
 
 impl MaxClient {
     /*
@@ -19,200 +23,429 @@ impl MaxClient {
         &self,
         count: i64,
         profile: bool,
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<UploadSlot> {
         let payload = json!({
             "count": count,
             "profile": profile,
         });
-        self.send_and_wait(80, payload, 0).await
+        self.send_and_wait(Opcode::PhotoUpload, payload, 0).await?.parse()
     }
 
     pub async fn get_video_upload(
         &self,
         count: i64,
         profile: bool,
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<Vec<UploadSlot>> {
         let payload = json!({
             "count": count,
             "profile": profile,
         });
-        self.send_and_wait(82, payload, 0).await
+        self.send_and_wait(Opcode::VideoUpload, payload, 0).await?.take("info")
     }
 
     pub async fn get_file_upload(
         &self,
         count: i64,
         profile: bool,
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<Vec<UploadSlot>> {
         let payload = json!({
             "count": count,
             "profile": profile,
         });
-        self.send_and_wait(87, payload, 0).await
+        self.send_and_wait(Opcode::FileUpload, payload, 0).await?.take("info")
     }
 
+    /**
+     * Загружает фото. Без mime тип определяется по содержимому (JPEG, PNG, GIF, WebP,
+     * HEIC, ...), затем по расширению. Перед загрузкой применяется photo_preprocessor
+     * из настроек клиента. Сервер принимает только media::UPLOAD_PHOTO_TYPES, остальное
+     * без перекодирования в preprocessor отклоняется с UnsupportedType
+     */
     pub async fn upload_photo(
         &self,
         upload_url: String,
         mut file: File,
         file_name: String,
         mime: Option<String>,
-    ) -> Value {
-        let ext = file_name
-        .split('.')
-        .last()
-        .map(|e| e.to_lowercase());
-
-        let mime = if let Some(m) = mime {
-            m
-        } else if let Some(ext) = ext {
-            match ext.as_str() {
-                "jpg" | "jpeg" => "image/jpeg".to_string(),
-                "png" => "image/png".to_string(),
-                _ => {
-                    return json!({ "error": "Unsupported file extension" });
-                }
-            }
-        } else {
-            return json!({ "error": "Failed to determine MIME type" });
-        };
-
+    ) -> ClientResult<UploadedPhoto> {
         let mut file_bytes = Vec::new();
-        if let Err(_) = file.read_to_end(&mut file_bytes).await {
-            return json!({ "error": "Failed to read photo" });
+        file.read_to_end(&mut file_bytes).await?;
+        if file_bytes.is_empty() {
+            return Err(UploadError::EmptyFile.into());
         }
 
-        let ext = mime.split('/').last().unwrap_or("jpg");
-
-        let form = multipart::Form::new().part(
-            "file",
-            multipart::Part::bytes(file_bytes)
-            .file_name(format!("image.{}", ext))
-            .mime_str(&mime)
-            .unwrap(),
-        );
-
-        let client = reqwest::Client::new();
-        let response = match client.post(upload_url).multipart(form).send().await {
-            Ok(r) => r,
-            Err(e) => return json!({ "error": format!("Upload request failed: {}", e) }),
+        let mime = match mime {
+            Some(m) => m,
+            None => match media::detect_mime(&file_bytes, &file_name) {
+                Some(m) if media::is_image(m) => m.to_string(),
+                _ => return Err(UploadError::UnsupportedType(file_name).into()),
+            },
         };
 
-        if !response.status().is_success() {
-            return json!({ "error": format!("Upload failed with status {}", response.status()) });
+        let photo = self.preprocess_photo(PhotoData { bytes: file_bytes, mime }).await?;
+        if !media::is_uploadable_photo(&photo.mime) {
+            return Err(UploadError::UnsupportedType(photo.mime).into());
         }
+        let ext = media::extension_for(&photo.mime);
 
-        let json_resp: serde_json::Value = match response.json().await {
-            Ok(j) => j,
-            Err(e) => return json!({ "error": format!("Failed to parse JSON: {}", e) }),
-        };
+        let part = multipart::Part::bytes(photo.bytes)
+            .file_name(format!("image.{}", ext))
+            .mime_str(&photo.mime)
+            .map_err(|_| UploadError::UnsupportedType(photo.mime.clone()))?;
+        let form = multipart::Form::new().part("file", part);
 
-        let token = match json_resp.get("photos")
+        let client = self.http_client().await?;
+        let response = client
+            .post(upload_url)
+            .multipart(form)
+            .send()
+            .await
+            .map_err(|e| UploadError::Request(e.to_string()))?;
+
+        let body = check_status(response).await?;
+        let json_resp: Value = serde_json::from_str(&body).map_err(|e| UploadError::Parse(e.to_string()))?;
+
+        let token = json_resp.get("photos")
         .and_then(|v| v.as_object())
         .and_then(|obj| obj.values().next())
         .and_then(|photo| photo.get("token"))
-        .and_then(|t| t.as_str()) {
-            Some(t) => t,
-            None => return json!({ "error": "Token not found in response" }),
-        };
+        .and_then(|t| t.as_str())
+        .ok_or(UploadError::MissingToken)?;
 
-        json!({ "photoToken": token.to_string() })
+        Ok(UploadedPhoto { photo_token: token.to_string() })
     }
 
     pub async fn upload_video(
         &self,
         upload_url: String,
-        video_id: u64,
+        video_id: i64,
         token: String,
-        mut file: File,
+        file: File,
         file_name: String,
-    ) -> Value {
-        let mut file_bytes = Vec::new();
-        if let Err(e) = file.read_to_end(&mut file_bytes).await {
-            return json!({ "error": format!("Failed to read video: {}", e) });
+    ) -> ClientResult<UploadedVideo> {
+        self.upload_video_with(upload_url, video_id, token, file, file_name, &UploadOptions::default()).await
+    }
+
+    /**
+     * upload_video кусками по options.chunk_size с прогрессом и продолжением с options.offset
+     */
+    pub async fn upload_video_with(
+        &self,
+        upload_url: String,
+        video_id: i64,
+        token: String,
+        file: File,
+        file_name: String,
+        options: &UploadOptions,
+    ) -> ClientResult<UploadedVideo> {
+        self.upload_chunks(&upload_url, file, &file_name, options).await?;
+        Ok(UploadedVideo { video_id, token })
+    }
+
+    pub async fn upload_file(
+        &self,
+        upload_url: String,
+        file_id: i64,
+        file: File,
+        file_name: String,
+    ) -> ClientResult<UploadedFile> {
+        self.upload_file_with(upload_url, file_id, file, file_name, &UploadOptions::default()).await
+    }
+
+    pub async fn upload_file_with(
+        &self,
+        upload_url: String,
+        file_id: i64,
+        file: File,
+        file_name: String,
+        options: &UploadOptions,
+    ) -> ClientResult<UploadedFile> {
+        self.upload_chunks(&upload_url, file, &file_name, options).await?;
+        Ok(UploadedFile { file_id })
+    }
+
+    /**
+     * Отправляет файл кусками с Content-Range "start-end/total". Неудачный кусок
+     * повторяется с последнего подтвержденного смещения, после max_retries подряд
+     * возвращается UploadError::Interrupted с этим смещением
+     */
+    async fn upload_chunks(
+        &self,
+        upload_url: &str,
+        mut file: File,
+        file_name: &str,
+        options: &UploadOptions,
+    ) -> ClientResult<()> {
+        let total = file.metadata().await?.len();
+        if total == 0 {
+            return Err(UploadError::EmptyFile.into());
         }
 
-        let file_size = file_bytes.len();
-        if file_size == 0 {
-            return json!({ "error": "Empty file" });
+        let client = self.http_client().await?;
+        let chunk_size = options.chunk_size.max(1) as u64;
+        let mut offset = options.offset.min(total);
+        let mut failures = 0;
+
+        while offset < total {
+            let len = chunk_size.min(total - offset);
+            let mut chunk = vec![0u8; len as usize];
+            file.seek(SeekFrom::Start(offset)).await?;
+            file.read_exact(&mut chunk).await?;
+
+            let result = client
+                .post(upload_url)
+                .timeout(Constants::UPLOAD_CHUNK_TIMEOUT)
+                .header("Content-Disposition", format!("attachment; filename={}", file_name))
+                .header("Content-Range", format!("{}-{}/{}", offset, offset + len - 1, total))
+                .header("Content-Length", len)
+                .header("Connection", "keep-alive")
+                .body(chunk)
+                .send()
+                .await;
+
+            let error = match result {
+                Ok(response) => match check_status(response).await {
+                    Ok(body) => {
+                        let accepted = acknowledged(&body).unwrap_or(offset + len).clamp(offset, total);
+                        /* Подтверждение без продвижения - такая же неудача, иначе цикл не закончится */
+                        if accepted == offset {
+                            UploadError::Stalled(body)
+                        } else {
+                            offset = accepted;
+                            failures = 0;
+                            trace!("Загружено {} из {} байт", offset, total);
+                            options.report(offset, total);
+                            continue;
+                        }
+                    }
+                    Err(e) => e,
+                },
+                Err(e) => UploadError::Request(e.to_string()),
+            };
+
+            if failures >= options.max_retries {
+                return Err(UploadError::Interrupted { offset, source: Box::new(error) }.into());
+            }
+            let delay = options.retry_delay.saturating_mul(2u32.saturating_pow(failures)).min(Constants::RETRY_MAX_DELAY);
+            failures += 1;
+            warn!("{}, повтор с {} байт через {:?}", error, offset, delay);
+            sleep(delay).await;
         }
 
-        let client = match Client::builder()
-        .timeout(Duration::from_secs(600))
-        .build()
-        {
-            Ok(c) => c,
-            Err(e) => return json!({ "error": format!("Failed to build client: {}", e) }),
-        };
+        Ok(())
+    }
 
-        let response = match client.post(upload_url)
-        .header("Content-Disposition", format!("attachment; filename={}", file_name))
-        .header("Content-Range", format!("0-{}/{}", file_size - 1, file_size))
-        .header("Content-Length", file_size)
-        .header("Connection", "keep-alive")
-        .body(file_bytes)
-        .send()
-        .await
-        {
-            Ok(r) => r,
-            Err(e) => return json!({ "error": format!("Upload failed: {}", e) }),
+    async fn preprocess_photo(&self, photo: PhotoData) -> ClientResult<PhotoData> {
+        let Some(preprocessor) = self.state.lock().await.config.photo_preprocessor.clone() else {
+            return Ok(photo);
         };
 
-        if !response.status().is_success() {
-            return json!({ "error": format!("Upload failed with status {}", response.status()) });
-        }
+        tokio::task::spawn_blocking(move || preprocessor.process(photo))
+            .await
+            .map_err(|e| Error::Other(format!("Предобработка фото: {}", e)))?
+    }
+
+    /**
+     * Загружает фото и отправляет его вложением к message (текст и форматирование сохраняются)
+     *
+     * client.send_photo(MessageBuilder::new(chat_id).text("Смотри"), "cat.jpg").await?;
+     */
+    pub async fn send_photo(
+        &self,
+        message: MessageBuilder,
+        path: impl AsRef<Path>,
+    ) -> ClientResult<Message> {
+        let (file, file_name) = open_upload(path.as_ref()).await?;
+        let slot = self.get_photo_upload(1, false).await?;
+
+        let photo = self.upload_photo(slot.url, file, file_name, None).await?;
 
-        json!({
-            "videoId": video_id,
-            "token": token
-        })
+        self.send(message.attach(photo.into())).await
     }
 
-    pub async fn upload_file(
+    /**
+     * Загружает видео и отправляет его вложением. Пока сервер обрабатывает видео,
+     * MSG_SEND отвечает attachment.not.ready - отправка повторяется после NOTIF_ATTACH
+     * или паузы, но не дольше Constants::ATTACH_PROCESSING_TIMEOUT
+     */
+    pub async fn send_video(
         &self,
-        upload_url: String,
-        file_id: u64,
-        mut file: File,
-        file_name: String,
-    ) -> Value {
-        let file_size = match file.metadata().await {
-            Ok(m) => m.len(),
-            Err(e) => return json!({ "error": format!("Failed to get metadata: {}", e) }),
+        message: MessageBuilder,
+        path: impl AsRef<Path>,
+    ) -> ClientResult<Message> {
+        self.send_video_with(message, path, &UploadOptions::default()).await
+    }
+
+    pub async fn send_video_with(
+        &self,
+        message: MessageBuilder,
+        path: impl AsRef<Path>,
+        options: &UploadOptions,
+    ) -> ClientResult<Message> {
+        let (file, file_name) = open_upload(path.as_ref()).await?;
+        let slot = first_slot(self.get_video_upload(1, false).await?, Opcode::VideoUpload)?;
+        let (video_id, token) = match (slot.id, slot.token) {
+            (Some(id), Some(token)) => (id, token),
+            _ => return Err(Error::Protocol("VIDEO_UPLOAD без videoId или token".to_string())),
         };
 
-        if file_size == 0 {
-            return json!({ "error": "Empty file" });
-        }
+        /* Подписка до загрузки, чтобы не пропустить NOTIF_ATTACH */
+        let events = self.subscribe();
+        let video = self.upload_video_with(slot.url, video_id, token, file, file_name, options).await?;
 
-        let stream = ReaderStream::new(file);
-        let body = Body::wrap_stream(stream);
+        self.send_when_ready(message.attach(video.into()), video_id, events).await
+    }
 
-        let client = match Client::builder().build() {
-            Ok(c) => c,
-            Err(e) => return json!({ "error": format!("Client build failed: {}", e) }),
+    /**
+     * Загружает файл и отправляет его вложением, дожидаясь обработки как send_video
+     */
+    pub async fn send_file(
+        &self,
+        message: MessageBuilder,
+        path: impl AsRef<Path>,
+    ) -> ClientResult<Message> {
+        self.send_file_with(message, path, &UploadOptions::default()).await
+    }
+
+    pub async fn send_file_with(
+        &self,
+        message: MessageBuilder,
+        path: impl AsRef<Path>,
+        options: &UploadOptions,
+    ) -> ClientResult<Message> {
+        let (file, file_name) = open_upload(path.as_ref()).await?;
+        let slot = first_slot(self.get_file_upload(1, false).await?, Opcode::FileUpload)?;
+        let Some(file_id) = slot.id else {
+            return Err(Error::Protocol("FILE_UPLOAD без fileId".to_string()));
         };
 
-        let response = match client
-        .post(upload_url)
-        .header("Content-Disposition", format!("attachment; filename={}", file_name))
-        .header("Content-Length", file_size)
-        .header("Content-Range", format!("0-{}/{}", file_size - 1, file_size))
-        .body(body)
-        .send()
-        .await
-        {
-            Ok(r) => r,
-            Err(e) => return json!({ "error": format!("Upload failed: {}", e) }),
+        let events = self.subscribe();
+        let uploaded = self.upload_file_with(slot.url, file_id, file, file_name, options).await?;
+
+        self.send_when_ready(message.attach(uploaded.into()), file_id, events).await
+    }
+
+    /**
+     * Отправляет файл как фото, видео или документ в зависимости от содержимого.
+     * Фото, которые сервер не принимает (HEIC, AVIF, ...), уходят фото, только если
+     * photo_preprocessor перекодировал их, иначе - документом
+     */
+    pub async fn send_media(
+        &self,
+        message: MessageBuilder,
+        path: impl AsRef<Path>,
+    ) -> ClientResult<Message> {
+        let path = path.as_ref();
+        let mut head = Vec::with_capacity(media::SNIFF_LEN);
+        File::open(path).await?.take(media::SNIFF_LEN as u64).read_to_end(&mut head).await?;
+
+        let file_name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+        match media::detect_mime(&head, &file_name) {
+            Some(mime) if media::is_uploadable_photo(mime) => self.send_photo(message, path).await,
+            Some(mime) if media::is_image(mime) => {
+                if self.state.lock().await.config.photo_preprocessor.is_none() {
+                    return self.send_file(message, path).await;
+                }
+                match self.send_photo(message.clone(), path).await {
+                    Err(Error::Upload(UploadError::UnsupportedType(e))) => {
+                        debug!("{} нельзя отправить фото ({}), отправляется документом", mime, e);
+                        self.send_file(message, path).await
+                    }
+                    result => result,
+                }
+            }
+            Some(mime) if media::is_video(mime) => self.send_video(message, path).await,
+            _ => self.send_file(message, path).await,
+        }
+    }
+
+    async fn send_when_ready(
+        &self,
+        message: MessageBuilder,
+        attach_id: i64,
+        mut events: broadcast::Receiver<Event>,
+    ) -> ClientResult<Message> {
+        let deadline = Instant::now() + Constants::ATTACH_PROCESSING_TIMEOUT;
+        /* Один cid на все попытки, чтобы сервер не создал дубликат */
+        let message = match message.cid {
+            Some(_) => message,
+            None => message.cid(self.outbox.next_cid()),
         };
 
-        if !response.status().is_success() {
-            return json!({
-                "error": format!("Upload failed with status {}", response.status())
-            });
+        loop {
+            match self.send(message.clone()).await {
+                Err(Error::Api(e)) if e.code == ATTACHMENT_NOT_READY && Instant::now() < deadline => {
+                    debug!("Вложение {} еще обрабатывается", attach_id);
+                    let ready = wait_attach(&mut events, attach_id);
+                    let waited = timeout_at(deadline, async {
+                        tokio::select! {
+                            result = ready => result,
+                            _ = sleep(Constants::ATTACH_RETRY_DELAY) => Ok(()),
+                        }
+                    })
+                    .await;
+                    if let Ok(Err(e)) = waited {
+                        return Err(e);
+                    }
+                }
+                result => return result,
+            }
         }
+    }
+}
+
+const ATTACHMENT_NOT_READY: &str = "attachment.not.ready";
+
+async fn open_upload(path: &Path) -> ClientResult<(File, String)> {
+    let file = File::open(path).await?;
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "file".to_string());
+    Ok((file, file_name))
+}
 
-        json!({
-            "fileId": file_id
-        })
+fn first_slot(slots: Vec<UploadSlot>, opcode: Opcode) -> ClientResult<UploadSlot> {
+    slots
+        .into_iter()
+        .next()
+        .ok_or_else(|| Error::Protocol(format!("{} вернул пустой список слотов", opcode.name())))
+}
+
+/* Сервер может ответить принятым диапазоном "start-end/total" */
+fn acknowledged(body: &str) -> Option<u64> {
+    let (range, _) = body.trim().split_once('/')?;
+    let (_, end) = range.split_once('-')?;
+    end.parse::<u64>().ok().map(|end| end + 1)
+}
+
+/* Тело успешного ответа сервера загрузки или UploadError::Status */
+async fn check_status(response: reqwest::Response) -> Result<String, UploadError> {
+    let status = response.status();
+    let body = response.text().await.map_err(|e| UploadError::Request(e.to_string()))?;
+    if !status.is_success() {
+        return Err(UploadError::Status {
+            status: status.as_u16(),
+            body: body.chars().take(512).collect(),
+        });
+    }
+    Ok(body)
+}
+
+/* NOTIF_ATTACH с videoId или fileId загруженного вложения. Ошибка, если канал событий закрыт */
+async fn wait_attach(events: &mut broadcast::Receiver<Event>, attach_id: i64) -> ClientResult<()> {
+    loop {
+        match events.recv().await {
+            Ok(Event::Raw(push)) if push.opcode == u16::from(Opcode::NotifAttach) => {
+                let id = push.payload.get("videoId").or_else(|| push.payload.get("fileId"));
+                if id.and_then(value_as_id) == Some(attach_id) {
+                    return Ok(());
+                }
+            }
+            Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+            Err(broadcast::error::RecvError::Closed) => {
+                return Err(Error::ConnectionClosed("канал событий закрыт".into()));
+            }
+        }
     }
 }
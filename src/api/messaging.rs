@@ -1,6 +1,6 @@
-use crate::{errors::ClientResult, MaxClient};
-use crate::models::{Response, FetchHistoryOptions};
-use serde_json::{json, Map};
+use crate::{errors::ClientResult, pending::PendingResponse, MaxClient};
+use crate::models::{FetchHistoryOptions, FileInfo, Message, MessageBuilder, ReactionInfo, VideoInfo, Opcode};
+use serde_json::{json, Map, Value};
 use std::collections::HashMap;
 use chrono::Utc;
 
@@ -10,42 +10,18 @@ impl MaxClient {
         chat_id: i64,
         text: String,
         args: Option<HashMap<String, serde_json::Value>>,
-    ) -> ClientResult<Response> {
-        let args_map = args.unwrap_or_default();
-
-        let mut message = Map::new();
-
-        message.insert("text".into(), json!(text));
-        message.insert("cid".into(), json!(Utc::now().timestamp_millis()));
-        message.insert(
-            "elements".into(),
-            args_map.get("elements").cloned().unwrap_or(json!([])),
-        );
-        message.insert(
-            "attaches".into(),
-            args_map.get("attaches").cloned().unwrap_or(json!([])),
-        );
-
-        if let Some(link) = args_map.get("replyTo").and_then(|id| {
-            id.as_str()
-            .and_then(|s| s.parse::<u64>().ok())
-            .map(|num| {
-                json!({
-                    "type": "REPLY",
-                    "messageId": num
-                })
-            })
-        }) {
-            message.insert("link".into(), link);
-        }
+    ) -> ClientResult<Message> {
+        let payload = message_payload(chat_id, self.outbox.next_cid(), text, args);
 
-        let payload = json!({
-            "chatId": chat_id,
-            "message": message,
-            "notify": args_map.get("notify").cloned().unwrap_or(json!(true)),
-        });
+        self.send_and_wait(Opcode::MsgSend, payload, 0).await?.take("message")
+    }
 
-        self.send_and_wait(64, payload, 0).await
+    /**
+     * Отправка сообщения, собранного через MessageBuilder
+     */
+    pub async fn send(&self, message: MessageBuilder) -> ClientResult<Message> {
+        let cid = message.cid.unwrap_or_else(|| self.outbox.next_cid());
+        self.send_and_wait(Opcode::MsgSend, message.payload(cid), 0).await?.take("message")
     }
     
     
@@ -54,7 +30,7 @@ impl MaxClient {
         chat_id: i64,
         message_id: u64,
         reaction: String
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<ReactionInfo> {
         let payload = json!({
             "chatId": chat_id,
             "messageId": message_id,
@@ -63,33 +39,39 @@ impl MaxClient {
                 "id": reaction,
             }
         });
-        self.send_and_wait(178, payload, 0).await
+        self.send_and_wait(Opcode::MsgReaction, payload, 0).await?.take("reactionInfo")
     }
     
     pub async fn remove_reaction(
         &self,
         chat_id: i64,
         message_id: u64,
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<ReactionInfo> {
         let payload = json!({
             "chatId": chat_id,
             "messageId": message_id,
         });
-        self.send_and_wait(179, payload, 0).await
+        self.send_and_wait(Opcode::MsgCancelReaction, payload, 0).await?.take("reactionInfo")
     }
 
     pub async fn read_message(
         &self,
         chat_id: i64,
         message_id: u64,
-    ) -> ClientResult<Response> {
-        let payload = json!({
-            "type": "READ_MESSAGE",
-            "chatId": chat_id,
-            "messageId": message_id,
-            "mark": Utc::now().timestamp_millis() as u64,
-        });
-        self.send_and_wait(50, payload, 0).await
+    ) -> ClientResult<()> {
+        self.send_and_wait(Opcode::ChatMark, read_mark_payload(chat_id, message_id), 0).await?;
+        Ok(())
+    }
+
+    /**
+     * Отметка о прочтении без ожидания ответа, для пачек отметок
+     */
+    pub async fn read_message_no_wait(
+        &self,
+        chat_id: i64,
+        message_id: u64,
+    ) -> ClientResult<PendingResponse> {
+        self.send_no_wait(Opcode::ChatMark, read_mark_payload(chat_id, message_id), 0).await
     }
 
     pub async fn pin_message(
@@ -97,13 +79,14 @@ impl MaxClient {
         chat_id: i64,
         message_id: u64,
         notify_pin: bool,
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<()> {
         let payload = json!({
             "chatId": chat_id,
             "notifyPin": notify_pin,
             "pinMessageId": message_id,
         });
-        self.send_and_wait(55, payload, 0).await
+        self.send_and_wait(Opcode::ChatUpdate, payload, 0).await?;
+        Ok(())
     }
 
     pub async fn delete_message(
@@ -111,7 +94,7 @@ impl MaxClient {
         chat_id: i64,
         message_id: u64,
         for_me: bool,
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<()> {
         self.delete_messages(chat_id, vec![message_id], for_me).await
     }
 
@@ -120,14 +103,15 @@ impl MaxClient {
         chat_id: i64,
         message_ids: Vec<u64>,
         for_me: bool,
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<()> {
         let payload = json!({
             "chatId": chat_id,
             "messageIds": message_ids,
             "forMe": for_me,
         });
 
-        self.send_and_wait(66, payload, 0).await
+        self.send_and_wait(Opcode::MsgDelete, payload, 0).await?;
+        Ok(())
     }
 
     pub async fn edit_message(
@@ -135,7 +119,7 @@ impl MaxClient {
         chat_id: i64,
         message_id: u64,
         text: String
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<Message> {
         let payload = json!({
             "chatId": chat_id,
             "messageId": message_id,
@@ -143,14 +127,14 @@ impl MaxClient {
             "elements": [],
             "attaches": [],
         });
-        self.send_and_wait(67, payload, 0).await
+        self.send_and_wait(Opcode::MsgEdit, payload, 0).await?.take("message")
     }
 
     pub async fn fetch_history(
         &self,
         chat_id: i64,
         opts: Option<FetchHistoryOptions>,
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<Vec<Message>> {
         let opts = opts.unwrap_or_default();
 
         let payload = serde_json::json!({
@@ -166,7 +150,7 @@ impl MaxClient {
             "interactive": opts.interactive,
         });
 
-        self.send_and_wait(49, payload, 0).await
+        self.send_and_wait(Opcode::ChatHistory, payload, 0).await?.take("messages")
     }
 
     pub async fn get_video_by_id(
@@ -174,13 +158,13 @@ impl MaxClient {
         chat_id: i64,
         message_id: u64,
         video_id: i64,
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<VideoInfo> {
         let payload = json!({
             "chatId": chat_id,
             "messageId": message_id,
             "videoId": video_id
         });
-        self.send_and_wait(83, payload, 0).await
+        self.send_and_wait(Opcode::VideoPlay, payload, 0).await?.parse()
     }
 
     pub async fn get_file_by_id(
@@ -188,16 +172,63 @@ impl MaxClient {
         chat_id: i64,
         message_id: u64,
         file_id: i64,
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<FileInfo> {
         let payload = json!({
             "chatId": chat_id,
             "messageId": message_id,
             "fileId": file_id
         });
-        self.send_and_wait(88, payload, 0).await
+        self.send_and_wait(Opcode::FileDownload, payload, 0).await?.parse()
     }
     
-    /* TODO Upload image, video, file */
-    
-    
+}
+
+fn read_mark_payload(chat_id: i64, message_id: u64) -> Value {
+    json!({
+        "type": "READ_MESSAGE",
+        "chatId": chat_id,
+        "messageId": message_id,
+        "mark": Utc::now().timestamp_millis() as u64,
+    })
+}
+
+pub(crate) fn message_payload(
+    chat_id: i64,
+    cid: i64,
+    text: String,
+    args: Option<HashMap<String, Value>>,
+) -> Value {
+    let args_map = args.unwrap_or_default();
+
+    let mut message = Map::new();
+
+    message.insert("text".into(), json!(text));
+    message.insert("cid".into(), json!(cid));
+    message.insert(
+        "elements".into(),
+        args_map.get("elements").cloned().unwrap_or(json!([])),
+    );
+    message.insert(
+        "attaches".into(),
+        args_map.get("attaches").cloned().unwrap_or(json!([])),
+    );
+
+    if let Some(link) = args_map.get("replyTo").and_then(|id| {
+        id.as_str()
+        .and_then(|s| s.parse::<u64>().ok())
+        .map(|num| {
+            json!({
+                "type": "REPLY",
+                "messageId": num
+            })
+        })
+    }) {
+        message.insert("link".into(), link);
+    }
+
+    json!({
+        "chatId": chat_id,
+        "message": message,
+        "notify": args_map.get("notify").cloned().unwrap_or(json!(true)),
+    })
 }
@@ -1,9 +1,11 @@
 pub mod auth;
 pub mod messaging;
+pub mod outbox;
 pub mod telemetry;
 pub mod contacts;
 pub mod chats;
 pub mod files;
+pub mod downloads;
 pub mod calls;
 pub mod user;
 pub mod channels;
@@ -0,0 +1,66 @@
+use crate::api::messaging::message_payload;
+use crate::outbox::{self, OutboxEntry, OutboxStatus};
+use crate::models::MessageBuilder;
+use crate::MaxClient;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+impl MaxClient {
+    /**
+     * Ставит сообщение в очередь исходящих и сразу возвращает его cid.
+     * Сообщение уйдет после подключения и sync(), статус приходит событиями Event::Outbox
+     */
+    pub fn queue_message(
+        &self,
+        chat_id: i64,
+        text: String,
+        args: Option<HashMap<String, Value>>,
+    ) -> i64 {
+        let cid = self.outbox.next_cid();
+        self.queue_payload(cid, chat_id, message_payload(chat_id, cid, text, args));
+        cid
+    }
+
+    /* queue_message для сообщения из MessageBuilder */
+    pub fn queue(&self, message: MessageBuilder) -> i64 {
+        let cid = message.cid.unwrap_or_else(|| self.outbox.next_cid());
+        self.queue_payload(cid, message.chat_id(), message.payload(cid));
+        cid
+    }
+
+    pub(crate) fn queue_payload(&self, cid: i64, chat_id: i64, payload: Value) {
+        self.outbox.push(cid, chat_id, payload);
+    }
+
+    /* Запускает обработчик очереди, вызывается из connect внутри рантайма */
+    pub(crate) fn start_outbox(&self) {
+        if self.outbox.start_worker() {
+            tokio::spawn(outbox::run(
+                self.outbox.clone(),
+                Arc::downgrade(&self.state),
+                self.event_tx.clone(),
+            ));
+        }
+    }
+
+    /* Недоставленные сообщения в порядке отправки */
+    pub fn outbox(&self) -> Vec<OutboxEntry> {
+        self.outbox.entries()
+    }
+
+    /* Статус сообщения в очереди, None - доставлено или неизвестный cid */
+    pub fn outbox_status(&self, cid: i64) -> Option<OutboxStatus> {
+        self.outbox.status(cid)
+    }
+
+    /* Повторная отправка сообщения в статусе Failed */
+    pub fn retry_message(&self, cid: i64) -> bool {
+        self.outbox.retry(cid)
+    }
+
+    /* Убирает из очереди сообщение, которое сейчас не отправляется */
+    pub fn cancel_message(&self, cid: i64) -> bool {
+        self.outbox.remove(cid)
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{
-    navigation, MaxClient,
+    models::Opcode, navigation, MaxClient,
 };
 use chrono::Utc;
 use log::{debug, error, info, warn};
@@ -47,13 +47,10 @@ impl MaxClient {
             }
         };
 
-        match self.send_and_wait(5, payload_json, 0).await {
-            Ok(data) => {
-                if let Some(error) = data.payload.get("error") {
-                    error!("API телеметрии вернуло ошибку: {}", error);
-                } else {
-                    debug!("Ответ на телеметрию: {:?}", data);
-                }
+        /* Ответ на телеметрию не нужен: handle сразу сбрасывается */
+        match self.send_no_wait(Opcode::Log, payload_json, 0).await {
+            Ok(pending) => {
+                debug!("Событие телеметрии отправлено, seq: {}", pending.seq());
             }
             Err(e) => {
                 warn!("Ошибка отправки события телеметрии: {}", e);
@@ -64,7 +61,7 @@ impl MaxClient {
     async fn send_cold_start_internal(&self) {
         let mut state = self.state.lock().await;
 
-        let Some(user_id) = state.user_id.clone() else {
+        let Some(user_id) = state.user_id else {
             error!("Не могу отправить COLD_START, user_id не установлен");
             return;
         };
@@ -81,7 +78,7 @@ impl MaxClient {
             event: "COLD_START",
             time: Utc::now().timestamp_millis(),
             r#type: "NAV".to_string(),
-            user_id: user_id,
+            user_id,
             params,
         };
 
@@ -96,12 +93,12 @@ impl MaxClient {
         let (payload, screen_to_name) = {
             let mut state = self.state.lock().await;
 
-            let Some(user_id) = state.user_id.clone() else {
+            let Some(user_id) = state.user_id else {
                 error!("Не могу отправить NAV, user_id не установлен");
                 return;
             };
 
-            let session_id = state.session_id.clone();
+            let session_id = state.session_id;
             let screen_from_name = state.current_screen.clone();
 
             state.action_id += 1;
@@ -125,7 +122,7 @@ impl MaxClient {
                 event: "NAV",
                 r#type: "NAV".to_string(),
                 time: Utc::now().timestamp_millis(),
-                user_id: user_id,
+                user_id,
                 params,
             };
             
@@ -147,7 +144,7 @@ impl MaxClient {
         ];
         let weights: [f64; 5] = [0.05, 0.10, 0.15, 0.20, 0.50];
         
-        let dist = WeightedIndex::new(&weights).expect("Неверные веса для get_random_sleep_time");
+        let dist = WeightedIndex::new(weights).expect("Неверные веса для get_random_sleep_time");
         let mut rng = rand::thread_rng();
         
         let (low, high) = sleep_options[dist.sample(&mut rng)];
@@ -161,7 +158,7 @@ impl MaxClient {
      *  Эту функцию нужно вызвать ОДИН РАЗ после успешного логина
      */
     pub async fn spawn_telemetry_task(&self) {
-        let client = self.clone();
+        let client = self.detached();
         
         let mut shutdown_rx = match self.state.lock().await.shutdown_tx.as_ref() {
             Some(tx) => tx.subscribe(),
@@ -1,28 +1,32 @@
 use crate::{errors::ClientResult, MaxClient};
-use crate::models::Response;
+use crate::models::{Profile, Session, Opcode};
 use serde_json::{json, Map, Value};
 
 impl MaxClient {
     /*
      * Удаление сессии
      */
-    pub async fn logout(&self) -> ClientResult<Response> {
+    pub async fn logout(&self) -> ClientResult<()> {
+        let result = self.send_and_wait(Opcode::Logout, json!({}), 0).await;
         self.disconnect().await;
-        self.send_and_wait(20, json!({}), 0).await
+        self.clear_session().await;
+        result?;
+        Ok(())
     }
 
     /*
      * Список сессий
      */
-    pub async fn get_sessions(&self) -> ClientResult<Response> {
-        self.send_and_wait(96, json!({}), 0).await
+    pub async fn get_sessions(&self) -> ClientResult<Vec<Session>> {
+        self.send_and_wait(Opcode::SessionsInfo, json!({}), 0).await?.take("sessions")
     }
 
     /*
      * Закрыть все сессии, кроме текущей
      */
-    pub async fn close_all_sessions(&self) -> ClientResult<Response> {
-        self.send_and_wait(97, json!({}), 0).await
+    pub async fn close_all_sessions(&self) -> ClientResult<()> {
+        self.send_and_wait(Opcode::SessionsClose, json!({}), 0).await?;
+        Ok(())
     }
 
     /*
@@ -34,7 +38,7 @@ impl MaxClient {
         last_name: String,
         description: Option<String>,
         avatar_token: Option<String>,
-    ) -> ClientResult<Response> {
+    ) -> ClientResult<Profile> {
         let mut payload = Map::new();
 
         payload.insert("firstName".into(), json!(first_name));
@@ -49,6 +53,6 @@ impl MaxClient {
             payload.insert("avatarType".into(), json!("USER_AVATAR"));
         }
 
-        self.send_and_wait(16, Value::Object(payload), 0).await
+        self.send_and_wait(Opcode::Profile, Value::Object(payload), 0).await?.take("profile")
     }
 }
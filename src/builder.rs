@@ -0,0 +1,191 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::constants::Constants;
+use crate::errors::ErrorCodes;
+use crate::media::PhotoPreprocessor;
+use crate::retry::{RateLimit, RetryPolicy};
+use crate::transport::proxy::ProxyConfig;
+use crate::transport::record::TrafficRecorder;
+use crate::transport::tls::TlsConfig;
+use crate::{ClientMode, MaxClient};
+
+/**
+ * Настройки подключения клиента. Создаются через MaxClientBuilder
+ */
+#[derive(Clone)]
+pub struct ClientConfig {
+    pub websocket_uri: String,
+    pub mobile_host: String,
+    pub mobile_port: u16,
+    pub tls: TlsConfig,
+    pub request_timeout: Duration,
+    pub ping_interval: Duration,
+    pub event_capacity: usize,
+    /* Рассылать Event::Request/Event::Response для каждого запроса (для логов) */
+    pub traffic_events: bool,
+    pub mode: ClientMode,
+    pub compression_threshold: Option<usize>,
+    pub auto_reconnect: bool,
+    /* None - переподключаться без ограничения числа попыток */
+    pub reconnect_max_attempts: Option<u32>,
+    pub recorder: Option<Arc<TrafficRecorder>>,
+    pub proxy: Option<ProxyConfig>,
+    pub retry: RetryPolicy,
+    pub send_rate_limit: Option<RateLimit>,
+    pub photo_preprocessor: Option<Arc<dyn PhotoPreprocessor>>,
+    /* Классы кодов ошибок сервера. Без auth_expired отклоненный токен повторяется до reconnect_max_attempts */
+    pub error_codes: Arc<ErrorCodes>,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            websocket_uri: Constants::WEBSOCKET_URI.to_string(),
+            mobile_host: Constants::MOBILE_HOST.to_string(),
+            mobile_port: Constants::MOBILE_PORT,
+            tls: TlsConfig::default(),
+            request_timeout: Constants::DEFAULT_TIMEOUT,
+            ping_interval: Constants::PING_INTERVAL,
+            event_capacity: Constants::EVENT_CHANNEL_CAPACITY,
+            traffic_events: false,
+            mode: ClientMode::default(),
+            compression_threshold: None,
+            auto_reconnect: true,
+            reconnect_max_attempts: None,
+            recorder: None,
+            proxy: None,
+            retry: RetryPolicy::default(),
+            send_rate_limit: None,
+            photo_preprocessor: None,
+            error_codes: Arc::new(ErrorCodes::default()),
+        }
+    }
+}
+
+/**
+ * Билдер MaxClient: адреса серверов, TLS, таймауты и режим транспорта.
+ * Позволяет подключаться к openmax-server или стендам без правки констант
+ *
+ * let client = MaxClient::builder()
+ *     .mode(ClientMode::Mobile)
+ *     .mobile_host("127.0.0.1", 4433)
+ *     .request_timeout(Duration::from_secs(30))
+ *     .build();
+ */
+#[derive(Clone, Default)]
+pub struct MaxClientBuilder {
+    config: ClientConfig,
+}
+
+impl MaxClientBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn websocket_uri(mut self, uri: impl Into<String>) -> Self {
+        self.config.websocket_uri = uri.into();
+        self
+    }
+
+    pub fn mobile_host(mut self, host: impl Into<String>, port: u16) -> Self {
+        self.config.mobile_host = host.into();
+        self.config.mobile_port = port;
+        self
+    }
+
+    pub fn tls(mut self, tls: TlsConfig) -> Self {
+        self.config.tls = tls;
+        self
+    }
+
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.config.request_timeout = timeout;
+        self
+    }
+
+    pub fn ping_interval(mut self, interval: Duration) -> Self {
+        self.config.ping_interval = interval;
+        self
+    }
+
+    /* Размер буфера broadcast-канала событий (см. MaxClient::subscribe) */
+    pub fn event_capacity(mut self, capacity: usize) -> Self {
+        self.config.event_capacity = capacity.max(1);
+        self
+    }
+
+    /**
+     * События Event::Request/Event::Response для каждого запроса. По умолчанию выключены:
+     * на нагруженном клиенте они вытесняют из канала пуши и подписчики получают Lagged
+     */
+    pub fn traffic_events(mut self, enabled: bool) -> Self {
+        self.config.traffic_events = enabled;
+        self
+    }
+
+    pub fn mode(mut self, mode: ClientMode) -> Self {
+        self.config.mode = mode;
+        self
+    }
+
+    pub fn compression_threshold(mut self, threshold: Option<usize>) -> Self {
+        self.config.compression_threshold = threshold;
+        self
+    }
+
+    pub fn auto_reconnect(mut self, enabled: bool) -> Self {
+        self.config.auto_reconnect = enabled;
+        self
+    }
+
+    /* После стольких неудачных попыток реконнекта клиент переходит в Failed */
+    pub fn reconnect_max_attempts(mut self, attempts: u32) -> Self {
+        self.config.reconnect_max_attempts = Some(attempts);
+        self
+    }
+
+    /* Запись всего трафика в JSONL (см. transport::record) */
+    pub fn recorder(mut self, recorder: Arc<TrafficRecorder>) -> Self {
+        self.config.recorder = Some(recorder);
+        self
+    }
+
+    /* Прокси для WebSocket, мобильного TCP и загрузки файлов */
+    pub fn proxy(mut self, proxy: ProxyConfig) -> Self {
+        self.config.proxy = Some(proxy);
+        self
+    }
+
+    /* Повторы при флуд-контроле и временных ошибках, по умолчанию выключены (см. retry::RetryPolicy) */
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.config.retry = policy;
+        self
+    }
+
+    /* Не больше burst сообщений подряд, затем одно в interval */
+    pub fn send_rate_limit(mut self, burst: u32, interval: Duration) -> Self {
+        self.config.send_rate_limit = Some(RateLimit::new(burst, interval));
+        self
+    }
+
+    /* Обработка фото перед upload_photo (см. media::PhotoPreprocessor) */
+    pub fn photo_preprocessor(mut self, preprocessor: impl PhotoPreprocessor + 'static) -> Self {
+        self.config.photo_preprocessor = Some(Arc::new(preprocessor));
+        self
+    }
+
+    /* Коды ошибок сервера по классам (см. errors::ErrorCodes) */
+    pub fn error_codes(mut self, codes: ErrorCodes) -> Self {
+        self.config.error_codes = Arc::new(codes);
+        self
+    }
+
+    pub fn config(&self) -> &ClientConfig {
+        &self.config
+    }
+
+    pub fn build(self) -> MaxClient {
+        MaxClient::with_config(self.config)
+    }
+}
@@ -8,6 +8,19 @@ impl Constants {
     pub const ORIGIN_HEADER: &'static str = "https://web.max.ru";
     pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(10000);
     pub const PING_INTERVAL: Duration = Duration::from_secs(30);
+    pub const EVENT_CHANNEL_CAPACITY: usize = 20;
+    pub const RECONNECT_MIN_DELAY: Duration = Duration::from_secs(1);
+    pub const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(60);
+    pub const RETRY_MAX_ATTEMPTS: u32 = 3;
+    pub const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+    pub const RETRY_MAX_DELAY: Duration = Duration::from_secs(30);
+    pub const OUTBOX_MAX_TIMEOUTS: u32 = 3;
+    pub const UPLOAD_CHUNK_SIZE: usize = 6 * 1024 * 1024;
+    pub const UPLOAD_CHUNK_TIMEOUT: Duration = Duration::from_secs(120);
+    pub const ATTACH_PROCESSING_TIMEOUT: Duration = Duration::from_secs(120);
+    pub const ATTACH_RETRY_DELAY: Duration = Duration::from_secs(2);
+    pub const PHOTO_MAX_SIDE: u32 = 2560;
+    pub const PHOTO_JPEG_QUALITY: u8 = 85;
     pub const USER_AGENT: &'static str =
         "Mozilla/5.0 (X11; Linux x86_64; rv:142.0) Gecko/20100101 Firefox/142.0";
 }
@@ -1,55 +1,246 @@
+use serde_json::Value;
+use std::fmt;
 use std::time::Duration;
+use thiserror::Error as ThisError;
 use tokio::sync::oneshot;
 
-#[derive(Debug)]
+/**
+ * Коды ошибок сервера по классам. Протокол коды не документирует, поэтому по умолчанию
+ * списки пусты: ошибка без retryAfter/waitTime остается Error::Api. Коды из записанного
+ * трафика задаются через MaxClientBuilder::error_codes. По AuthExpired реконнект
+ * прекращается, RateLimited и Transient повторяются по RetryPolicy
+ *
+ * let client = MaxClient::builder()
+ *     .error_codes(ErrorCodes {
+ *         auth_expired: vec!["<код отклоненного токена>".into()],
+ *         ..ErrorCodes::default()
+ *     })
+ *     .build();
+ */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ErrorCodes {
+    /* Токен отклонен, нужен повторный вход */
+    pub auth_expired: Vec<String>,
+    /* Ограничение частоты запросов без явной задержки */
+    pub rate_limited: Vec<String>,
+    /* Временная ошибка сервера: идемпотентный запрос можно повторить */
+    pub transient: Vec<String>,
+}
+
+impl ErrorCodes {
+    /**
+     * Классифицирует ошибочный payload ответа сервера
+     */
+    pub fn classify(&self, payload: Value) -> Error {
+        let error = Box::new(ApiError::from_payload(payload));
+        let has = |codes: &[String]| codes.contains(&error.code);
+
+        if has(&self.auth_expired) {
+            Error::AuthExpired(error)
+        } else if let Some(retry_after) = error.retry_after() {
+            Error::FloodWait { error, retry_after }
+        } else if has(&self.rate_limited) {
+            Error::RateLimited(error)
+        } else if has(&self.transient) {
+            Error::Transient(error)
+        } else {
+            Error::Api(error)
+        }
+    }
+}
+
+/**
+ * Разобранная ошибка из ответа сервера (cmd 3 или payload с полем "error")
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiError {
+    /* Машинный код ошибки, например "chat.not.found" */
+    pub code: String,
+    pub message: Option<String>,
+    pub localized_message: Option<String>,
+    pub title: Option<String>,
+    /* Исходный payload целиком */
+    pub payload: Value,
+}
+
+impl ApiError {
+    pub fn from_payload(payload: Value) -> Self {
+        let field = |name: &str| payload.get(name).and_then(Value::as_str).map(str::to_string);
+
+        let code = match payload.get("error") {
+            Some(Value::String(code)) => code.clone(),
+            Some(Value::Null) | None => "unknown".to_string(),
+            Some(other) => other.to_string(),
+        };
+
+        ApiError {
+            code,
+            message: field("message"),
+            localized_message: field("localizedMessage"),
+            title: field("title"),
+            payload,
+        }
+    }
+
+    /**
+     * Задержка, которую сервер просит выждать перед повтором (retryAfter/waitTime в секундах)
+     */
+    pub fn retry_after(&self) -> Option<Duration> {
+        ["retryAfter", "waitTime"]
+            .iter()
+            .find_map(|name| self.payload.get(*name).and_then(Value::as_f64))
+            .filter(|secs| secs.is_finite() && *secs >= 0.0)
+            .map(Duration::from_secs_f64)
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.localized_message.as_ref().or(self.message.as_ref()) {
+            Some(message) => write!(f, "{} ({})", message, self.code),
+            None => write!(f, "{}", self.code),
+        }
+    }
+}
+
+/**
+ * Ошибка HTTP-загрузки фото, видео или файла (upload_*)
+ */
+#[derive(Debug, ThisError)]
+pub enum UploadError {
+    #[error("Пустой файл")]
+    EmptyFile,
+    /* Не удалось определить или разобрать MIME-тип */
+    #[error("Неподдерживаемый тип файла: {0}")]
+    UnsupportedType(String),
+    #[error("Ошибка HTTP запроса: {0}")]
+    Request(String),
+    #[error("Сервер загрузки ответил {status}: {body}")]
+    Status { status: u16, body: String },
+    #[error("Не удалось разобрать ответ сервера загрузки: {0}")]
+    Parse(String),
+    #[error("В ответе сервера загрузки нет токена")]
+    MissingToken,
+    /* Сервер ответил успехом, но не подтвердил ни одного нового байта */
+    #[error("Сервер загрузки не принял данные: {0}")]
+    Stalled(String),
+    /* PhotoPreprocessor не смог обработать фото, например StripExif - разобрать JPEG */
+    #[error("Ошибка предобработки фото: {0}")]
+    Preprocess(String),
+    /* Загрузка оборвалась, offset - сколько байт сервер уже принял (см. UploadOptions::offset) */
+    #[error("Загрузка прервана на {offset} байт: {source}")]
+    Interrupted { offset: u64, source: Box<UploadError> },
+}
+
+/**
+ * Ошибка скачивания (download*)
+ */
+#[derive(Debug, ThisError)]
+pub enum DownloadError {
+    /* У вложения нет ссылки или сервер не вернул url */
+    #[error("Нет ссылки для скачивания: {0}")]
+    NoUrl(String),
+    #[error("Ошибка HTTP запроса: {0}")]
+    Request(String),
+    #[error("Сервер ответил {status}")]
+    Status { status: u16 },
+    #[error("Размер не совпадает: ожидалось {expected}, получено {actual}")]
+    SizeMismatch { expected: u64, actual: u64 },
+    #[error("SHA-256 не совпадает: ожидалось {expected}, получено {actual}")]
+    ChecksumMismatch { expected: String, actual: String },
+    /* SHA-256 нельзя проверить при продолжении в AsyncWrite: начала файла нет */
+    #[error("Проверка SHA-256 при offset > 0 доступна только в download_to_path")]
+    ChecksumUnavailable,
+    /* Соединение обрывалось max_retries раз подряд, offset - сколько байт уже записано */
+    #[error("Скачивание прервано на {offset} байт: {source}")]
+    Interrupted { offset: u64, source: Box<DownloadError> },
+}
+
+#[derive(Debug, ThisError)]
 pub enum Error {
+    #[error("Клиент не подключен")]
     NotConnected,
+    #[error("Ошибка подключения: {0}")]
     ConnectionFailed(String),
+    #[error("Соединение оборвано: {0}")]
     ConnectionClosed(String),
+    #[error("Ошибка отправки: {0}")]
     SendFailed(String),
-    ParseError(serde_json::Error),
+    #[error("Ошибка парсинга JSON: {0}")]
+    ParseError(#[from] serde_json::Error),
+    #[error("Таймаут запроса: {0:?}")]
     RequestTimeout(Duration),
-    ApiResponse(serde_json::Value),
-    OneshotRecvError(oneshot::error::RecvError),
-    IoError(std::io::Error),
+    #[error("Запрос отменен")]
+    Cancelled,
+    #[error("Ошибка API: {0}")]
+    Api(Box<ApiError>),
+    #[error("Сессия истекла, нужен повторный вход: {0}")]
+    AuthExpired(Box<ApiError>),
+    #[error("Превышен лимит запросов: {0}")]
+    RateLimited(Box<ApiError>),
+    #[error("Временная ошибка сервера: {0}")]
+    Transient(Box<ApiError>),
+    #[error("Флуд-контроль, повтор через {retry_after:?}: {error}")]
+    FloodWait { error: Box<ApiError>, retry_after: Duration },
+    /* Кадр не соответствует протоколу (неожиданный опкод, seq и т.п.) */
+    #[error("Ошибка протокола: {0}")]
+    Protocol(String),
+    /* Не удалось декодировать кадр (LZ4, MsgPack, JSON) */
+    #[error("Ошибка декодирования: {0}")]
+    Decode(String),
+    #[error("Ошибка загрузки: {0}")]
+    Upload(#[from] UploadError),
+    #[error("Ошибка скачивания: {0}")]
+    Download(#[from] DownloadError),
+    #[error("Ошибка получения ответа: {0}")]
+    OneshotRecvError(#[from] oneshot::error::RecvError),
+    #[error("Ошибка I/O: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Ошибка Tauri: {0}")]
     TauriError(String),
+    #[error("Неизвестная ошибка: {0}")]
     Other(String),
 }
 
-impl From<String> for Error {
-    fn from(s: String) -> Self {
-        Error::Other(s)
+impl Error {
+    /**
+     * Классифицирует ошибочный payload без настроенных кодов (см. ErrorCodes):
+     * FloodWait, если сервер указал задержку, иначе Api
+     */
+    pub fn from_api(payload: Value) -> Self {
+        ErrorCodes::default().classify(payload)
     }
-}
 
-impl From<serde_json::Error> for Error {
-    fn from(e: serde_json::Error) -> Self {
-        Error::ParseError(e)
+    /**
+     * Ошибка сервера, если она пришла в ответе на запрос
+     */
+    pub fn api_error(&self) -> Option<&ApiError> {
+        match self {
+            Error::Api(e) | Error::AuthExpired(e) | Error::RateLimited(e) | Error::Transient(e) => Some(e),
+            Error::FloodWait { error, .. } => Some(error),
+            _ => None,
+        }
     }
-}
 
-impl From<oneshot::error::RecvError> for Error {
-    fn from(e: oneshot::error::RecvError) -> Self {
-        Error::OneshotRecvError(e)
+    /**
+     * Ошибка соединения, а не ответа сервера: после нее запрос стоит повторить
+     * на новом соединении
+     */
+    pub fn is_connection_error(&self) -> bool {
+        matches!(
+            self,
+            Error::NotConnected
+                | Error::ConnectionClosed(_)
+                | Error::SendFailed(_)
+                | Error::RequestTimeout(_)
+                | Error::OneshotRecvError(_)
+        )
     }
 }
 
-use std::fmt;
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Error::NotConnected => write!(f, "Клиент не подключен"),
-            Error::ConnectionFailed(e) => write!(f, "Ошибка подключения: {}", e),
-            Error::ConnectionClosed(e) => write!(f, "Соединение оборвано: {}", e),
-            Error::SendFailed(e) => write!(f, "Ошибка отправки: {}", e),
-            Error::ParseError(e) => write!(f, "Ошибка парсинга JSON: {}", e),
-            Error::RequestTimeout(d) => write!(f, "Таймаут запроса: {:?}", d),
-            Error::ApiResponse(json) => write!(f, "Ошибка API: {}", json),
-            Error::OneshotRecvError(e) => write!(f, "Ошибка получения ответа: {}", e),
-            Error::IoError(e) => write!(f, "Ошибка I/O: {}", e),
-            Error::TauriError(e) => write!(f, "Ошибка Tauri: {}", e),
-            Error::Other(s) => write!(f, "Неизвестная ошибка: {}", s),
-        }
+impl From<String> for Error {
+    fn from(s: String) -> Self {
+        Error::Other(s)
     }
 }
 
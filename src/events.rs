@@ -0,0 +1,143 @@
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::outbox::OutboxStatus;
+use crate::models::{value_as_id, Chat, Message, Opcode, Presence, ReactionInfo, Request, Response};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "state", rename_all = "lowercase")]
+pub enum ConnectionState {
+    Connecting,
+    Connected,
+    Reconnecting { attempt: u32, delay_ms: u64 },
+    Disconnected,
+    /* Реконнект прекращен: сервер отклонил токен или исчерпаны попытки */
+    Failed { reason: String },
+}
+
+/**
+ * События клиента, рассылаемые через MaxClient::subscribe
+ */
+#[derive(Debug, Clone)]
+pub enum Event {
+    NewMessage {
+        chat_id: i64,
+        message: Box<Message>,
+    },
+    MessageEdited {
+        chat_id: i64,
+        message: Box<Message>,
+    },
+    MessageDeleted {
+        chat_id: i64,
+        message_ids: Vec<i64>,
+    },
+    ReactionChanged {
+        chat_id: i64,
+        message_id: i64,
+        reaction_info: ReactionInfo,
+    },
+    Typing {
+        chat_id: i64,
+        user_id: i64,
+    },
+    Presence {
+        user_id: i64,
+        presence: Presence,
+    },
+    ReadMark {
+        chat_id: i64,
+        user_id: i64,
+        mark: i64,
+    },
+    ChatUpdated {
+        chat: Box<Chat>,
+    },
+    ConnectionState(ConnectionState),
+    /* Смена статуса сообщения в очереди исходящих (см. MaxClient::queue_message) */
+    Outbox {
+        cid: i64,
+        chat_id: i64,
+        status: OutboxStatus,
+        /* Сообщение от сервера для Delivered */
+        message: Option<Box<Message>>,
+    },
+    /* Исходящий запрос (для логов, только с MaxClientBuilder::traffic_events) */
+    Request(Request),
+    /**
+     * Ответ на запрос, который уже не ждут (send_no_wait, отмена, таймаут).
+     * С MaxClientBuilder::traffic_events - и на все остальные запросы
+     */
+    Response(Response),
+    /* Неизвестный или нераспознанный пуш от сервера */
+    Raw(Response),
+}
+
+fn as_id(value: Option<&Value>) -> Option<i64> {
+    value.and_then(value_as_id)
+}
+
+fn parse<T: DeserializeOwned>(value: Option<&Value>) -> Option<T> {
+    serde_json::from_value(value?.clone()).ok()
+}
+
+impl Event {
+    /**
+     * Разбирает пуш от сервера (ответ без ожидающего seq) по opcode.
+     * Если разобрать не удалось, возвращает Event::Raw
+     */
+    pub fn from_push(resp: Response) -> Event {
+        Self::decode(&resp).unwrap_or(Event::Raw(resp))
+    }
+
+    fn decode(resp: &Response) -> Option<Event> {
+        let p = &resp.payload;
+
+        match Opcode::try_from(resp.opcode).ok()? {
+            Opcode::NotifMessage => {
+                let chat_id = as_id(p.get("chatId"))?;
+                let message: Box<Message> = parse(p.get("message"))?;
+
+                match message.status.as_deref() {
+                    Some("EDITED") => Some(Event::MessageEdited { chat_id, message }),
+                    Some("REMOVED") => Some(Event::MessageDeleted {
+                        chat_id,
+                        message_ids: vec![message.id],
+                    }),
+                    _ => Some(Event::NewMessage { chat_id, message }),
+                }
+            }
+            Opcode::NotifTyping => Some(Event::Typing {
+                chat_id: as_id(p.get("chatId"))?,
+                user_id: as_id(p.get("userId"))?,
+            }),
+            Opcode::NotifMark => Some(Event::ReadMark {
+                chat_id: as_id(p.get("chatId"))?,
+                user_id: as_id(p.get("userId"))?,
+                mark: as_id(p.get("mark"))?,
+            }),
+            Opcode::NotifPresence => Some(Event::Presence {
+                user_id: as_id(p.get("userId"))?,
+                presence: parse(p.get("presence"))?,
+            }),
+            Opcode::NotifChat => Some(Event::ChatUpdated {
+                chat: parse(p.get("chat"))?,
+            }),
+            Opcode::NotifMsgDelete => Some(Event::MessageDeleted {
+                chat_id: as_id(p.get("chatId"))?,
+                message_ids: p
+                    .get("messageIds")?
+                    .as_array()?
+                    .iter()
+                    .filter_map(|id| as_id(Some(id)))
+                    .collect(),
+            }),
+            Opcode::NotifMsgReactionsChanged => Some(Event::ReactionChanged {
+                chat_id: as_id(p.get("chatId"))?,
+                message_id: as_id(p.get("messageId"))?,
+                reaction_info: parse(Some(p))?,
+            }),
+            _ => None,
+        }
+    }
+}
@@ -1,70 +1,144 @@
-use log::{debug, error, info, trace, warn};
+use log::{debug, error, info, warn};
 use std::{
     collections::HashMap,
     sync::{Arc, Mutex},
+    time::Duration,
 };
 use chrono::Utc;
-use http::header::HeaderValue;
-use serde_json::{json, Value};
+use rand::Rng;
+use serde_json::json;
 use tokio::{
     sync::{broadcast, oneshot, Mutex as TokioMutex},
-    time::timeout,
+    time::sleep,
 };
-use yawc::{CompressionLevel, HttpRequestBuilder, Options, WebSocket};
 use rustls::crypto::ring;
 
 pub mod api;
+pub mod builder;
 pub mod constants;
 pub mod errors;
+pub mod events;
+pub mod media;
 pub mod models;
 pub mod navigation;
+pub mod options;
+pub mod outbox;
+pub mod pending;
+pub mod retry;
+pub mod session;
+#[cfg(feature = "testing")]
+pub mod testing;
 
 pub mod transport;
 
 use transport::{
-    TransportFactory, 
+    TransportConnector,
     TransportReader, 
     TransportWriter,
-    web::WebTransport,
-    mobile::MobileTransport
+    web::WebConnector,
+    mobile::MobileConnector,
+    record::{RecordingReader, RecordingWriter, TrafficRecorder},
+    proxy::ProxyConfig,
 };
 
+use builder::{ClientConfig, MaxClientBuilder};
 use constants::Constants;
 use errors::{ClientResult, Error};
-use models::{Request, Response, Identity};
+use events::{ConnectionState, Event};
+use models::{Request, Response, Identity, Opcode};
+use options::RequestOptions;
+use outbox::Outbox;
+use pending::{PendingEntry, PendingGuard, PendingMap, PendingResponse};
+use retry::TokenBucket;
+use session::{SessionData, SessionStore, SyncMarkers};
 
 struct ClientState {
     writer: Option<Box<dyn TransportWriter>>,
     seq: u64,
     temp_token: Option<String>,
     token: Option<String>,
-    pending: Arc<Mutex<HashMap<u64, oneshot::Sender<ClientResult<Response>>>>>,
+    pending: PendingMap,
     shutdown_tx: Option<broadcast::Sender<()>>,
+    conn_shutdown_tx: Option<broadcast::Sender<()>>,
     user_id: Option<u64>,
     action_id: u64,
     session_id: i64,
     identity: Option<Identity>,
     current_screen: String,
     is_closed: bool,
-    mobile_port: u16,
-    mobile_host: String,
+    /* false для connect_with_transport: транспорт нельзя открыть повторно */
+    reconnectable: bool,
+    /* Лимит отправки сообщений из config.send_rate_limit */
+    send_limiter: Option<Arc<TokenBucket>>,
+    /* Успешный sync() на текущем соединении, после него работает outbox */
+    logged_in: bool,
+    /* Число запущенных задач реконнекта */
+    reconnect_tasks: u32,
+    /* Идет Handshake: обрыв в это время обрабатывает сам вызывающий (connect или цикл реконнекта) */
+    handshaking: bool,
+    config: ClientConfig,
+    http: Option<reqwest::Client>,
+    store: Option<Arc<dyn SessionStore>>,
+    sync_markers: SyncMarkers,
 }
 
+/**
+ * Режим подключения: определяет транспорт и payload Handshake.
+ * Custom позволяет подключить собственный транспорт через TransportConnector
+ */
+#[derive(Clone, Default)]
 pub enum ClientMode {
+    #[default]
     Web,
     Mobile,
+    Custom(Arc<dyn TransportConnector>),
+}
+
+impl ClientMode {
+    pub fn connector(&self) -> Arc<dyn TransportConnector> {
+        match self {
+            ClientMode::Web => Arc::new(WebConnector),
+            ClientMode::Mobile => Arc::new(MobileConnector),
+            ClientMode::Custom(connector) => Arc::clone(connector),
+        }
+    }
+
+    pub fn handshake_payload(&self, identity: &Identity) -> serde_json::Value {
+        self.connector().handshake_payload(identity)
+    }
+}
+
+impl std::fmt::Debug for ClientMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClientMode::Web => f.write_str("Web"),
+            ClientMode::Mobile => f.write_str("Mobile"),
+            ClientMode::Custom(_) => f.write_str("Custom"),
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct MaxClient {
     state: Arc<TokioMutex<ClientState>>,
-    event_tx: broadcast::Sender<Value>,
+    event_tx: broadcast::Sender<Event>,
+    /* Параметры запросов этой копии клиента, см. with_options */
+    options: RequestOptions,
+    outbox: Arc<Outbox>,
 }
 
 impl MaxClient {
     pub fn new() -> Self {
+        Self::with_config(ClientConfig::default())
+    }
+
+    pub fn builder() -> MaxClientBuilder {
+        MaxClientBuilder::new()
+    }
+
+    pub fn with_config(config: ClientConfig) -> Self {
         let (shutdown_tx, _) = broadcast::channel(1);
-        let (event_tx, _) = broadcast::channel(20);
+        let (event_tx, _) = broadcast::channel(config.event_capacity);
         MaxClient {
             state: Arc::new(TokioMutex::new(ClientState {
                 writer: None,
@@ -73,26 +147,129 @@ impl MaxClient {
                 token: None,
                 pending: Arc::new(Mutex::new(HashMap::new())),
                 shutdown_tx: Some(shutdown_tx),
+                conn_shutdown_tx: None,
                 user_id: None,
                 action_id: 0,
                 session_id: Utc::now().timestamp_millis(),
                 identity: None,
                 current_screen: "chats_list_tab".to_string(),
                 is_closed: true,
-                mobile_host: Constants::MOBILE_HOST.to_string(),
-                mobile_port: Constants::MOBILE_PORT,
+                reconnectable: true,
+                reconnect_tasks: 0,
+                handshaking: false,
+                send_limiter: config.send_rate_limit.map(|limit| Arc::new(TokenBucket::new(limit))),
+                logged_in: false,
+                config,
+                http: None,
+                store: None,
+                sync_markers: SyncMarkers::default(),
             })),
+            outbox: Arc::new(Outbox::new(event_tx.clone())),
+            event_tx,
+            options: RequestOptions::default(),
+        }
+    }
+
+    /* Клиент поверх существующего состояния (для фоновых задач, держащих Weak) */
+    fn from_parts(state: Arc<TokioMutex<ClientState>>, event_tx: broadcast::Sender<Event>, outbox: Arc<Outbox>) -> Self {
+        MaxClient {
+            state,
             event_tx,
+            options: RequestOptions::default(),
+            outbox,
+        }
+    }
+
+    /**
+     * Создает клиента и восстанавливает сессию (identity, токен, user_id, маркеры sync)
+     * из хранилища. Дальнейшие изменения сессии сохраняются в него автоматически
+     */
+    pub async fn from_store(store: Arc<dyn SessionStore>) -> ClientResult<Self> {
+        let client = Self::new();
+        client.set_store(store).await?;
+        Ok(client)
+    }
+
+    pub async fn set_store(&self, store: Arc<dyn SessionStore>) -> ClientResult<()> {
+        let data = store.load().await?;
+
+        let mut state = self.state.lock().await;
+        if let Some(data) = data {
+            info!("Сессия восстановлена из хранилища");
+            if data.identity.is_some() {
+                state.identity = data.identity;
+            }
+            state.token = data.token;
+            state.user_id = data.user_id;
+            state.sync_markers = data.sync;
+        }
+        state.store = Some(store);
+
+        Ok(())
+    }
+
+    /**
+     * Сохраняет текущую сессию в хранилище (если оно задано)
+     */
+    pub async fn save_session(&self) {
+        let (store, data) = {
+            let state = self.state.lock().await;
+            let Some(store) = state.store.clone() else {
+                return;
+            };
+            (store, SessionData {
+                identity: state.identity.clone(),
+                token: state.token.clone(),
+                user_id: state.user_id,
+                sync: state.sync_markers,
+            })
+        };
+
+        if let Err(e) = store.save(&data).await {
+            warn!("Не удалось сохранить сессию: {}", e);
+        }
+    }
+
+    /**
+     * Удаляет сессию из хранилища (если оно задано)
+     */
+    pub async fn clear_session(&self) {
+        let Some(store) = self.state.lock().await.store.clone() else {
+            return;
+        };
+
+        if let Err(e) = store.clear().await {
+            warn!("Не удалось удалить сессию: {}", e);
         }
     }
 
-    pub fn subscribe(&self) -> broadcast::Receiver<Value> {
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
         self.event_tx.subscribe()
     }
     
     pub async fn is_connected(&self) -> bool {
         self.state.lock().await.writer.is_some()
     }
+
+    /* Соединение установлено и на нем выполнен sync() */
+    pub async fn is_logged_in(&self) -> bool {
+        let state = self.state.lock().await;
+        state.writer.is_some() && state.logged_in
+    }
+
+    /* Число запросов, ожидающих ответа */
+    pub async fn pending_count(&self) -> usize {
+        self.state.lock().await.pending.lock().unwrap().len()
+    }
+
+    /* Опкоды запросов, ожидающих ответа, в порядке seq */
+    pub async fn pending_opcodes(&self) -> Vec<u16> {
+        let state = self.state.lock().await;
+        let pending = state.pending.lock().unwrap();
+        let mut entries: Vec<_> = pending.iter().map(|(seq, entry)| (*seq, entry.opcode)).collect();
+        entries.sort_unstable();
+        entries.into_iter().map(|(_, opcode)| opcode).collect()
+    }
     
     pub async fn set_user_id(&self, user_id: u64) {
         self.state.lock().await.user_id = Some(user_id);
@@ -102,83 +279,349 @@ impl MaxClient {
         self.state.lock().await.token.clone()
     }
 
+    pub async fn get_identity(&self) -> Option<Identity> {
+        self.state.lock().await.identity.clone()
+    }
+
     pub async fn set_host(&self, address: String, port: u16) {
         let mut state = self.state.lock().await;
-        state.mobile_host = address;
-        state.mobile_port = port;
+        state.config.mobile_host = address;
+        state.config.mobile_port = port;
     }
     
-    pub async fn connect(&self, identity: Identity, is_mobile: bool) -> ClientResult<Response> {
+    /**
+     * Порог LZ4-сжатия исходящих кадров для мобильного транспорта (None - без сжатия).
+     * Применяется при следующем подключении
+     */
+    pub async fn set_compression_threshold(&self, threshold: Option<usize>) {
+        self.state.lock().await.config.compression_threshold = threshold;
+    }
+
+    /**
+     * Включает/выключает автоматический реконнект при обрыве соединения
+     */
+    pub async fn set_auto_reconnect(&self, enabled: bool) {
+        self.state.lock().await.config.auto_reconnect = enabled;
+    }
+
+    /**
+     * Режим подключения (транспорт), применяется при следующем connect
+     */
+    pub async fn set_mode(&self, mode: ClientMode) {
+        self.state.lock().await.config.mode = mode;
+    }
+
+    /**
+     * Прокси для WebSocket, мобильного TCP и HTTP загрузок (None - напрямую).
+     * Для сокетов применяется при следующем подключении
+     */
+    pub async fn set_proxy(&self, proxy: Option<ProxyConfig>) {
+        let mut state = self.state.lock().await;
+        state.config.proxy = proxy;
+        state.http = None;
+    }
+
+    /**
+     * HTTP клиент для загрузки и скачивания файлов с учетом прокси
+     */
+    pub async fn http_client(&self) -> ClientResult<reqwest::Client> {
+        let mut state = self.state.lock().await;
+        if let Some(client) = &state.http {
+            return Ok(client.clone());
+        }
+
+        let mut builder = reqwest::Client::builder();
+        if let Some(proxy) = &state.config.proxy {
+            builder = builder.proxy(proxy.reqwest_proxy()?);
+        }
+
+        let client = builder.build()
+            .map_err(|e| Error::Other(format!("Failed to build HTTP client: {}", e)))?;
+        state.http = Some(client.clone());
+        Ok(client)
+    }
+
+    /**
+     * Запись трафика в JSONL (None - выключить), применяется при следующем подключении
+     */
+    pub async fn set_recorder(&self, recorder: Option<Arc<TrafficRecorder>>) {
+        self.state.lock().await.config.recorder = recorder;
+    }
+
+    pub async fn connect(&self, identity: Identity) -> ClientResult<Response> {
+        self.prepare_connect(&identity, true).await;
+
+        self.emit_state(ConnectionState::Connecting);
+        let resp = self.establish(&identity).await?;
+        self.emit_state(ConnectionState::Connected);
+
+        Ok(resp)
+    }
+
+    /**
+     * Подключение через готовый транспорт (прокси, запись трафика, in-memory для тестов).
+     * Handshake формируется текущим ClientMode. Автоматический реконнект для такого
+     * подключения невозможен: при обрыве клиент переходит в Disconnected
+     */
+    pub async fn connect_with_transport(
+        &self,
+        identity: Identity,
+        writer: Box<dyn TransportWriter>,
+        reader: Box<dyn TransportReader>,
+    ) -> ClientResult<Response> {
+        self.prepare_connect(&identity, false).await;
+
+        let handshake_payload = self.state.lock().await.config.mode.handshake_payload(&identity);
+
+        self.emit_state(ConnectionState::Connecting);
+        let resp = self.attach(writer, reader, handshake_payload).await?;
+        self.emit_state(ConnectionState::Connected);
+
+        Ok(resp)
+    }
+
+    async fn prepare_connect(&self, identity: &Identity, reconnectable: bool) {
         let _ = ring::default_provider().install_default();
+        self.start_outbox();
+
+        {
+            let mut state = self.state.lock().await;
+            state.identity = Some(identity.clone());
+            state.is_closed = false;
+            state.reconnectable = reconnectable;
 
-        let state_clone = Arc::clone(&self.state);
-        let mut state_lock = state_clone.lock().await;
+            let (shutdown_tx, _) = broadcast::channel(1);
+            if let Some(old_tx) = state.shutdown_tx.replace(shutdown_tx) {
+                let _ = old_tx.send(());
+            }
+        }
+    }
 
-        state_lock.identity = Some(identity.clone());
+    /**
+     * Поднимает транспорт, запускает задачи чтения/пинга и отправляет Handshake.
+     * Используется как при первом подключении, так и при реконнекте
+     */
+    async fn establish(&self, identity: &Identity) -> ClientResult<Response> {
+        let config = self.state.lock().await.config.clone();
+        let connector = config.mode.connector();
+        let (writer, reader) = connector.connect(&config).await?;
 
-        let (writer, reader): (Box<dyn TransportWriter>, Box<dyn TransportReader>) = if is_mobile {
-            info!("Подключение Mobile TCP/TLS...");
-            let transport = MobileTransport::connect_tls(&state_lock.mobile_host, state_lock.mobile_port, None, None).await?;
-            let (w, r) = transport.split();
-            (Box::new(w), Box::new(r))
-        } else {
-            info!("Подключение к WebSocket...");
-            let req_builder = HttpRequestBuilder::new()
-                .header("Origin", HeaderValue::from_static(Constants::ORIGIN_HEADER))
-                .header("User-Agent", HeaderValue::from_static(Constants::USER_AGENT));
-
-            let ws = WebSocket::connect(Constants::WEBSOCKET_URI.parse().unwrap())
-                .with_options(Options::default().with_compression_level(CompressionLevel::fast()))
-                .with_request(req_builder)
-                .await
-                .map_err(|e| Error::ConnectionFailed(e.to_string()))?;
-
-            info!("WebSocket подключен.");
-            let transport = WebTransport::new(ws);
-            let (w, r) = transport.split();
-            (Box::new(w), Box::new(r))
-        };
+        self.attach(writer, reader, connector.handshake_payload(identity)).await
+    }
 
+    /**
+     * Запускает задачи чтения/пинга поверх открытого транспорта и отправляет Handshake
+     */
+    async fn attach(
+        &self,
+        writer: Box<dyn TransportWriter>,
+        reader: Box<dyn TransportReader>,
+        handshake_payload: serde_json::Value,
+    ) -> ClientResult<Response> {
         info!("Разделение потоков и запуск задач...");
 
-        state_lock.is_closed = false;
-        
-        let pending_clone = Arc::clone(&state_lock.pending);
-        let (shutdown_tx, shutdown_rx_read) = broadcast::channel(1);
-        let shutdown_rx_ping = shutdown_tx.subscribe();
-        let event_tx = self.event_tx.clone();
+        let mut state_lock = self.state.lock().await;
+
+        let (writer, reader): (Box<dyn TransportWriter>, Box<dyn TransportReader>) =
+            match state_lock.config.recorder.clone() {
+                Some(recorder) => (
+                    Box::new(RecordingWriter::new(writer, recorder.clone())),
+                    Box::new(RecordingReader::new(reader, recorder)),
+                ),
+                None => (writer, reader),
+            };
+
+        let (conn_shutdown_tx, shutdown_rx_read) = broadcast::channel(1);
+        let shutdown_rx_ping = conn_shutdown_tx.subscribe();
+        if let Some(old_tx) = state_lock.conn_shutdown_tx.replace(conn_shutdown_tx.clone()) {
+            let _ = old_tx.send(());
+        }
 
+        let pending_clone = Arc::clone(&state_lock.pending);
+        state_lock.logged_in = false;
         state_lock.session_id = Utc::now().timestamp_millis();
 
-        tokio::spawn(Self::read_task(reader, pending_clone, event_tx, shutdown_rx_read, Arc::clone(&self.state)));
+        tokio::spawn(Self::read_task(self.detached(), reader, pending_clone, conn_shutdown_tx.clone(), shutdown_rx_read));
         debug!("Задача чтения (read_task) запущена.");
-        
-        let ping_client = self.clone();
-        tokio::spawn(Self::ping_task(ping_client, shutdown_rx_ping));
+
+        let ping_client = self.detached();
+        tokio::spawn(Self::ping_task(ping_client, conn_shutdown_tx.clone(), shutdown_rx_ping));
         debug!("Задача пинга (ping_task) запущена.");
-        
+
         state_lock.writer = Some(writer);
-        state_lock.shutdown_tx = Some(shutdown_tx);
-        
+        state_lock.handshaking = true;
+
         drop(state_lock);
-        
+
         debug!("Отправка Handshake");
-        
-        let handshake_payload = if is_mobile {
-            json!({
-                "clientSessionId": 1,
-                "mt_instanceid": identity.mt_instance,
-                "userAgent": identity.user_agent,
-                "deviceId": identity.device_id,
-            })
-        } else {
-            json!({
-                "deviceId": identity.device_id,
-                "userAgent": identity.user_agent,
-            })
+
+        let result = self.send_and_wait(Opcode::SessionInit, handshake_payload, 0).await;
+        self.state.lock().await.handshaking = false;
+
+        match result {
+            Ok(resp) => Ok(resp),
+            Err(e) => {
+                self.drop_connection().await;
+                Err(e)
+            }
+        }
+    }
+
+    /**
+     * Закрывает текущее соединение, не трогая сессию (токен, identity)
+     */
+    async fn drop_connection(&self) {
+        let mut state = self.state.lock().await;
+        if let Some(tx) = state.conn_shutdown_tx.take() {
+            let _ = tx.send(());
+        }
+        state.writer = None;
+    }
+
+    fn emit_state(&self, connection_state: ConnectionState) {
+        debug!("Состояние соединения: {:?}", connection_state);
+        let _ = self.event_tx.send(Event::ConnectionState(connection_state));
+    }
+
+    fn reconnect_delay(attempt: u32) -> Duration {
+        let base = Constants::RECONNECT_MIN_DELAY
+            .saturating_mul(1u32 << attempt.saturating_sub(1).min(16))
+            .min(Constants::RECONNECT_MAX_DELAY);
+        let jitter = rand::thread_rng().gen_range(0..=base.as_millis() as u64 / 4);
+        base + Duration::from_millis(jitter)
+    }
+
+    /**
+     * Цикл переподключения с экспоненциальной задержкой.
+     * Повторяет Handshake с сохраненным Identity и, если есть токен, sync.
+     * Отклоненный токен или исчерпанные попытки завершают цикл состоянием Failed
+     */
+    fn reconnect_task(client: MaxClient) -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send>> {
+        Box::pin(async move {
+            if !Self::reconnect_loop(client.clone()).await {
+                client.state.lock().await.reconnect_tasks -= 1;
+            }
+        })
+    }
+
+    /**
+     * Ждет, пока задача реконнекта восстановит соединение (и sync, если есть токен).
+     * false, если реконнекта нет или он не удался за limit
+     */
+    async fn wait_reconnected(&self, limit: Duration) -> bool {
+        let mut events = self.subscribe();
+        {
+            let state = self.state.lock().await;
+            if state.writer.is_some() && (state.token.is_none() || state.logged_in) {
+                return true;
+            }
+            if state.reconnect_tasks == 0 {
+                return false;
+            }
+        }
+
+        let reconnected = async {
+            loop {
+                match events.recv().await {
+                    Ok(Event::ConnectionState(ConnectionState::Connected)) => return true,
+                    Ok(Event::ConnectionState(ConnectionState::Failed { .. } | ConnectionState::Disconnected))
+                    | Err(broadcast::error::RecvError::Closed) => return false,
+                    _ => {}
+                }
+            }
+        };
+        tokio::time::timeout(limit, reconnected).await.unwrap_or(false)
+    }
+
+    /**
+     * true, если цикл сам снял себя с reconnect_tasks (соединение восстановлено).
+     * Пока цикл работает, connection_lost новых задач не запускает, поэтому
+     * обрыв на любом шаге попытки обрабатывается здесь же
+     */
+    async fn reconnect_loop(client: MaxClient) -> bool {
+        let mut shutdown_rx = match client.state.lock().await.shutdown_tx.as_ref() {
+            Some(tx) => tx.subscribe(),
+            None => return false,
         };
 
-        self.send_and_wait(6, handshake_payload, 0).await
+        let mut attempt: u32 = 0;
+
+        loop {
+            let (identity, has_token, max_attempts) = {
+                let state = client.state.lock().await;
+                if state.is_closed {
+                    return false;
+                }
+                let Some(identity) = state.identity.clone() else {
+                    error!("Реконнект невозможен: identity не установлен");
+                    return false;
+                };
+                (identity, state.token.is_some(), state.config.reconnect_max_attempts)
+            };
+
+            if max_attempts.is_some_and(|max| attempt >= max) {
+                warn!("Реконнект прекращен после {} попыток", attempt);
+                client.emit_state(ConnectionState::Failed {
+                    reason: format!("Reconnect failed after {} attempts", attempt),
+                });
+                return false;
+            }
+
+            attempt += 1;
+            let delay = Self::reconnect_delay(attempt);
+            client.emit_state(ConnectionState::Reconnecting {
+                attempt,
+                delay_ms: delay.as_millis() as u64,
+            });
+            info!("Реконнект через {:?} (попытка {})", delay, attempt);
+
+            tokio::select! {
+                _ = sleep(delay) => {},
+                _ = shutdown_rx.recv() => {
+                    info!("Реконнект отменен");
+                    return false;
+                }
+            }
+
+            if client.state.lock().await.is_closed {
+                return false;
+            }
+
+            if let Err(e) = client.establish(&identity).await {
+                warn!("Ошибка реконнекта (попытка {}): {}", attempt, e);
+                continue;
+            }
+            info!("Handshake после реконнекта успешен");
+
+            if has_token {
+                match client.sync().await {
+                    Ok(_) => {}
+                    Err(Error::AuthExpired(e)) => {
+                        warn!("Токен отклонен при sync после реконнекта: {}", e);
+                        client.drop_connection().await;
+                        client.emit_state(ConnectionState::Failed { reason: e.to_string() });
+                        return false;
+                    }
+                    Err(e) => {
+                        warn!("Ошибка sync после реконнекта (попытка {}): {}", attempt, e);
+                        client.drop_connection().await;
+                        continue;
+                    }
+                }
+            }
+
+            /* Обрыв после sync, но до снятия с reconnect_tasks, новую задачу не запустил */
+            {
+                let mut state = client.state.lock().await;
+                if state.writer.is_none() {
+                    continue;
+                }
+                state.reconnect_tasks -= 1;
+            }
+
+            client.emit_state(ConnectionState::Connected);
+            return true;
+        }
     }
     
     pub async fn disconnect(&self) {
@@ -188,6 +631,10 @@ impl MaxClient {
         if let Some(shutdown_tx) = state.shutdown_tx.take() {
             let _ = shutdown_tx.send(()); 
         }
+
+        if let Some(conn_shutdown_tx) = state.conn_shutdown_tx.take() {
+            let _ = conn_shutdown_tx.send(());
+        }
         
         state.writer = None;
         
@@ -197,6 +644,9 @@ impl MaxClient {
         state.user_id = None;
         state.seq = 0;
         
+        drop(state);
+        self.emit_state(ConnectionState::Disconnected);
+        
         info!("Клиент отключен, состояние сброшено.");
     }
     
@@ -204,29 +654,6 @@ impl MaxClient {
         let this = self.clone();
         
         Box::pin(async move {
-            /*let (should_reconnect, identity) = {
-                let state = this.state.lock().await;
-
-                if state.is_closed {
-                    return Err(Error::NotConnected)
-                }
-
-                if state.writer.is_none() {
-                    (true, state.identity.clone())
-                } else {
-                    (false, None)
-                }
-            };
-
-            if should_reconnect {
-                if let Some(i) = identity {
-                    debug!("Реконнект внутри send_frame...");
-                    this.connect(i, true).await?;
-                } else {
-                    return Err(Error::ConnectionFailed("Device ID not set".to_string()));
-                }
-            }*/
-
             let mut state = this.state.lock().await;
             if let Some(writer) = &mut state.writer {
                 writer.send(request).await
@@ -236,68 +663,161 @@ impl MaxClient {
         })
     }
     
+    /**
+     * Копия клиента, запросы которой (в том числе из любых методов API) выполняются с options.
+     * Соединение и состояние общие с исходным клиентом
+     */
+    pub fn with_options(&self, options: RequestOptions) -> MaxClient {
+        MaxClient {
+            options,
+            ..self.clone()
+        }
+    }
+
+    /* Копия без параметров запроса для фоновых задач */
+    fn detached(&self) -> MaxClient {
+        self.with_options(RequestOptions::default())
+    }
+
+    /**
+     * Отправляет запрос и ждет ответ. Флуд-контроль и временные ошибки
+     * повторяются по config.retry, отправка сообщений ограничена config.send_rate_limit
+     */
     pub async fn send_and_wait(
         &self,
-        opcode: u16,
+        opcode: impl Into<u16>,
+        payload: serde_json::Value,
+        cmd: u8,
+    ) -> ClientResult<Response> {
+        self.send_and_wait_with(opcode, payload, cmd, &self.options).await
+    }
+
+    /**
+     * send_and_wait с явными параметрами запроса (таймаут, отмена, приоритет)
+     */
+    pub async fn send_and_wait_with(
+        &self,
+        opcode: impl Into<u16>,
         payload: serde_json::Value,
         cmd: u8,
+        options: &RequestOptions,
     ) -> ClientResult<Response> {
+        let opcode = opcode.into();
+        let policy = self.state.lock().await.config.retry.clone();
+
+        let mut attempt = 0;
+        loop {
+            let result = match self.dispatch(opcode, payload.clone(), cmd, options).await {
+                Ok(pending) => pending.wait().await,
+                Err(e) => Err(e),
+            };
+            let error = match result {
+                Ok(response) => return Ok(response),
+                Err(e) => e,
+            };
+
+            match policy.delay_for(opcode, &error, attempt) {
+                /* После обрыва повтор имеет смысл только на новом соединении */
+                Some(_) if error.is_connection_error() => {
+                    warn!("{} для {}, повтор после реконнекта", error, Opcode::describe(opcode));
+                    attempt += 1;
+                    let reconnected = options
+                        .cancellable(self.wait_reconnected(policy.max_delay))
+                        .await
+                        .ok_or(Error::Cancelled)?;
+                    if !reconnected {
+                        return Err(error);
+                    }
+                }
+                Some(delay) => {
+                    warn!("{} для {}, повтор через {:?}", error, Opcode::describe(opcode), delay);
+                    attempt += 1;
+                    options.cancellable(sleep(delay)).await.ok_or(Error::Cancelled)?;
+                }
+                None => return Err(error),
+            }
+        }
+    }
+
+    /**
+     * Отправляет запрос, не дожидаясь ответа. Возвращает handle, который можно
+     * дождаться позже или сбросить, - так можно конвейером слать сотни отметок
+     * о прочтении или событий телеметрии. Повторы (config.retry) здесь не применяются
+     */
+    pub async fn send_no_wait(
+        &self,
+        opcode: impl Into<u16>,
+        payload: serde_json::Value,
+        cmd: u8,
+    ) -> ClientResult<PendingResponse> {
+        self.dispatch(opcode.into(), payload, cmd, &self.options).await
+    }
+
+    /* Лимит отправки, регистрация в pending и запись кадра */
+    async fn dispatch(
+        &self,
+        opcode: u16,
+        payload: serde_json::Value,
+        cmd: u8,
+        options: &RequestOptions,
+    ) -> ClientResult<PendingResponse> {
+        if options.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+
+        let limiter = self.state.lock().await.send_limiter.clone()
+            .filter(|_| Opcode::try_from(opcode).is_ok_and(Opcode::is_message_send));
+        if let Some(limiter) = limiter {
+            options.cancellable(limiter.acquire(options.priority)).await.ok_or(Error::Cancelled)?;
+        }
+
         let (tx, rx) = oneshot::channel();
         
-        let request = {
+        let (request, pending, traffic_events) = {
             let mut state = self.state.lock().await;
-            state.seq += 1;
+            /* seq в мобильном протоколе 16-битный, см. transport::codec */
+            state.seq = state.seq % u16::MAX as u64 + 1;
             let current_seq = state.seq;
             
-            state.pending.lock().unwrap().insert(current_seq, tx);
+            let entry = PendingEntry::new(opcode, tx);
+            let request_id = entry.id;
+            state.pending.lock().unwrap().insert(current_seq, entry);
             
-            Request {
+            (Request {
                 ver: 10,
                 cmd,
                 seq: current_seq,
                 opcode,
                 payload,
-            }
+            }, PendingResponse {
+                rx,
+                guard: PendingGuard { pending: state.pending.clone(), seq: current_seq, id: request_id },
+                opcode,
+                timeout: options.timeout.unwrap_or(state.config.request_timeout),
+                options: options.clone(),
+                error_codes: state.config.error_codes.clone(),
+            }, state.config.traffic_events)
         };
         
-        debug!("Отправка: {:?}", request);
-        let _ = self.event_tx.send(json!({
-            "type": "log",
-            "request": request
-        })); 
-        
-        self.send_frame(request.clone()).await?;
-        
-        match timeout(Constants::DEFAULT_TIMEOUT, rx).await {
-            Ok(Ok(Ok(response))) => {
-                trace!("Получен ответ для seq: {}", response.seq);
-                if response.payload.get("error").is_some() {
-                    Err(Error::ApiResponse(response.payload))
-                } else {
-                    Ok(response)
-                }
-            }
-            Ok(Ok(Err(e))) => Err(e),
-            Ok(Err(e)) => {
-                error!("Ошибка получения ответа (канал закрыт) для seq: {}", request.seq);
-                Err(e.into())
-            }
-            Err(_) => {
-                warn!("Таймаут запроса для seq: {}", request.seq);
-                self.state.lock().await.pending.lock().unwrap().remove(&request.seq);
-                Err(Error::RequestTimeout(Constants::DEFAULT_TIMEOUT))
-            }
+        debug!("Отправка {}: {:?}", Opcode::describe(request.opcode), request);
+        if traffic_events {
+            let _ = self.event_tx.send(Event::Request(request.clone()));
         }
+        
+        self.send_frame(request).await?;
+        Ok(pending)
     }
     
     async fn read_task(
+        client: MaxClient,
         mut reader: Box<dyn TransportReader>,
-        pending: Arc<Mutex<HashMap<u64, oneshot::Sender<ClientResult<Response>>>>>,
-        event_sender: broadcast::Sender<Value>,
+        pending: PendingMap,
+        conn_shutdown_tx: broadcast::Sender<()>,
         mut shutdown_rx: broadcast::Receiver<()>,
-        state: Arc<TokioMutex<ClientState>>,
     ) {
-        loop {
+        let event_sender = client.event_tx.clone();
+        let traffic_events = client.state.lock().await.config.traffic_events;
+        let close_reason = loop {
             tokio::select! {
                 msg_result = reader.next_message() => {
                     match msg_result {
@@ -305,73 +825,127 @@ impl MaxClient {
                             let seq = resp.seq;
                             let waiting_sender = {
                                 let mut guard = pending.lock().unwrap();
-                                guard.remove(&seq)
+                                guard.remove(&seq).map(|entry| entry.tx)
                             };
                             
                             if let Some(sender) = waiting_sender {
-                                let log = json!({
-                                    "type": "log",
-                                    "response": &resp
-                                });
-                                let _ = event_sender.send(log);
+                                if traffic_events {
+                                    let _ = event_sender.send(Event::Response(resp.clone()));
+                                }
                                 let _ = sender.send(Ok(resp));
+                            } else if resp.cmd != 0 {
+                                /* Ответ на запрос, который уже не ждут (send_no_wait, отмена, таймаут) */
+                                debug!("Ответ без ожидания {} для seq: {}", Opcode::describe(resp.opcode), seq);
+                                if traffic_events {
+                                    let _ = event_sender.send(Event::Response(resp));
+                                }
                             } else {
-                                let _ = event_sender.send(json!({
-                                    "type": "rx",
-                                    "response": resp
-                                }));
+                                debug!("Пуш {}: {:?}", Opcode::describe(resp.opcode), resp.payload);
+                                let event = Event::from_push(resp);
+                                /* Доставкой из outbox считаем только свое сообщение в том же чате */
+                                if let Event::NewMessage { chat_id, message } = &event {
+                                    if let Some(cid) = message.cid {
+                                        let user_id = client.state.lock().await.user_id;
+                                        if user_id.is_some_and(|id| message.sender == Some(id as i64)) {
+                                            client.outbox.deliver(cid, *chat_id, Some(message.clone()));
+                                        }
+                                    }
+                                }
+                                let _ = event_sender.send(event);
                             }
                         },
                         Ok(None) => {
                             info!("Соединение закрыто (EOF)");
-                            let _ = event_sender.send(json!({
-                                "type": "log",
-                                "response": "closed"
-                            })); 
-                            let mut s = state.lock().await;
-                            s.writer = None;
-                            let mut pending_guard = pending.lock().unwrap();
-                            for (_, sender) in pending_guard.drain() {
-                                let _ = sender.send(Err(Error::ConnectionClosed("Соединение закрыто".to_string())));
-                            }
-                            break;
+                            break "Соединение закрыто".to_string();
                         },
                         Err(e) => {
                             error!("Ошибка чтения транспорта:\n{}", e);
-                            let mut s = state.lock().await;
-                            s.writer = None;
-                            let mut pending_guard = pending.lock().unwrap();
-                            for (_, sender) in pending_guard.drain() {
-                                let _ = sender.send(Err(Error::ConnectionClosed(e.to_string()))); 
-                            }
-                            break;
+                            break e.to_string();
                         }
                     }
                 }
-                _ = shutdown_rx.recv() => break,
+                _ = shutdown_rx.recv() => return,
             }
+        };
+
+        client.connection_lost(&conn_shutdown_tx, close_reason).await;
+    }
+
+    /**
+     * Обработка обрыва соединения: сбрасывает writer, завершает ожидающие
+     * запросы ошибкой и, если разрешено, запускает реконнект. Во время Handshake
+     * и при уже работающем цикле реконнекта новая задача не запускается
+     */
+    async fn connection_lost(&self, conn_shutdown_tx: &broadcast::Sender<()>, reason: String) {
+        let should_reconnect = {
+            let mut s = self.state.lock().await;
+
+            let is_current = s.conn_shutdown_tx
+                .as_ref()
+                .is_some_and(|tx| tx.same_channel(conn_shutdown_tx));
+            if !is_current {
+                return;
+            }
+
+            s.writer = None;
+            s.conn_shutdown_tx = None;
+            let _ = conn_shutdown_tx.send(());
+
+            let mut pending_guard = s.pending.lock().unwrap();
+            for (_, entry) in pending_guard.drain() {
+                let _ = entry.tx.send(Err(Error::ConnectionClosed(reason.clone())));
+            }
+            drop(pending_guard);
+
+            if s.reconnect_tasks > 0 {
+                return;
+            }
+
+            let should_reconnect = !s.is_closed && s.config.auto_reconnect && s.reconnectable && !s.handshaking;
+            if should_reconnect {
+                s.reconnect_tasks += 1;
+            }
+            should_reconnect
+        };
+
+        if should_reconnect {
+            tokio::spawn(Self::reconnect_task(self.detached()));
+        } else {
+            self.emit_state(ConnectionState::Disconnected);
         }
     }
     
     async fn ping_task(
         client: MaxClient,
+        conn_shutdown_tx: broadcast::Sender<()>,
         mut shutdown_rx: broadcast::Receiver<()>,
     ) {
         info!("Ping task started");
-        let mut interval = tokio::time::interval(Constants::PING_INTERVAL);
+        let ping_interval = client.state.lock().await.config.ping_interval;
+        let mut interval = tokio::time::interval(ping_interval);
 
         loop {
             tokio::select! {
                 _ = interval.tick() => {
                     debug!("Отправка Ping...");
-                    match client.send_and_wait(1, json!({ "interactive": true }), 0).await {
+                    /* Без повторов: ошибка соединения здесь и есть сигнал обрыва */
+                    let pong = match client.send_no_wait(Opcode::Ping, json!({ "interactive": true }), 0).await {
+                        Ok(pending) => pending.wait().await,
+                        Err(e) => Err(e),
+                    };
+                    match pong {
                         Ok(_) => {
                             info!("Pong получен");
                         }
-                        Err(e) => {
+                        Err(e) if e.is_connection_error() => {
                             error!("Ошибка Ping: {}. Остановка ping_task", e);
+                            client.connection_lost(&conn_shutdown_tx, e.to_string()).await;
                             break;
                         }
+                        /* Ответ с ошибкой или локальный отказ: соединение живо */
+                        Err(e) => {
+                            warn!("Ошибка Ping: {}", e);
+                        }
                     }
                 }
                 _ = shutdown_rx.recv() => {
@@ -399,3 +973,9 @@ impl MaxClient {
         self.state.lock().await.temp_token = Some(token);
     }
 }
+
+impl Default for MaxClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,411 @@
+#[cfg(feature = "image")]
+use std::io::Cursor;
+
+#[cfg(feature = "image")]
+use image::{codecs::jpeg::JpegEncoder, imageops::FilterType, DynamicImage, ImageDecoder, ImageReader};
+
+/* Версия image, с которой работает ResizePhoto */
+#[cfg(feature = "image")]
+pub use image;
+
+#[cfg(feature = "image")]
+use crate::constants::Constants;
+use crate::errors::{ClientResult, UploadError};
+#[cfg(feature = "image")]
+use crate::errors::Error;
+
+/* Сколько первых байт файла нужно sniff_mime */
+pub const SNIFF_LEN: usize = 64;
+
+/**
+ * MIME-тип по содержимому. Контейнеры (zip, OLE) различаются только по расширению,
+ * поэтому для них используйте detect_mime
+ */
+pub fn sniff_mime(bytes: &[u8]) -> Option<&'static str> {
+    let starts = |sig: &[u8]| bytes.starts_with(sig);
+    let at = |offset: usize, sig: &[u8]| bytes.get(offset..offset + sig.len()) == Some(sig);
+
+    if starts(b"\xFF\xD8\xFF") {
+        return Some("image/jpeg");
+    }
+    if starts(b"\x89PNG\r\n\x1A\n") {
+        return Some("image/png");
+    }
+    if starts(b"GIF87a") || starts(b"GIF89a") {
+        return Some("image/gif");
+    }
+    if starts(b"BM") && bytes.len() >= 14 {
+        return Some("image/bmp");
+    }
+    if starts(b"II*\0") || starts(b"MM\0*") {
+        return Some("image/tiff");
+    }
+    if starts(b"RIFF") {
+        return match bytes.get(8..12)? {
+            b"WEBP" => Some("image/webp"),
+            b"WAVE" => Some("audio/wav"),
+            b"AVI " => Some("video/x-msvideo"),
+            _ => None,
+        };
+    }
+    if at(4, b"ftyp") {
+        return ftyp_mime(bytes.get(8..12)?);
+    }
+    if starts(b"\x1A\x45\xDF\xA3") {
+        let webm = bytes.windows(4).any(|w| w == b"webm");
+        return Some(if webm { "video/webm" } else { "video/x-matroska" });
+    }
+    if starts(b"OggS") {
+        return Some("audio/ogg");
+    }
+    if starts(b"fLaC") {
+        return Some("audio/flac");
+    }
+    if starts(b"ID3") || mp3_frame(bytes) {
+        return Some("audio/mpeg");
+    }
+    if starts(b"%PDF-") {
+        return Some("application/pdf");
+    }
+    if starts(b"PK\x03\x04") {
+        return Some("application/zip");
+    }
+    if starts(b"Rar!\x1A\x07") {
+        return Some("application/vnd.rar");
+    }
+    if starts(b"7z\xBC\xAF\x27\x1C") {
+        return Some("application/x-7z-compressed");
+    }
+    if starts(b"\x1F\x8B") {
+        return Some("application/gzip");
+    }
+    if starts(b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1") {
+        return Some("application/x-ole-storage");
+    }
+    None
+}
+
+/* ISO BMFF (MP4, MOV, HEIF): тип по major brand */
+fn ftyp_mime(brand: &[u8]) -> Option<&'static str> {
+    match brand {
+        b"heic" | b"heix" | b"heim" | b"heis" => Some("image/heic"),
+        b"mif1" | b"msf1" => Some("image/heif"),
+        b"avif" | b"avis" => Some("image/avif"),
+        b"qt  " => Some("video/quicktime"),
+        b"M4A " | b"M4B " => Some("audio/mp4"),
+        b"3gp4" | b"3gp5" | b"3gp6" | b"3g2a" => Some("video/3gpp"),
+        _ => Some("video/mp4"),
+    }
+}
+
+/**
+ * MIME-тип по расширению имени файла
+ */
+pub fn mime_from_extension(file_name: &str) -> Option<&'static str> {
+    let (_, ext) = file_name.rsplit_once('.')?;
+    let mime = match ext.to_ascii_lowercase().as_str() {
+        "jpg" | "jpeg" | "jfif" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "heic" => "image/heic",
+        "heif" => "image/heif",
+        "avif" => "image/avif",
+        "bmp" => "image/bmp",
+        "tif" | "tiff" => "image/tiff",
+        "mp4" | "m4v" => "video/mp4",
+        "mov" => "video/quicktime",
+        "webm" => "video/webm",
+        "mkv" => "video/x-matroska",
+        "avi" => "video/x-msvideo",
+        "3gp" => "video/3gpp",
+        "mp3" => "audio/mpeg",
+        "m4a" => "audio/mp4",
+        "ogg" | "oga" | "opus" => "audio/ogg",
+        "flac" => "audio/flac",
+        "wav" => "audio/wav",
+        "pdf" => "application/pdf",
+        "doc" => "application/msword",
+        "xls" => "application/vnd.ms-excel",
+        "ppt" => "application/vnd.ms-powerpoint",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        "pptx" => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+        "odt" => "application/vnd.oasis.opendocument.text",
+        "zip" => "application/zip",
+        "rar" => "application/vnd.rar",
+        "7z" => "application/x-7z-compressed",
+        "gz" => "application/gzip",
+        "txt" => "text/plain",
+        "csv" => "text/csv",
+        "json" => "application/json",
+        _ => return None,
+    };
+    Some(mime)
+}
+
+/**
+ * MIME-тип по содержимому, а если сигнатура неизвестна или это контейнер
+ * (docx внутри zip, doc внутри OLE) - по расширению
+ */
+pub fn detect_mime(bytes: &[u8], file_name: &str) -> Option<&'static str> {
+    match sniff_mime(bytes) {
+        Some("application/zip" | "application/x-ole-storage") | None => {
+            mime_from_extension(file_name).or_else(|| sniff_mime(bytes))
+        }
+        sniffed => sniffed,
+    }
+}
+
+/**
+ * Расширение для имени файла при загрузке
+ */
+pub fn extension_for(mime: &str) -> &str {
+    match mime {
+        "image/jpeg" => "jpg",
+        "image/svg+xml" => "svg",
+        "video/quicktime" => "mov",
+        "video/x-matroska" => "mkv",
+        "video/x-msvideo" => "avi",
+        "audio/mpeg" => "mp3",
+        _ => mime.rsplit('/').next().unwrap_or("bin"),
+    }
+}
+
+pub fn is_image(mime: &str) -> bool {
+    mime.starts_with("image/")
+}
+
+pub fn is_video(mime: &str) -> bool {
+    mime.starts_with("video/")
+}
+
+/* Форматы фото, которые принимает сервер загрузки. HEIC, AVIF, TIFF и прочие нужно перекодировать */
+pub const UPLOAD_PHOTO_TYPES: [&str; 4] = ["image/jpeg", "image/png", "image/gif", "image/webp"];
+
+pub fn is_uploadable_photo(mime: &str) -> bool {
+    UPLOAD_PHOTO_TYPES.contains(&mime)
+}
+
+/**
+ * Фото перед отправкой на сервер загрузки
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PhotoData {
+    pub bytes: Vec<u8>,
+    pub mime: String,
+}
+
+/**
+ * Предобработка фото перед upload_photo: уменьшение до лимитов сервера, удаление EXIF,
+ * перекодирование (например, HEIC в JPEG). Вызывается в spawn_blocking, поэтому может
+ * декодировать изображение синхронно. Подключается через MaxClientBuilder::photo_preprocessor.
+ * Готовые обработчики: StripExif и ResizePhoto (feature "image"), свой - любая функция
+ *
+ * let client = MaxClient::builder()
+ *     .photo_preprocessor(|photo: PhotoData| -> ClientResult<PhotoData> {
+ *         if photo.mime != "image/heic" {
+ *             return Ok(photo);
+ *         }
+ *         let bytes = heic_to_jpeg(&photo.bytes)?;
+ *         Ok(PhotoData { bytes, mime: "image/jpeg".into() })
+ *     })
+ *     .build();
+ */
+pub trait PhotoPreprocessor: Send + Sync {
+    fn process(&self, photo: PhotoData) -> ClientResult<PhotoData>;
+}
+
+impl<F> PhotoPreprocessor for F
+where
+    F: Fn(PhotoData) -> ClientResult<PhotoData> + Send + Sync,
+{
+    fn process(&self, photo: PhotoData) -> ClientResult<PhotoData> {
+        self(photo)
+    }
+}
+
+/**
+ * Удаляет из JPEG метаданные APP1 (EXIF с геолокацией, XMP) без перекодирования.
+ * Из EXIF остается только Orientation, иначе повернутые камерой фото лягут набок.
+ * JPEG, структуру которого не удалось разобрать, отклоняется с UploadError::Preprocess,
+ * чтобы не отправить его с метаданными. Остальные форматы не меняет
+ */
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StripExif;
+
+impl PhotoPreprocessor for StripExif {
+    fn process(&self, photo: PhotoData) -> ClientResult<PhotoData> {
+        if photo.mime != "image/jpeg" {
+            return Ok(photo);
+        }
+
+        match strip_jpeg_app1(&photo.bytes) {
+            Some(bytes) => Ok(PhotoData { bytes, mime: photo.mime }),
+            None => Err(UploadError::Preprocess("не удалось разобрать JPEG, EXIF не удален".into()).into()),
+        }
+    }
+}
+
+/**
+ * Уменьшает фото до max_side по большей стороне и перекодирует в JPEG. Поворот из
+ * EXIF Orientation применяется к пикселям, метаданные и прозрачность не сохраняются.
+ * GIF не трогается, чтобы не потерять анимацию. HEIC и AVIF не декодируются
+ *
+ * let client = MaxClient::builder().photo_preprocessor(ResizePhoto::new(1920)).build();
+ */
+#[cfg(feature = "image")]
+#[derive(Debug, Clone, Copy)]
+pub struct ResizePhoto {
+    pub max_side: u32,
+    /* Качество JPEG, 1-100 */
+    pub quality: u8,
+}
+
+#[cfg(feature = "image")]
+impl Default for ResizePhoto {
+    fn default() -> Self {
+        Self { max_side: Constants::PHOTO_MAX_SIDE, quality: Constants::PHOTO_JPEG_QUALITY }
+    }
+}
+
+#[cfg(feature = "image")]
+impl ResizePhoto {
+    pub fn new(max_side: u32) -> Self {
+        Self { max_side, ..Self::default() }
+    }
+
+    pub fn quality(mut self, v: u8) -> Self {
+        self.quality = v.clamp(1, 100);
+        self
+    }
+}
+
+#[cfg(feature = "image")]
+impl PhotoPreprocessor for ResizePhoto {
+    fn process(&self, photo: PhotoData) -> ClientResult<PhotoData> {
+        if photo.mime == "image/gif" {
+            return Ok(photo);
+        }
+        let image_error = |e: image::ImageError| -> Error {
+            UploadError::UnsupportedType(format!("{}: {}", photo.mime, e)).into()
+        };
+
+        let mut decoder = ImageReader::new(Cursor::new(&photo.bytes))
+            .with_guessed_format()?
+            .into_decoder()
+            .map_err(image_error)?;
+        let orientation = decoder.orientation().map_err(image_error)?;
+        let mut image = DynamicImage::from_decoder(decoder).map_err(image_error)?;
+        image.apply_orientation(orientation);
+
+        if image.width().max(image.height()) > self.max_side {
+            image = image.resize(self.max_side, self.max_side, FilterType::Lanczos3);
+        }
+
+        let mut bytes = Vec::new();
+        JpegEncoder::new_with_quality(&mut bytes, self.quality)
+            .encode_image(&image.to_rgb8())
+            .map_err(image_error)?;
+        Ok(PhotoData { bytes, mime: "image/jpeg".into() })
+    }
+}
+
+/* Копирует JPEG без сегментов APP1, EXIF заменяется минимальным с одним Orientation. None - структура не распознана */
+/**
+ * Заголовок кадра MPEG audio без ID3: синхрослово, версия и слой не зарезервированы,
+ * допустимые индексы битрейта и частоты. FF FE и FF FF - BOM UTF-16LE и заполнение, не MP3
+ */
+fn mp3_frame(bytes: &[u8]) -> bool {
+    let [0xFF, b1, b2, ..] = *bytes else {
+        return false;
+    };
+    let version = (b1 >> 3) & 0x03;
+    let layer = (b1 >> 1) & 0x03;
+    let bitrate = b2 >> 4;
+    let sample_rate = (b2 >> 2) & 0x03;
+
+    b1 & 0xE0 == 0xE0 && b1 < 0xFE && version != 0x01 && layer != 0 && bitrate != 0x0F && sample_rate != 0x03
+}
+
+fn strip_jpeg_app1(bytes: &[u8]) -> Option<Vec<u8>> {
+    if !bytes.starts_with(b"\xFF\xD8") {
+        return None;
+    }
+
+    let mut out = Vec::with_capacity(bytes.len());
+    out.extend_from_slice(b"\xFF\xD8");
+    let mut pos = 2;
+
+    while pos + 4 <= bytes.len() {
+        if bytes[pos] != 0xFF {
+            return None;
+        }
+        /* Перед маркером может быть сколько угодно байт заполнения 0xFF */
+        if bytes[pos + 1] == 0xFF {
+            pos += 1;
+            continue;
+        }
+        let marker = bytes[pos + 1];
+        /* SOS: дальше сжатые данные до конца файла */
+        if marker == 0xDA {
+            break;
+        }
+        let len = u16::from_be_bytes([bytes[pos + 2], bytes[pos + 3]]) as usize;
+        let end = pos + 2 + len;
+        if len < 2 || end > bytes.len() {
+            return None;
+        }
+        if marker != 0xE1 {
+            out.extend_from_slice(&bytes[pos..end]);
+        } else if let Some(orientation) = exif_orientation(&bytes[pos + 4..end]).filter(|&o| o != 1) {
+            out.extend_from_slice(&orientation_app1(orientation));
+        }
+        pos = end;
+    }
+
+    out.extend_from_slice(&bytes[pos..]);
+    Some(out)
+}
+
+/* Orientation (тег 0x0112) из IFD0 содержимого APP1 "Exif\0\0" + TIFF */
+fn exif_orientation(app1: &[u8]) -> Option<u16> {
+    let tiff = app1.strip_prefix(b"Exif\0\0")?;
+    let little_endian = match tiff.get(..4)? {
+        b"II*\0" => true,
+        b"MM\0*" => false,
+        _ => return None,
+    };
+    let u16_at = |offset: usize| {
+        let raw = [*tiff.get(offset)?, *tiff.get(offset + 1)?];
+        Some(if little_endian { u16::from_le_bytes(raw) } else { u16::from_be_bytes(raw) })
+    };
+    let u32_at = |offset: usize| {
+        let raw: [u8; 4] = tiff.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if little_endian { u32::from_le_bytes(raw) } else { u32::from_be_bytes(raw) })
+    };
+
+    let ifd = u32_at(4)? as usize;
+    let count = u16_at(ifd)? as usize;
+    (0..count)
+        .map(|i| ifd + 2 + i * 12)
+        .find(|&entry| u16_at(entry) == Some(0x0112))
+        .and_then(|entry| u16_at(entry + 8))
+        .filter(|orientation| (1..=8).contains(orientation))
+}
+
+/* Сегмент APP1 с EXIF из одного тега Orientation (big-endian TIFF) */
+fn orientation_app1(orientation: u16) -> Vec<u8> {
+    let mut segment = Vec::with_capacity(36);
+    segment.extend_from_slice(b"\xFF\xE1\x00\x22Exif\0\0");
+    /* Заголовок TIFF и IFD0 сразу за ним */
+    segment.extend_from_slice(b"MM\0*\0\0\0\x08");
+    segment.extend_from_slice(&1u16.to_be_bytes());
+    /* Тег 0x0112, тип SHORT, одно значение */
+    segment.extend_from_slice(b"\x01\x12\x00\x03\0\0\0\x01");
+    segment.extend_from_slice(&orientation.to_be_bytes());
+    segment.extend_from_slice(&[0, 0]);
+    /* Следующего IFD нет */
+    segment.extend_from_slice(&[0, 0, 0, 0]);
+    segment
+}
@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::common::de_opt_id;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Call {
+    #[serde(default)]
+    pub conversation_id: Option<String>,
+
+    #[serde(default, deserialize_with = "de_opt_id")]
+    pub chat_id: Option<i64>,
+
+    #[serde(default, deserialize_with = "de_opt_id")]
+    pub caller_id: Option<i64>,
+
+    #[serde(default)]
+    pub call_type: Option<String>,
+
+    #[serde(default)]
+    pub time: Option<i64>,
+
+    #[serde(default)]
+    pub duration: Option<i64>,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+use super::common::{de_id, de_id_map, de_ids, de_opt_id};
+use super::{Contact, Message, Presence};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Chat {
+    #[serde(deserialize_with = "de_id")]
+    pub id: i64,
+
+    #[serde(default, rename = "type")]
+    pub kind: Option<String>,
+
+    #[serde(default)]
+    pub status: Option<String>,
+
+    #[serde(default)]
+    pub title: Option<String>,
+
+    #[serde(default)]
+    pub description: Option<String>,
+
+    #[serde(default, deserialize_with = "de_opt_id")]
+    pub owner: Option<i64>,
+
+    #[serde(default)]
+    pub link: Option<String>,
+
+    #[serde(default)]
+    pub base_icon_url: Option<String>,
+
+    /* userId -> время последнего прочтения */
+    #[serde(default, deserialize_with = "de_id_map")]
+    pub participants: HashMap<String, i64>,
+
+    #[serde(default)]
+    pub participants_count: Option<i64>,
+
+    #[serde(default, deserialize_with = "de_ids")]
+    pub admins: Vec<i64>,
+
+    #[serde(default)]
+    pub last_message: Option<Message>,
+
+    #[serde(default)]
+    pub last_event_time: Option<i64>,
+
+    #[serde(default)]
+    pub created: Option<i64>,
+
+    #[serde(default)]
+    pub modified: Option<i64>,
+
+    #[serde(default)]
+    pub options: Map<String, Value>,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Member {
+    pub contact: Contact,
+
+    #[serde(default)]
+    pub presence: Option<Presence>,
+
+    #[serde(default)]
+    pub read_mark: Option<i64>,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MembersPage {
+    #[serde(default)]
+    pub members: Vec<Member>,
+
+    #[serde(default)]
+    pub marker: Option<i64>,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkInfo {
+    #[serde(default)]
+    pub chat: Option<Chat>,
+
+    #[serde(default)]
+    pub contact: Option<Contact>,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResult {
+    #[serde(default)]
+    pub result: Vec<SearchItem>,
+
+    #[serde(default)]
+    pub total: Option<i64>,
+
+    #[serde(default)]
+    pub marker: Option<Value>,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchItem {
+    #[serde(default, deserialize_with = "de_opt_id")]
+    pub chat_id: Option<i64>,
+
+    #[serde(default)]
+    pub chat: Option<Chat>,
+
+    #[serde(default)]
+    pub contact: Option<Contact>,
+
+    #[serde(default)]
+    pub message: Option<Message>,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
@@ -1,4 +1,8 @@
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::errors::ClientResult;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Request {
@@ -19,6 +23,76 @@ pub struct Response {
     pub payload: serde_json::Value,
 }
 
+impl Response {
+    /**
+     * Разбирает весь payload в модель
+     */
+    pub fn parse<T: DeserializeOwned>(self) -> ClientResult<T> {
+        Ok(serde_json::from_value(self.payload)?)
+    }
+
+    /**
+     * Разбирает поле payload[key] в модель
+     */
+    pub fn take<T: DeserializeOwned>(mut self, key: &'static str) -> ClientResult<T> {
+        match self.payload.get_mut(key).map(Value::take) {
+            Some(value) => Ok(serde_json::from_value(value)?),
+            None => Err(<serde_json::Error as serde::de::Error>::missing_field(key).into()),
+        }
+    }
+
+    /**
+     * Как take, но отсутствующее или null поле дает None
+     */
+    pub fn take_opt<T: DeserializeOwned>(mut self, key: &'static str) -> ClientResult<Option<T>> {
+        match self.payload.get_mut(key).map(Value::take) {
+            Some(Value::Null) | None => Ok(None),
+            Some(value) => Ok(Some(serde_json::from_value(value)?)),
+        }
+    }
+}
+
+/* Большие id (> 2^53) в мобильном транспорте приходят строками, см. msgpack_to_json */
+pub fn value_as_id(value: &Value) -> Option<i64> {
+    match value {
+        Value::Number(n) => n.as_i64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn invalid_id<E: serde::de::Error>(value: &Value) -> E {
+    E::custom(format!("invalid id: {}", value))
+}
+
+/* id числом или строкой */
+pub fn de_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i64, D::Error> {
+    let value = Value::deserialize(deserializer)?;
+    value_as_id(&value).ok_or_else(|| invalid_id(&value))
+}
+
+/* Необязательный id: null и нераспознанное значение дают None */
+pub fn de_opt_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<i64>, D::Error> {
+    let value = Option::<Value>::deserialize(deserializer)?;
+    Ok(value.as_ref().and_then(value_as_id))
+}
+
+/* Список id, каждый числом или строкой */
+pub fn de_ids<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<i64>, D::Error> {
+    Vec::<Value>::deserialize(deserializer)?
+        .iter()
+        .map(|value| value_as_id(value).ok_or_else(|| invalid_id(value)))
+        .collect()
+}
+
+/* Словарь с числовыми значениями, пришедшими числом или строкой (participants) */
+pub fn de_id_map<'de, D: Deserializer<'de>>(deserializer: D) -> Result<HashMap<String, i64>, D::Error> {
+    HashMap::<String, Value>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(key, value)| value_as_id(&value).map(|id| (key, id)).ok_or_else(|| invalid_id(&value)))
+        .collect()
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Identity {
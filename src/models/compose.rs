@@ -0,0 +1,209 @@
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use super::ItemType;
+
+/**
+ * Тип форматирования текста (elements в MSG_SEND)
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ElementType {
+    Strong,
+    Emphasized,
+    Underline,
+    Strikethrough,
+    Monospaced,
+    Link,
+    UserMention,
+}
+
+/**
+ * Вложение исходящего сообщения: токен или id, полученные после загрузки
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "_type", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OutgoingAttachment {
+    #[serde(rename_all = "camelCase")]
+    Photo { photo_token: String },
+    #[serde(rename_all = "camelCase")]
+    Video { video_id: i64, token: String },
+    #[serde(rename_all = "camelCase")]
+    File { file_id: i64 },
+    #[serde(rename_all = "camelCase")]
+    Sticker { sticker_id: i64 },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OutgoingElement {
+    #[serde(rename = "type")]
+    kind: ElementType,
+    from: usize,
+    length: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    entity_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attributes: Option<Value>,
+}
+
+/**
+ * Сообщение для MSG_SEND (опкод 64). Текст собирается по частям,
+ * смещения форматирования считаются в UTF-16, как у веб-клиента
+ *
+ * let message = MessageBuilder::new(chat_id)
+ *     .text("Привет, ")
+ *     .mention(user_id, "Иван")
+ *     .text("! ")
+ *     .bold("Важно")
+ *     .reply_to(message_id)
+ *     .silent();
+ * client.send(message).await?;
+ */
+#[derive(Debug, Clone)]
+pub struct MessageBuilder {
+    chat_id: i64,
+    text: String,
+    utf16_len: usize,
+    elements: Vec<OutgoingElement>,
+    attaches: Vec<OutgoingAttachment>,
+    link: Option<Value>,
+    notify: bool,
+    send_at: Option<i64>,
+    pub(crate) cid: Option<i64>,
+}
+
+impl MessageBuilder {
+    pub fn new(chat_id: i64) -> Self {
+        Self {
+            chat_id,
+            text: String::new(),
+            utf16_len: 0,
+            elements: Vec::new(),
+            attaches: Vec::new(),
+            link: None,
+            notify: true,
+            send_at: None,
+            cid: None,
+        }
+    }
+
+    pub fn chat_id(&self) -> i64 {
+        self.chat_id
+    }
+
+    /* Обычный текст без форматирования */
+    pub fn text(mut self, text: &str) -> Self {
+        self.push_text(text);
+        self
+    }
+
+    pub fn bold(self, text: &str) -> Self {
+        self.styled(ElementType::Strong, text, None, None)
+    }
+
+    pub fn italic(self, text: &str) -> Self {
+        self.styled(ElementType::Emphasized, text, None, None)
+    }
+
+    pub fn underline(self, text: &str) -> Self {
+        self.styled(ElementType::Underline, text, None, None)
+    }
+
+    pub fn strikethrough(self, text: &str) -> Self {
+        self.styled(ElementType::Strikethrough, text, None, None)
+    }
+
+    pub fn code(self, text: &str) -> Self {
+        self.styled(ElementType::Monospaced, text, None, None)
+    }
+
+    pub fn link(self, text: &str, url: &str) -> Self {
+        self.styled(ElementType::Link, text, None, Some(json!({ "url": url })))
+    }
+
+    /* Упоминание пользователя, text - отображаемое имя */
+    pub fn mention(self, user_id: i64, text: &str) -> Self {
+        self.styled(ElementType::UserMention, text, Some(user_id), None)
+    }
+
+    /* Ответ на сообщение из того же чата */
+    pub fn reply_to(mut self, message_id: i64) -> Self {
+        self.link = Some(json!({ "type": "REPLY", "messageId": message_id }));
+        self
+    }
+
+    /* Пересылка сообщения из другого чата */
+    pub fn forward(mut self, chat_id: i64, message_id: i64) -> Self {
+        self.link = Some(json!({ "type": "FORWARD", "chatId": chat_id, "messageId": message_id }));
+        self
+    }
+
+    pub fn attach(mut self, attachment: OutgoingAttachment) -> Self {
+        self.attaches.push(attachment);
+        self
+    }
+
+    /* Без уведомления получателей (notify: false) */
+    pub fn silent(self) -> Self {
+        self.notify(false)
+    }
+
+    pub fn notify(mut self, notify: bool) -> Self {
+        self.notify = notify;
+        self
+    }
+
+    /* Отложенное сообщение (ItemType::Delayed), send_at - время отправки в мс */
+    pub fn delayed(mut self, send_at: i64) -> Self {
+        self.send_at = Some(send_at);
+        self
+    }
+
+    /* Свой cid вместо сгенерированного клиентом */
+    pub fn cid(mut self, cid: i64) -> Self {
+        self.cid = Some(cid);
+        self
+    }
+
+    fn push_text(&mut self, text: &str) -> (usize, usize) {
+        let from = self.utf16_len;
+        let length = text.encode_utf16().count();
+        self.text.push_str(text);
+        self.utf16_len += length;
+        (from, length)
+    }
+
+    fn styled(mut self, kind: ElementType, text: &str, entity_id: Option<i64>, attributes: Option<Value>) -> Self {
+        let (from, length) = self.push_text(text);
+        if length > 0 {
+            self.elements.push(OutgoingElement { kind, from, length, entity_id, attributes });
+        }
+        self
+    }
+
+    /**
+     * Payload для MSG_SEND
+     */
+    pub fn payload(&self, cid: i64) -> Value {
+        let mut message = Map::new();
+        message.insert("text".into(), json!(self.text));
+        message.insert("cid".into(), json!(cid));
+        message.insert("elements".into(), json!(self.elements));
+        message.insert("attaches".into(), json!(self.attaches));
+        if let Some(link) = &self.link {
+            message.insert("link".into(), link.clone());
+        }
+
+        let mut payload = Map::new();
+        payload.insert("chatId".into(), json!(self.chat_id));
+        payload.insert("message".into(), Value::Object(message));
+        payload.insert("notify".into(), json!(self.notify));
+        if let Some(send_at) = self.send_at {
+            payload.insert("itemType".into(), json!(ItemType::Delayed));
+            payload.insert("delayedAttributes".into(), json!({ "timeToFire": send_at }));
+        }
+
+        Value::Object(payload)
+    }
+}
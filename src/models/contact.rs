@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::common::{de_id, de_opt_id};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Contact {
+    #[serde(deserialize_with = "de_id")]
+    pub id: i64,
+
+    #[serde(default)]
+    pub names: Vec<Name>,
+
+    #[serde(default)]
+    pub phone: Option<i64>,
+
+    #[serde(default)]
+    pub description: Option<String>,
+
+    #[serde(default)]
+    pub link: Option<String>,
+
+    #[serde(default)]
+    pub base_url: Option<String>,
+
+    #[serde(default)]
+    pub base_raw_url: Option<String>,
+
+    #[serde(default, deserialize_with = "de_opt_id")]
+    pub photo_id: Option<i64>,
+
+    #[serde(default)]
+    pub account_status: Option<i64>,
+
+    #[serde(default)]
+    pub update_time: Option<i64>,
+
+    #[serde(default)]
+    pub options: Vec<String>,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Contact {
+    /**
+     * Отображаемое имя (первое из names)
+     */
+    pub fn display_name(&self) -> Option<&str> {
+        self.names.first().map(|n| n.name.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Name {
+    #[serde(default)]
+    pub name: String,
+
+    #[serde(default)]
+    pub first_name: Option<String>,
+
+    #[serde(default)]
+    pub last_name: Option<String>,
+
+    #[serde(default, rename = "type")]
+    pub kind: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+    pub contact: Contact,
+
+    #[serde(default)]
+    pub profile_options: Vec<Value>,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Presence {
+    #[serde(default)]
+    pub seen: Option<i64>,
+
+    #[serde(default)]
+    pub status: Option<String>,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
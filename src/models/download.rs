@@ -0,0 +1,113 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::constants::Constants;
+use super::{ProgressCallback, TransferProgress};
+
+/**
+ * Параметры скачивания (download, download_to_path, download_attachment)
+ *
+ * let options = DownloadOptions::new()
+ *     .size(attach.size.unwrap_or_default())
+ *     .on_progress(|p| println!("{} байт", p.transferred));
+ * client.download_attachment(chat_id, message_id, &attach, "video.mp4", &options).await?;
+ */
+#[derive(Clone)]
+pub struct DownloadOptions {
+    /* С какого байта продолжать запись в AsyncWrite (download_to_path берет размер файла сам) */
+    pub offset: u64,
+    /**
+     * Докачивать ли уже существующий файл в download_to_path. По умолчанию выключено:
+     * сервер не сообщает, тот ли это файл, и чужое начало склеилось бы с новым хвостом.
+     * Включайте только для своих недокачанных файлов, лучше вместе с sha256
+     */
+    pub resume: bool,
+    /* Ожидаемый размер, иначе сверяется с размером из ответа сервера */
+    pub expected_size: Option<u64>,
+    /* Ожидаемый SHA-256 в hex */
+    pub sha256: Option<String>,
+    /* Сколько раз подряд переподключаться после обрыва */
+    pub max_retries: u32,
+    pub retry_delay: Duration,
+    pub progress: Option<ProgressCallback>,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            resume: false,
+            expected_size: None,
+            sha256: None,
+            max_retries: Constants::RETRY_MAX_ATTEMPTS,
+            retry_delay: Constants::RETRY_BASE_DELAY,
+            progress: None,
+        }
+    }
+}
+
+impl fmt::Debug for DownloadOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DownloadOptions")
+            .field("offset", &self.offset)
+            .field("resume", &self.resume)
+            .field("expected_size", &self.expected_size)
+            .field("sha256", &self.sha256)
+            .field("max_retries", &self.max_retries)
+            .field("retry_delay", &self.retry_delay)
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
+}
+
+impl DownloadOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn offset(mut self, v: u64) -> Self {
+        self.offset = v;
+        self
+    }
+
+    pub fn resume(mut self, v: bool) -> Self {
+        self.resume = v;
+        self
+    }
+
+    pub fn size(mut self, v: u64) -> Self {
+        self.expected_size = Some(v);
+        self
+    }
+
+    pub fn sha256(mut self, hex: &str) -> Self {
+        self.sha256 = Some(hex.to_ascii_lowercase());
+        self
+    }
+
+    pub fn max_retries(mut self, v: u32) -> Self {
+        self.max_retries = v;
+        self
+    }
+
+    pub fn retry_delay(mut self, v: Duration) -> Self {
+        self.retry_delay = v;
+        self
+    }
+
+    /* Вызывается после каждого записанного куска */
+    pub fn on_progress<F>(mut self, f: F) -> Self
+    where
+        F: Fn(TransferProgress) + Send + Sync + 'static,
+    {
+        self.progress = Some(Arc::new(f));
+        self
+    }
+
+    pub(crate) fn report(&self, transferred: u64, total: Option<u64>) {
+        if let Some(progress) = &self.progress {
+            progress(TransferProgress { transferred, total });
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::common::{de_id, de_opt_id};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Message {
+    #[serde(deserialize_with = "de_id")]
+    pub id: i64,
+
+    #[serde(default)]
+    pub time: i64,
+
+    #[serde(default, deserialize_with = "de_opt_id")]
+    pub sender: Option<i64>,
+
+    #[serde(default)]
+    pub text: String,
+
+    #[serde(default, deserialize_with = "de_opt_id")]
+    pub cid: Option<i64>,
+
+    #[serde(default, rename = "type")]
+    pub kind: Option<String>,
+
+    #[serde(default)]
+    pub status: Option<String>,
+
+    #[serde(default)]
+    pub update_time: Option<i64>,
+
+    #[serde(default)]
+    pub elements: Vec<Element>,
+
+    #[serde(default)]
+    pub attaches: Vec<Attachment>,
+
+    #[serde(default)]
+    pub link: Option<MessageLink>,
+
+    #[serde(default)]
+    pub reaction_info: Option<ReactionInfo>,
+
+    /* Все поля, которые не описаны в модели */
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Element {
+    #[serde(rename = "type")]
+    pub kind: String,
+
+    #[serde(default)]
+    pub from: i64,
+
+    #[serde(default)]
+    pub length: i64,
+
+    #[serde(default)]
+    pub attributes: Option<Value>,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageLink {
+    #[serde(rename = "type")]
+    pub kind: String,
+
+    #[serde(default, deserialize_with = "de_opt_id")]
+    pub chat_id: Option<i64>,
+
+    #[serde(default, deserialize_with = "de_opt_id")]
+    pub message_id: Option<i64>,
+
+    #[serde(default)]
+    pub message: Option<Box<Message>>,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AttachmentType {
+    Photo,
+    Video,
+    File,
+    Audio,
+    Sticker,
+    Control,
+    Contact,
+    Share,
+    InlineKeyboard,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    #[serde(rename = "_type")]
+    pub kind: AttachmentType,
+
+    #[serde(default, deserialize_with = "de_opt_id")]
+    pub photo_id: Option<i64>,
+
+    #[serde(default, deserialize_with = "de_opt_id")]
+    pub video_id: Option<i64>,
+
+    #[serde(default, deserialize_with = "de_opt_id")]
+    pub file_id: Option<i64>,
+
+    #[serde(default)]
+    pub base_url: Option<String>,
+
+    #[serde(default)]
+    pub token: Option<String>,
+
+    #[serde(default)]
+    pub photo_token: Option<String>,
+
+    #[serde(default)]
+    pub name: Option<String>,
+
+    #[serde(default)]
+    pub size: Option<u64>,
+
+    #[serde(default)]
+    pub width: Option<u32>,
+
+    #[serde(default)]
+    pub height: Option<u32>,
+
+    #[serde(default)]
+    pub duration: Option<u64>,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReactionInfo {
+    #[serde(default)]
+    pub total_count: i64,
+
+    #[serde(default)]
+    pub counters: Vec<Reaction>,
+
+    #[serde(default)]
+    pub your_reaction: Option<String>,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reaction {
+    pub reaction: String,
+
+    #[serde(default)]
+    pub count: i64,
+}
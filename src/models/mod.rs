@@ -1,5 +1,25 @@
+mod call;
+mod chat;
 mod common;
+mod compose;
+mod contact;
+mod download;
 mod history;
+mod message;
+mod opcode;
+mod session;
+mod transfer;
+mod upload;
 
+pub use call::*;
+pub use chat::*;
 pub use common::*;
+pub use compose::*;
+pub use contact::*;
+pub use download::*;
 pub use history::*;
+pub use message::*;
+pub use opcode::*;
+pub use session::*;
+pub use transfer::*;
+pub use upload::*;
@@ -0,0 +1,186 @@
+use std::fmt;
+
+macro_rules! opcodes {
+    ($($variant:ident = $code:literal => $name:literal,)*) => {
+        /**
+         * Опкоды протокола Max
+         */
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #[repr(u16)]
+        pub enum Opcode {
+            $($variant = $code,)*
+        }
+
+        impl Opcode {
+            /* Имя опкода для логов, например MSG_SEND */
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(Opcode::$variant => $name,)*
+                }
+            }
+        }
+
+        impl TryFrom<u16> for Opcode {
+            type Error = u16;
+
+            fn try_from(code: u16) -> Result<Self, Self::Error> {
+                match code {
+                    $($code => Ok(Opcode::$variant),)*
+                    unknown => Err(unknown),
+                }
+            }
+        }
+    };
+}
+
+opcodes! {
+    Ping = 1 => "PING",
+    Debug = 2 => "DEBUG",
+    Reconnect = 3 => "RECONNECT",
+    Log = 5 => "LOG",
+    SessionInit = 6 => "SESSION_INIT",
+    Profile = 16 => "PROFILE",
+    AuthRequest = 17 => "AUTH_REQUEST",
+    Auth = 18 => "AUTH",
+    Login = 19 => "LOGIN",
+    Logout = 20 => "LOGOUT",
+    Sync = 21 => "SYNC",
+    Config = 22 => "CONFIG",
+    AuthConfirm = 23 => "AUTH_CONFIRM",
+    ContactInfo = 32 => "CONTACT_INFO",
+    ContactAdd = 33 => "CONTACT_ADD",
+    ContactUpdate = 34 => "CONTACT_UPDATE",
+    ContactPresence = 35 => "CONTACT_PRESENCE",
+    ContactList = 36 => "CONTACT_LIST",
+    ContactSearch = 37 => "CONTACT_SEARCH",
+    ContactInfoByPhone = 46 => "CONTACT_INFO_BY_PHONE",
+    ChatInfo = 48 => "CHAT_INFO",
+    ChatHistory = 49 => "CHAT_HISTORY",
+    ChatMark = 50 => "CHAT_MARK",
+    ChatMedia = 51 => "CHAT_MEDIA",
+    ChatDelete = 52 => "CHAT_DELETE",
+    ChatsList = 53 => "CHATS_LIST",
+    ChatClear = 54 => "CHAT_CLEAR",
+    ChatUpdate = 55 => "CHAT_UPDATE",
+    ChatCheckLink = 56 => "CHAT_CHECK_LINK",
+    ChatJoin = 57 => "CHAT_JOIN",
+    ChatLeave = 58 => "CHAT_LEAVE",
+    ChatMembers = 59 => "CHAT_MEMBERS",
+    PublicSearch = 60 => "PUBLIC_SEARCH",
+    ChatCreate = 63 => "CHAT_CREATE",
+    MsgSend = 64 => "MSG_SEND",
+    MsgTyping = 65 => "MSG_TYPING",
+    MsgDelete = 66 => "MSG_DELETE",
+    MsgEdit = 67 => "MSG_EDIT",
+    ChatSearch = 68 => "CHAT_SEARCH",
+    MsgGet = 71 => "MSG_GET",
+    MsgSearch = 73 => "MSG_SEARCH",
+    ChatMembersUpdate = 77 => "CHAT_MEMBERS_UPDATE",
+    VideoChatHistory = 79 => "VIDEO_CHAT_HISTORY",
+    PhotoUpload = 80 => "PHOTO_UPLOAD",
+    StickerUpload = 81 => "STICKER_UPLOAD",
+    VideoUpload = 82 => "VIDEO_UPLOAD",
+    VideoPlay = 83 => "VIDEO_PLAY",
+    FileUpload = 87 => "FILE_UPLOAD",
+    FileDownload = 88 => "FILE_DOWNLOAD",
+    LinkInfo = 89 => "LINK_INFO",
+    MsgDeleteRange = 92 => "MSG_DELETE_RANGE",
+    SessionsInfo = 96 => "SESSIONS_INFO",
+    SessionsClose = 97 => "SESSIONS_CLOSE",
+    AuthLoginCheckPassword = 115 => "AUTH_LOGIN_CHECK_PASSWORD",
+    NotifMessage = 128 => "NOTIF_MESSAGE",
+    NotifTyping = 129 => "NOTIF_TYPING",
+    NotifMark = 130 => "NOTIF_MARK",
+    NotifContact = 131 => "NOTIF_CONTACT",
+    NotifPresence = 132 => "NOTIF_PRESENCE",
+    NotifConfig = 134 => "NOTIF_CONFIG",
+    NotifChat = 135 => "NOTIF_CHAT",
+    NotifAttach = 136 => "NOTIF_ATTACH",
+    NotifCallStart = 137 => "NOTIF_CALL_START",
+    NotifContactSort = 139 => "NOTIF_CONTACT_SORT",
+    NotifMsgDeleteRange = 140 => "NOTIF_MSG_DELETE_RANGE",
+    NotifMsgDelete = 142 => "NOTIF_MSG_DELETE",
+    NotifCallbackAnswer = 143 => "NOTIF_CALLBACK_ANSWER",
+    NotifLocation = 147 => "NOTIF_LOCATION",
+    NotifLocationRequest = 148 => "NOTIF_LOCATION_REQUEST",
+    NotifAssetsUpdate = 150 => "NOTIF_ASSETS_UPDATE",
+    NotifDraft = 152 => "NOTIF_DRAFT",
+    NotifDraftDiscard = 153 => "NOTIF_DRAFT_DISCARD",
+    NotifMsgDelayed = 154 => "NOTIF_MSG_DELAYED",
+    NotifMsgReactionsChanged = 155 => "NOTIF_MSG_REACTIONS_CHANGED",
+    NotifMsgYouReacted = 156 => "NOTIF_MSG_YOU_REACTED",
+    NotifProfile = 159 => "NOTIF_PROFILE",
+    DraftSave = 176 => "DRAFT_SAVE",
+    DraftDiscard = 177 => "DRAFT_DISCARD",
+    MsgReaction = 178 => "MSG_REACTION",
+    MsgCancelReaction = 179 => "MSG_CANCEL_REACTION",
+    MsgGetReactions = 180 => "MSG_GET_REACTIONS",
+    MsgGetDetailedReactions = 181 => "MSG_GET_DETAILED_REACTIONS",
+}
+
+impl Opcode {
+    /**
+     * Читаемое имя для произвольного опкода: "MSG_SEND(64)" или "UNKNOWN(999)"
+     */
+    pub fn describe(code: u16) -> String {
+        match Opcode::try_from(code) {
+            Ok(opcode) => format!("{}({})", opcode.name(), code),
+            Err(_) => format!("UNKNOWN({})", code),
+        }
+    }
+}
+
+impl Opcode {
+    /**
+     * Запросы, которые безопасно повторить после таймаута или обрыва: чтение и действия,
+     * повтор которых ничего не меняет (отметка о прочтении, статус набора текста)
+     */
+    pub fn is_idempotent(self) -> bool {
+        matches!(
+            self,
+            Opcode::Ping
+                | Opcode::Config
+                | Opcode::ContactInfo
+                | Opcode::ContactPresence
+                | Opcode::ContactList
+                | Opcode::ContactSearch
+                | Opcode::ContactInfoByPhone
+                | Opcode::ChatInfo
+                | Opcode::ChatHistory
+                | Opcode::ChatMark
+                | Opcode::ChatMedia
+                | Opcode::ChatsList
+                | Opcode::ChatCheckLink
+                | Opcode::ChatMembers
+                | Opcode::PublicSearch
+                | Opcode::MsgTyping
+                | Opcode::ChatSearch
+                | Opcode::MsgGet
+                | Opcode::MsgSearch
+                | Opcode::VideoChatHistory
+                | Opcode::VideoPlay
+                | Opcode::FileDownload
+                | Opcode::LinkInfo
+                | Opcode::SessionsInfo
+                | Opcode::MsgGetReactions
+                | Opcode::MsgGetDetailedReactions
+        )
+    }
+
+    /* Опкоды отправки сообщений, на которые действует клиентский лимит (см. retry::RateLimit) */
+    pub fn is_message_send(self) -> bool {
+        matches!(self, Opcode::MsgSend | Opcode::MsgEdit)
+    }
+}
+
+impl From<Opcode> for u16 {
+    fn from(opcode: Opcode) -> Self {
+        opcode as u16
+    }
+}
+
+impl fmt::Display for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+use super::{Chat, Contact, Message, Presence, Profile};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+    #[serde(default)]
+    pub client: Option<String>,
+
+    #[serde(default)]
+    pub info: Option<String>,
+
+    #[serde(default)]
+    pub location: Option<String>,
+
+    #[serde(default)]
+    pub current: bool,
+
+    #[serde(default)]
+    pub time: Option<i64>,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenAttr {
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PasswordChallenge {
+    pub track_id: String,
+
+    #[serde(default)]
+    pub hint: Option<String>,
+
+    #[serde(default)]
+    pub email: Option<String>,
+}
+
+/**
+ * Ответ на шаги авторизации (start_auth, check_code, check_password, submit_register)
+ */
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthResult {
+    /* Временный токен (start_auth) или токен регистрации (submit_register) */
+    #[serde(default)]
+    pub token: Option<String>,
+
+    /* Тип токена (LOGIN, REGISTER) -> токен */
+    #[serde(default)]
+    pub token_attrs: HashMap<String, TokenAttr>,
+
+    #[serde(default)]
+    pub profile: Option<Profile>,
+
+    #[serde(default)]
+    pub password_challenge: Option<PasswordChallenge>,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl AuthResult {
+    pub fn login_token(&self) -> Option<&str> {
+        self.token_attrs.get("LOGIN").map(|t| t.token.as_str())
+    }
+
+    pub fn register_token(&self) -> Option<&str> {
+        self.token_attrs.get("REGISTER").map(|t| t.token.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncResult {
+    #[serde(default)]
+    pub profile: Option<Profile>,
+
+    #[serde(default)]
+    pub chats: Vec<Chat>,
+
+    #[serde(default)]
+    pub contacts: Vec<Contact>,
+
+    /* userId -> presence */
+    #[serde(default)]
+    pub presence: HashMap<String, Presence>,
+
+    /* chatId -> сообщения */
+    #[serde(default)]
+    pub messages: HashMap<String, Vec<Message>>,
+
+    #[serde(default)]
+    pub time: Option<i64>,
+
+    #[serde(default)]
+    pub token: Option<String>,
+
+    #[serde(default)]
+    pub config: Option<Value>,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl SyncResult {
+    pub fn user_id(&self) -> Option<i64> {
+        self.profile.as_ref().map(|profile| profile.contact.id)
+    }
+}
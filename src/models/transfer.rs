@@ -0,0 +1,23 @@
+use std::sync::Arc;
+
+/**
+ * Прогресс загрузки или скачивания: сколько байт уже принято сервером (загрузка)
+ * или записано (скачивание). total - None, если размер заранее неизвестен
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferProgress {
+    pub transferred: u64,
+    pub total: Option<u64>,
+}
+
+impl TransferProgress {
+    pub fn fraction(&self) -> Option<f64> {
+        match self.total {
+            Some(0) => Some(1.0),
+            Some(total) => Some(self.transferred as f64 / total as f64),
+            None => None,
+        }
+    }
+}
+
+pub type ProgressCallback = Arc<dyn Fn(TransferProgress) + Send + Sync>;
@@ -0,0 +1,205 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::common::de_opt_id;
+use super::{OutgoingAttachment, ProgressCallback, TransferProgress};
+use crate::constants::Constants;
+
+/**
+ * Слот для загрузки (ответ get_photo_upload / get_video_upload / get_file_upload)
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadSlot {
+    pub url: String,
+
+    #[serde(default, alias = "videoId", alias = "fileId", deserialize_with = "de_opt_id")]
+    pub id: Option<i64>,
+
+    #[serde(default)]
+    pub token: Option<String>,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/**
+ * Результат upload_photo: токен для вложения PHOTO
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadedPhoto {
+    pub photo_token: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadedVideo {
+    pub video_id: i64,
+    pub token: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadedFile {
+    pub file_id: i64,
+}
+
+impl From<UploadedPhoto> for OutgoingAttachment {
+    fn from(photo: UploadedPhoto) -> Self {
+        OutgoingAttachment::Photo { photo_token: photo.photo_token }
+    }
+}
+
+impl From<UploadedVideo> for OutgoingAttachment {
+    fn from(video: UploadedVideo) -> Self {
+        OutgoingAttachment::Video { video_id: video.video_id, token: video.token }
+    }
+}
+
+impl From<UploadedFile> for OutgoingAttachment {
+    fn from(file: UploadedFile) -> Self {
+        OutgoingAttachment::File { file_id: file.file_id }
+    }
+}
+
+/**
+ * Ответ get_video_by_id: качество (MP4_720, ...) -> url
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VideoInfo {
+    #[serde(default)]
+    pub cache: Option<bool>,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl VideoInfo {
+    pub fn urls(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.extra
+            .iter()
+            .filter_map(|(quality, url)| Some((quality.as_str(), url.as_str()?)))
+            .filter(|(_, url)| url.starts_with("http"))
+    }
+
+    /* Ссылка с наибольшим разрешением (MP4_1080 > MP4_720 > ...) */
+    pub fn best_url(&self) -> Option<&str> {
+        self.urls()
+            .max_by_key(|(quality, _)| {
+                quality
+                    .rsplit('_')
+                    .next()
+                    .and_then(|height| height.parse::<u32>().ok())
+                    .unwrap_or(0)
+            })
+            .map(|(_, url)| url)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileInfo {
+    pub url: String,
+
+    #[serde(default, rename = "unsafe")]
+    pub is_unsafe: bool,
+
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/**
+ * Параметры загрузки видео и файлов кусками с Content-Range.
+ * В памяти держится только текущий кусок
+ *
+ * let options = UploadOptions::new()
+ *     .on_progress(|p| println!("{:.0}%", p.fraction().unwrap_or(0.0) * 100.0));
+ * match client.upload_video_with(url, id, token, file, name, &options).await {
+ *     Err(Error::Upload(UploadError::Interrupted { offset, .. })) => {
+ *         // позже: options.offset(offset)
+ *     }
+ *     ...
+ * }
+ */
+#[derive(Clone)]
+pub struct UploadOptions {
+    pub chunk_size: usize,
+    /* С какого байта начинать (продолжение прерванной загрузки) */
+    pub offset: u64,
+    /* Сколько раз подряд повторять неудачный кусок */
+    pub max_retries: u32,
+    /* Начальная задержка перед повтором куска, дальше удваивается */
+    pub retry_delay: Duration,
+    pub progress: Option<ProgressCallback>,
+}
+
+impl Default for UploadOptions {
+    fn default() -> Self {
+        Self {
+            chunk_size: Constants::UPLOAD_CHUNK_SIZE,
+            offset: 0,
+            max_retries: Constants::RETRY_MAX_ATTEMPTS,
+            retry_delay: Constants::RETRY_BASE_DELAY,
+            progress: None,
+        }
+    }
+}
+
+impl fmt::Debug for UploadOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UploadOptions")
+            .field("chunk_size", &self.chunk_size)
+            .field("offset", &self.offset)
+            .field("max_retries", &self.max_retries)
+            .field("retry_delay", &self.retry_delay)
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
+}
+
+impl UploadOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn chunk_size(mut self, v: usize) -> Self {
+        self.chunk_size = v.max(1);
+        self
+    }
+
+    pub fn offset(mut self, v: u64) -> Self {
+        self.offset = v;
+        self
+    }
+
+    pub fn max_retries(mut self, v: u32) -> Self {
+        self.max_retries = v;
+        self
+    }
+
+    pub fn retry_delay(mut self, v: Duration) -> Self {
+        self.retry_delay = v;
+        self
+    }
+
+    /* Вызывается после каждого принятого сервером куска */
+    pub fn on_progress<F>(mut self, f: F) -> Self
+    where
+        F: Fn(TransferProgress) + Send + Sync + 'static,
+    {
+        self.progress = Some(Arc::new(f));
+        self
+    }
+
+    pub(crate) fn report(&self, sent: u64, total: u64) {
+        if let Some(progress) = &self.progress {
+            progress(TransferProgress { transferred: sent, total: Some(total) });
+        }
+    }
+}
@@ -172,7 +172,7 @@ pub fn can_navigate(from_screen: &str, to_screen: &str) -> bool {
     }
     NAV_TRANSITIONS
         .get(from_screen)
-        .map_or(false, |transitions| transitions.contains(&to_screen))
+        .is_some_and(|transitions| transitions.contains(&to_screen))
 }
 
 pub fn get_random_navigation(from_screen: &str) -> &'static str {
@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use tokio_util::sync::CancellationToken;
+
+/**
+ * Приоритет запроса в клиентских очередях (сейчас - лимит отправки сообщений):
+ * ожидающие запросы с более высоким приоритетом проходят первыми
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl Priority {
+    pub(crate) const COUNT: usize = 3;
+
+    pub(crate) fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/**
+ * Параметры отдельного запроса. Применяются ко всем методам API через MaxClient::with_options
+ *
+ * let cancel = CancellationToken::new();
+ * let chats = client
+ *     .with_options(RequestOptions::new().timeout(Duration::from_secs(60)).cancel(cancel.clone()))
+ *     .sync()
+ *     .await?;
+ */
+#[derive(Debug, Clone, Default)]
+pub struct RequestOptions {
+    /* Таймаут ожидания ответа вместо config.request_timeout */
+    pub timeout: Option<Duration>,
+    /* Отмена: запрос убирается из pending, вызов возвращает Error::Cancelled */
+    pub cancel: Option<CancellationToken>,
+    pub priority: Priority,
+}
+
+impl RequestOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn cancel(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(CancellationToken::is_cancelled)
+    }
+
+    /**
+     * Ждет future или отмену запроса
+     */
+    pub(crate) async fn cancellable<T>(&self, fut: impl std::future::Future<Output = T>) -> Option<T> {
+        match &self.cancel {
+            Some(token) => tokio::select! {
+                biased;
+                _ = token.cancelled() => None,
+                value = fut => Some(value),
+            },
+            None => Some(fut.await),
+        }
+    }
+}
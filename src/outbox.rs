@@ -0,0 +1,284 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use chrono::Utc;
+use log::{debug, warn};
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::{broadcast, Mutex as TokioMutex, Notify};
+use tokio::time::sleep;
+
+use crate::constants::Constants;
+use crate::errors::Error;
+use crate::events::Event;
+use crate::models::{Message, Opcode};
+use crate::{ClientState, MaxClient};
+
+/**
+ * Состояние исходящего сообщения в очереди
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutboxStatus {
+    /* Ждет подключения и входа */
+    Pending,
+    /* Отправлено, ждем подтверждения сервера */
+    Sent,
+    /* Сервер вернул сообщение (ответом или пушем с тем же cid) */
+    Delivered,
+    /* Сервер отклонил сообщение, повтор только через retry_message */
+    Failed,
+}
+
+#[derive(Debug, Clone)]
+pub struct OutboxEntry {
+    /* Клиентский id сообщения, по нему сервер и пуши сопоставляют повторы */
+    pub cid: i64,
+    pub chat_id: i64,
+    pub status: OutboxStatus,
+    /* Число отправок (больше 1 после реконнекта) */
+    pub attempts: u32,
+    pub error: Option<String>,
+    pub(crate) payload: Value,
+    /* Отправки подряд без ответа сервера, см. Constants::OUTBOX_MAX_TIMEOUTS */
+    pub(crate) timeouts: u32,
+}
+
+#[derive(Default)]
+struct OutboxState {
+    entries: VecDeque<OutboxEntry>,
+    last_cid: i64,
+    worker_started: bool,
+}
+
+/**
+ * Очередь исходящих сообщений. Сообщения копятся, пока клиент не подключен,
+ * уходят по одному в порядке постановки после sync() и повторяются с тем же cid
+ * после обрыва. Доставленные сообщения убираются из очереди, отклоненные остаются
+ * в статусе Failed. Каждая смена статуса рассылается как Event::Outbox
+ */
+pub struct Outbox {
+    state: Mutex<OutboxState>,
+    wake: Notify,
+    event_tx: broadcast::Sender<Event>,
+}
+
+impl Outbox {
+    pub(crate) fn new(event_tx: broadcast::Sender<Event>) -> Self {
+        Self {
+            state: Mutex::new(OutboxState::default()),
+            wake: Notify::new(),
+            event_tx,
+        }
+    }
+
+    /* Уникальный cid на основе времени, даже для нескольких сообщений в одну миллисекунду */
+    pub(crate) fn next_cid(&self) -> i64 {
+        let mut state = self.state.lock().unwrap();
+        state.last_cid = Utc::now().timestamp_millis().max(state.last_cid + 1);
+        state.last_cid
+    }
+
+    pub(crate) fn push(&self, cid: i64, chat_id: i64, payload: Value) {
+        self.state.lock().unwrap().entries.push_back(OutboxEntry {
+            cid,
+            chat_id,
+            status: OutboxStatus::Pending,
+            attempts: 0,
+            error: None,
+            payload,
+            timeouts: 0,
+        });
+
+        self.emit(cid, chat_id, OutboxStatus::Pending, None);
+        self.wake();
+    }
+
+    /* true, если обработчик еще не запущен и его нужно запустить */
+    pub(crate) fn start_worker(&self) -> bool {
+        !std::mem::replace(&mut self.state.lock().unwrap().worker_started, true)
+    }
+
+    pub(crate) fn wake(&self) {
+        self.wake.notify_one();
+    }
+
+    pub(crate) fn entries(&self) -> Vec<OutboxEntry> {
+        self.state.lock().unwrap().entries.iter().cloned().collect()
+    }
+
+    pub(crate) fn status(&self, cid: i64) -> Option<OutboxStatus> {
+        self.find(cid, |entry| entry.status)
+    }
+
+    /**
+     * Возвращает отклоненное сообщение в очередь
+     */
+    pub(crate) fn retry(&self, cid: i64) -> bool {
+        let chat_id = self.find(cid, |entry| {
+            if entry.status != OutboxStatus::Failed {
+                return None;
+            }
+            entry.status = OutboxStatus::Pending;
+            entry.error = None;
+            entry.timeouts = 0;
+            Some(entry.chat_id)
+        });
+
+        match chat_id.flatten() {
+            Some(chat_id) => {
+                self.emit(cid, chat_id, OutboxStatus::Pending, None);
+                self.wake();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /**
+     * Убирает сообщение, которое еще не отправляется (Pending или Failed)
+     */
+    pub(crate) fn remove(&self, cid: i64) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let before = state.entries.len();
+        state.entries.retain(|entry| entry.cid != cid || entry.status == OutboxStatus::Sent);
+        state.entries.len() != before
+    }
+
+    fn find<T>(&self, cid: i64, f: impl FnOnce(&mut OutboxEntry) -> T) -> Option<T> {
+        let mut state = self.state.lock().unwrap();
+        state.entries.iter_mut().find(|entry| entry.cid == cid).map(f)
+    }
+
+    /* Первое сообщение в очереди, помечается как Sent */
+    fn take_next(&self) -> Option<OutboxEntry> {
+        let entry = {
+            let mut state = self.state.lock().unwrap();
+            let entry = state
+                .entries
+                .iter_mut()
+                .find(|entry| entry.status == OutboxStatus::Pending)?;
+            entry.status = OutboxStatus::Sent;
+            entry.attempts += 1;
+            entry.clone()
+        };
+
+        self.emit(entry.cid, entry.chat_id, OutboxStatus::Sent, None);
+        Some(entry)
+    }
+
+    /**
+     * Сервер не ответил на отправку: сообщение возвращается в очередь, а после
+     * OUTBOX_MAX_TIMEOUTS таймаутов подряд помечается Failed. Возвращает задержку
+     * перед следующей отправкой (None, если сообщение отклонено)
+     */
+    fn timed_out(&self, cid: i64, error: &Error) -> Option<Duration> {
+        let timeouts = self.find(cid, |entry| {
+            entry.timeouts += 1;
+            entry.timeouts
+        })?;
+
+        if timeouts >= Constants::OUTBOX_MAX_TIMEOUTS {
+            warn!("Сообщение cid {}: нет ответа после {} отправок", cid, timeouts);
+            self.set_status(cid, OutboxStatus::Failed, Some(error.to_string()));
+            return None;
+        }
+
+        self.set_status(cid, OutboxStatus::Pending, None);
+        Some(
+            Constants::RETRY_BASE_DELAY
+                .saturating_mul(1 << (timeouts - 1).min(16))
+                .min(Constants::RETRY_MAX_DELAY),
+        )
+    }
+
+    fn set_status(&self, cid: i64, status: OutboxStatus, error: Option<String>) {
+        let chat_id = self.find(cid, |entry| {
+            entry.status = status;
+            entry.error = error;
+            entry.chat_id
+        });
+        if let Some(chat_id) = chat_id {
+            self.emit(cid, chat_id, status, None);
+        }
+    }
+
+    /**
+     * Подтверждение доставки ответом или пушем NOTIF_MESSAGE с нашим cid.
+     * cid выбирает клиент, поэтому сообщение сопоставляется еще и по чату
+     */
+    pub(crate) fn deliver(&self, cid: i64, chat_id: i64, message: Option<Box<Message>>) {
+        {
+            let mut state = self.state.lock().unwrap();
+            let Some(index) = state
+                .entries
+                .iter()
+                .position(|entry| entry.cid == cid && entry.chat_id == chat_id)
+            else {
+                return;
+            };
+            state.entries.remove(index);
+        }
+
+        self.emit(cid, chat_id, OutboxStatus::Delivered, message);
+    }
+
+    fn emit(&self, cid: i64, chat_id: i64, status: OutboxStatus, message: Option<Box<Message>>) {
+        debug!("Outbox cid {}: {:?}", cid, status);
+        let _ = self.event_tx.send(Event::Outbox { cid, chat_id, status, message });
+    }
+}
+
+/**
+ * Обработчик очереди. Держит только Weak на состояние клиента
+ * и завершается при первом пробуждении после удаления клиента.
+ * Запускается при первом подключении (см. MaxClient::start_outbox)
+ */
+pub(crate) async fn run(
+    outbox: Arc<Outbox>,
+    state: Weak<TokioMutex<ClientState>>,
+    event_tx: broadcast::Sender<Event>,
+) {
+    loop {
+        outbox.wake.notified().await;
+
+        loop {
+            let Some(state) = state.upgrade() else {
+                return;
+            };
+            let client = MaxClient::from_parts(state, event_tx.clone(), outbox.clone());
+            if !client.is_logged_in().await {
+                break;
+            }
+
+            let Some(entry) = outbox.take_next() else {
+                break;
+            };
+
+            match client.send_and_wait(Opcode::MsgSend, entry.payload, 0).await {
+                Ok(response) => match response.take::<Box<Message>>("message") {
+                    Ok(message) => outbox.deliver(entry.cid, entry.chat_id, Some(message)),
+                    Err(_) => outbox.deliver(entry.cid, entry.chat_id, None),
+                },
+                Err(e @ Error::RequestTimeout(_)) => {
+                    if let Some(delay) = outbox.timed_out(entry.cid, &e) {
+                        debug!("Outbox cid {}: {}, повтор через {:?}", entry.cid, e, delay);
+                        drop(client);
+                        sleep(delay).await;
+                    }
+                }
+                Err(e) if e.is_connection_error() => {
+                    debug!("Outbox cid {}: {}, ждем переподключения", entry.cid, e);
+                    outbox.set_status(entry.cid, OutboxStatus::Pending, None);
+                    /* Соединение может еще числиться живым: следующий sync() разбудит обработчик */
+                    break;
+                }
+                Err(e) => {
+                    warn!("Сообщение cid {} отклонено: {}", entry.cid, e);
+                    outbox.set_status(entry.cid, OutboxStatus::Failed, Some(e.to_string()));
+                }
+            }
+        }
+    }
+}
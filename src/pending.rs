@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+use std::future::{Future, IntoFuture};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::{debug, error, trace, warn};
+use tokio::sync::oneshot;
+use tokio::time::timeout;
+
+use crate::errors::{ClientResult, Error, ErrorCodes};
+use crate::models::{Opcode, Response};
+use crate::options::RequestOptions;
+
+pub(crate) type PendingMap = Arc<Mutex<HashMap<u64, PendingEntry>>>;
+
+/* Уникальный номер запроса: seq после переполнения повторяется, а id нет */
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+pub(crate) struct PendingEntry {
+    pub(crate) id: u64,
+    pub(crate) opcode: u16,
+    pub(crate) tx: oneshot::Sender<ClientResult<Response>>,
+}
+
+impl PendingEntry {
+    pub(crate) fn new(opcode: u16, tx: oneshot::Sender<ClientResult<Response>>) -> Self {
+        Self { id: NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed), opcode, tx }
+    }
+}
+
+/**
+ * Убирает запрос из pending при таймауте, отмене или сбросе future.
+ * Запись удаляется только если под этим seq все еще наш запрос,
+ * иначе старый handle после переполнения seq снял бы чужой
+ */
+pub(crate) struct PendingGuard {
+    pub(crate) pending: PendingMap,
+    pub(crate) seq: u64,
+    pub(crate) id: u64,
+}
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        let mut pending = self.pending.lock().unwrap();
+        if pending.get(&self.seq).is_some_and(|entry| entry.id == self.id) {
+            pending.remove(&self.seq);
+        }
+    }
+}
+
+/**
+ * Отправленный запрос, ответ на который еще не пришел (см. MaxClient::send_no_wait).
+ * Ответ можно дождаться через .await или wait(), а можно просто сбросить handle:
+ * запрос уберется из pending, а ответ придет событием Event::Response
+ * (только с traffic_events, иначе он отбрасывается)
+ */
+pub struct PendingResponse {
+    pub(crate) rx: oneshot::Receiver<ClientResult<Response>>,
+    pub(crate) guard: PendingGuard,
+    pub(crate) opcode: u16,
+    pub(crate) timeout: Duration,
+    pub(crate) options: RequestOptions,
+    pub(crate) error_codes: Arc<ErrorCodes>,
+}
+
+impl PendingResponse {
+    pub fn seq(&self) -> u64 {
+        self.guard.seq
+    }
+
+    pub fn opcode(&self) -> u16 {
+        self.opcode
+    }
+
+    /**
+     * Ждет ответ сервера с таймаутом и отменой из RequestOptions запроса
+     */
+    pub async fn wait(self) -> ClientResult<Response> {
+        let PendingResponse { rx, guard, opcode, timeout: request_timeout, options, error_codes } = self;
+
+        let Some(result) = options.cancellable(timeout(request_timeout, rx)).await else {
+            debug!("Запрос {} seq: {} отменен", Opcode::describe(opcode), guard.seq);
+            return Err(Error::Cancelled);
+        };
+
+        match result {
+            Ok(Ok(Ok(response))) => {
+                trace!("Получен ответ {} для seq: {}", Opcode::describe(response.opcode), response.seq);
+                if response.payload.get("error").is_some() {
+                    Err(error_codes.classify(response.payload))
+                } else {
+                    Ok(response)
+                }
+            }
+            Ok(Ok(Err(e))) => Err(e),
+            Ok(Err(e)) => {
+                error!("Ошибка получения ответа (канал закрыт) для seq: {}", guard.seq);
+                Err(e.into())
+            }
+            Err(_) => {
+                warn!("Таймаут запроса {} для seq: {}", Opcode::describe(opcode), guard.seq);
+                Err(Error::RequestTimeout(request_timeout))
+            }
+        }
+    }
+}
+
+impl IntoFuture for PendingResponse {
+    type Output = ClientResult<Response>;
+    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + Send>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(self.wait())
+    }
+}
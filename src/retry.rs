@@ -0,0 +1,200 @@
+use std::sync::Mutex as StdMutex;
+use std::time::Duration;
+
+use tokio::sync::Notify;
+use tokio::time::{sleep, Instant};
+
+use crate::constants::Constants;
+use crate::errors::Error;
+use crate::models::Opcode;
+use crate::options::Priority;
+
+/**
+ * Политика повторов в send_and_wait.
+ *
+ * Ограничение частоты (FloodWait, RateLimited) означает, что сервер запрос не выполнил,
+ * поэтому такие ошибки повторяются для любого опкода. Обрыв соединения, таймаут и временные
+ * ошибки сервера повторяются только для идемпотентных опкодов (см. Opcode::is_idempotent).
+ * После обрыва повтор ждет, пока задача реконнекта восстановит соединение (не дольше max_delay)
+ */
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /* Сколько раз повторять запрос после первой попытки, 0 - без повторов */
+    pub max_retries: u32,
+    /* Начальная задержка экспоненциального backoff, если сервер не указал свою */
+    pub base_delay: Duration,
+    /* Потолок задержки: если сервер просит ждать дольше, ошибка возвращается сразу */
+    pub max_delay: Duration,
+    /* Повторять ли идемпотентные запросы после таймаута */
+    pub retry_timeouts: bool,
+}
+
+/* По умолчанию без повторов, включаются через MaxClientBuilder::retry_policy */
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::disabled()
+    }
+}
+
+impl RetryPolicy {
+    /* Рекомендуемые настройки: до RETRY_MAX_ATTEMPTS повторов с backoff от RETRY_BASE_DELAY */
+    pub fn enabled() -> Self {
+        Self {
+            max_retries: Constants::RETRY_MAX_ATTEMPTS,
+            base_delay: Constants::RETRY_BASE_DELAY,
+            max_delay: Constants::RETRY_MAX_DELAY,
+            retry_timeouts: false,
+        }
+    }
+
+    pub fn disabled() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::enabled()
+        }
+    }
+
+    /**
+     * Задержка перед повтором attempt (с нуля) или None, если ошибку нужно вернуть
+     */
+    pub fn delay_for(&self, opcode: u16, error: &Error, attempt: u32) -> Option<Duration> {
+        if attempt >= self.max_retries {
+            return None;
+        }
+
+        let idempotent = Opcode::try_from(opcode).is_ok_and(Opcode::is_idempotent);
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+
+        match error {
+            Error::FloodWait { retry_after, .. } => {
+                (*retry_after <= self.max_delay).then_some(*retry_after)
+            }
+            Error::RateLimited(_) => Some(backoff),
+            Error::Transient(_) if idempotent => Some(backoff),
+            Error::ConnectionClosed(_) | Error::NotConnected if idempotent => Some(backoff),
+            Error::RequestTimeout(_) if idempotent && self.retry_timeouts => Some(backoff),
+            _ => None,
+        }
+    }
+}
+
+/**
+ * Клиентский лимит на отправку сообщений: burst запросов подряд,
+ * затем не чаще одного в interval
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub burst: u32,
+    pub interval: Duration,
+}
+
+impl RateLimit {
+    pub fn new(burst: u32, interval: Duration) -> Self {
+        Self {
+            burst: burst.max(1),
+            interval,
+        }
+    }
+}
+
+struct BucketState {
+    tokens: f64,
+    updated: Instant,
+    /* Число ожидающих запросов по приоритетам */
+    waiting: [usize; Priority::COUNT],
+}
+
+impl BucketState {
+    fn refill(&mut self, limit: &RateLimit) {
+        let now = Instant::now();
+        let interval = limit.interval.as_secs_f64();
+        let burst = limit.burst as f64;
+
+        self.tokens = if interval > 0.0 {
+            let refilled = now.duration_since(self.updated).as_secs_f64() / interval;
+            (self.tokens + refilled).min(burst)
+        } else {
+            burst
+        };
+        self.updated = now;
+    }
+
+    fn has_higher(&self, priority: Priority) -> bool {
+        self.waiting[priority.index() + 1..].iter().any(|&n| n > 0)
+    }
+}
+
+/**
+ * Token bucket для RateLimit. Общий для всех клонов MaxClient
+ */
+pub(crate) struct TokenBucket {
+    limit: RateLimit,
+    state: StdMutex<BucketState>,
+    released: Notify,
+}
+
+/* Снимает запрос из очереди ожидания, в том числе при отмене */
+struct WaitGuard<'a> {
+    bucket: &'a TokenBucket,
+    priority: Priority,
+}
+
+impl Drop for WaitGuard<'_> {
+    fn drop(&mut self) {
+        self.bucket.state.lock().unwrap().waiting[self.priority.index()] -= 1;
+        self.bucket.released.notify_waiters();
+    }
+}
+
+impl TokenBucket {
+    pub(crate) fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            state: StdMutex::new(BucketState {
+                tokens: limit.burst as f64,
+                updated: Instant::now(),
+                waiting: [0; Priority::COUNT],
+            }),
+            released: Notify::new(),
+        }
+    }
+
+    /**
+     * Ждет свободный токен. Пока есть ожидающие запросы с более высоким приоритетом,
+     * токен достается им
+     */
+    pub(crate) async fn acquire(&self, priority: Priority) {
+        self.state.lock().unwrap().waiting[priority.index()] += 1;
+        let _guard = WaitGuard { bucket: self, priority };
+
+        loop {
+            let released = self.released.notified();
+
+            let wait = {
+                let mut state = self.state.lock().unwrap();
+                state.refill(&self.limit);
+                if state.has_higher(priority) {
+                    None
+                } else if state.tokens >= 1.0 {
+                    state.tokens -= 1.0;
+                    return;
+                } else {
+                    Some(Duration::from_secs_f64(
+                        (1.0 - state.tokens) * self.limit.interval.as_secs_f64(),
+                    ))
+                }
+            };
+
+            match wait {
+                Some(wait) => tokio::select! {
+                    _ = sleep(wait) => {}
+                    _ = released => {}
+                },
+                None => released.await,
+            }
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use crate::errors::ClientResult;
+use crate::models::Identity;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tokio::io::AsyncWriteExt;
+
+/**
+ * Маркеры инкрементальной синхронизации (opcode 19)
+ */
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncMarkers {
+    pub chats_sync: i64,
+    pub contacts_sync: i64,
+    pub presence_sync: i64,
+    pub drafts_sync: i64,
+}
+
+/**
+ * Все, что нужно для возобновления сессии без повторного логина
+ */
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionData {
+    #[serde(default)]
+    pub identity: Option<Identity>,
+
+    #[serde(default)]
+    pub token: Option<String>,
+
+    #[serde(default)]
+    pub user_id: Option<u64>,
+
+    #[serde(default)]
+    pub sync: SyncMarkers,
+}
+
+#[async_trait]
+pub trait SessionStore: Send + Sync {
+    async fn load(&self) -> ClientResult<Option<SessionData>>;
+    async fn save(&self, data: &SessionData) -> ClientResult<()>;
+    async fn clear(&self) -> ClientResult<()>;
+}
+
+/**
+ * Хранит сессию в JSON-файле
+ */
+pub struct FileSessionStore {
+    path: PathBuf,
+}
+
+impl FileSessionStore {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self { path: path.as_ref().to_path_buf() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[async_trait]
+impl SessionStore for FileSessionStore {
+    async fn load(&self) -> ClientResult<Option<SessionData>> {
+        match tokio::fs::read(&self.path).await {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn save(&self, data: &SessionData) -> ClientResult<()> {
+        let json = serde_json::to_vec_pretty(data)?;
+
+        /* Пишем во временный файл и переименовываем, чтобы не оставить битый JSON */
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+
+        /* В файле токен: доступ только владельцу. Старый tmp удаляем, иначе mode не применится */
+        let _ = tokio::fs::remove_file(&tmp_path).await;
+        let mut options = tokio::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+
+        let mut file = options.open(&tmp_path).await?;
+        file.write_all(&json).await?;
+        file.sync_all().await?;
+        drop(file);
+
+        tokio::fs::rename(&tmp_path, &self.path).await?;
+        Ok(())
+    }
+
+    async fn clear(&self) -> ClientResult<()> {
+        match tokio::fs::remove_file(&self.path).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/**
+ * Хранит сессию в памяти (для тестов и короткоживущих процессов)
+ */
+#[derive(Default)]
+pub struct MemorySessionStore {
+    data: Mutex<Option<SessionData>>,
+}
+
+impl MemorySessionStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_data(data: SessionData) -> Self {
+        Self { data: Mutex::new(Some(data)) }
+    }
+}
+
+#[async_trait]
+impl SessionStore for MemorySessionStore {
+    async fn load(&self) -> ClientResult<Option<SessionData>> {
+        Ok(self.data.lock().unwrap().clone())
+    }
+
+    async fn save(&self, data: &SessionData) -> ClientResult<()> {
+        *self.data.lock().unwrap() = Some(data.clone());
+        Ok(())
+    }
+
+    async fn clear(&self) -> ClientResult<()> {
+        *self.data.lock().unwrap() = None;
+        Ok(())
+    }
+}
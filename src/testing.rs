@@ -0,0 +1,657 @@
+/*
+ * Встроенный mock-сервер Max для интеграционных тестов без сети и openmax-server.
+ *
+ * Говорит на обоих протоколах клиента: JSON поверх WebSocket (WebTransport)
+ * и бинарные кадры с msgpack (MobileTransport). Ответы задаются обработчиками
+ * по опкодам, пуши отправляются всем подключенным клиентам через push.
+ *
+ * let server = MockServer::start(MockProtocol::Mobile).await?;
+ * server.respond(Opcode::ChatInfo, json!({ "chats": [] }));
+ *
+ * let client = server.client_builder().build();
+ * client.connect(testing::identity()).await?;
+ * server.push(Opcode::NotifTyping, json!({ "chatId": 1, "userId": 2 }));
+ *
+ * Web-вариант дополнительно отвечает на обычные HTTP-запросы по путям из on_http,
+ * а mock_uploads эмулирует слоты и серверы загрузки файлов.
+ *
+ * Доступен с feature "testing":
+ * [dev-dependencies]
+ * rumax = { version = "...", features = ["testing"] }
+ */
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures_util::{SinkExt, StreamExt};
+use http_body_util::{BodyExt, Full};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper_util::rt::TokioIo;
+use log::{debug, warn};
+use serde_json::{json, Value};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{broadcast, mpsc, Notify};
+use tokio_util::codec::Framed;
+use tokio_util::sync::CancellationToken;
+use yawc::{FrameView, OpCode, WebSocket};
+
+use crate::builder::MaxClientBuilder;
+use crate::errors::ClientResult;
+use crate::models::{Identity, Opcode, Request, Response, UserAgent};
+use crate::transport::codec::MobileCodec;
+use crate::transport::tls::TlsConfig;
+use crate::ClientMode;
+
+/* Сколько wait_for_request ждет нужный запрос */
+const WAIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockProtocol {
+    Web,
+    Mobile,
+}
+
+/* None - не отвечать на запрос (для проверки таймаутов) */
+type Handler = Arc<dyn Fn(&Request) -> Option<Value> + Send + Sync>;
+
+type HttpHandler = Arc<dyn Fn(&HttpExchange) -> HttpReply + Send + Sync>;
+
+/**
+ * HTTP-запрос к mock-серверу (заголовки в нижнем регистре)
+ */
+#[derive(Debug, Clone)]
+pub struct HttpExchange {
+    pub method: String,
+    pub path: String,
+    pub headers: HashMap<String, String>,
+    pub body: Bytes,
+}
+
+impl HttpExchange {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(&name.to_ascii_lowercase()).map(String::as_str)
+    }
+
+    /**
+     * Content-Range вида "bytes 0-99/1000" или "0-99/1000": (начало, конец, размер)
+     */
+    pub fn content_range(&self) -> Option<(u64, u64, u64)> {
+        let value = self.header("content-range")?;
+        let value = value.strip_prefix("bytes ").unwrap_or(value);
+        let (range, total) = value.split_once('/')?;
+        let (start, end) = range.split_once('-')?;
+        Some((start.parse().ok()?, end.parse().ok()?, total.parse().ok()?))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpReply {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Bytes,
+}
+
+impl HttpReply {
+    pub fn new(status: u16, body: impl Into<Bytes>) -> Self {
+        Self { status, headers: Vec::new(), body: body.into() }
+    }
+
+    pub fn json(value: Value) -> Self {
+        Self::new(200, value.to_string()).header("Content-Type", "application/json")
+    }
+
+    pub fn header(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.headers.push((name.to_string(), value.into()));
+        self
+    }
+}
+
+/* Файлы, собранные mock_uploads из кусков Content-Range */
+#[derive(Default)]
+struct UploadStore {
+    files: HashMap<String, Vec<u8>>,
+}
+
+struct Shared {
+    handlers: Mutex<HashMap<u16, Handler>>,
+    requests: Mutex<Vec<Request>>,
+    request_notify: Notify,
+    connections: Mutex<Vec<mpsc::UnboundedSender<Response>>>,
+    kick_tx: broadcast::Sender<()>,
+    http_handlers: Mutex<HashMap<String, HttpHandler>>,
+    http_requests: Mutex<Vec<HttpExchange>>,
+    uploads: Arc<Mutex<UploadStore>>,
+}
+
+impl Shared {
+    fn handle(&self, request: Request) -> Option<Response> {
+        debug!("Mock получил {}: {:?}", Opcode::describe(request.opcode), request.payload);
+
+        self.requests.lock().unwrap().push(request.clone());
+        self.request_notify.notify_waiters();
+
+        let handler = self.handlers.lock().unwrap().get(&request.opcode).cloned();
+        let payload = match handler {
+            Some(handler) => handler(&request)?,
+            None => json!({
+                "error": "mock.unhandled",
+                "message": format!("No mock handler for {}", Opcode::describe(request.opcode)),
+            }),
+        };
+
+        Some(Response {
+            ver: request.ver,
+            cmd: if payload.get("error").is_some() { 3 } else { 1 },
+            seq: request.seq,
+            opcode: request.opcode,
+            payload,
+        })
+    }
+
+    fn broadcast(&self, opcode: u16, payload: Value) -> usize {
+        let push = Response {
+            ver: 10,
+            cmd: 0,
+            seq: 0,
+            opcode,
+            payload,
+        };
+
+        let mut connections = self.connections.lock().unwrap();
+        connections.retain(|tx| tx.send(push.clone()).is_ok());
+        connections.len()
+    }
+
+    fn register(&self) -> mpsc::UnboundedReceiver<Response> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.connections.lock().unwrap().push(tx);
+        rx
+    }
+}
+
+/**
+ * Mock-сервер на 127.0.0.1 со случайным портом. Останавливается при drop
+ */
+pub struct MockServer {
+    protocol: MockProtocol,
+    addr: SocketAddr,
+    shared: Arc<Shared>,
+    shutdown: CancellationToken,
+}
+
+impl MockServer {
+    pub async fn start(protocol: MockProtocol) -> ClientResult<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+
+        let (kick_tx, _) = broadcast::channel(1);
+        let shared = Arc::new(Shared {
+            handlers: Mutex::new(HashMap::new()),
+            requests: Mutex::new(Vec::new()),
+            request_notify: Notify::new(),
+            connections: Mutex::new(Vec::new()),
+            kick_tx,
+            http_handlers: Mutex::new(HashMap::new()),
+            http_requests: Mutex::new(Vec::new()),
+            uploads: Arc::new(Mutex::new(UploadStore::default())),
+        });
+
+        let server = Self {
+            protocol,
+            addr,
+            shared: shared.clone(),
+            shutdown: CancellationToken::new(),
+        };
+
+        server.respond(Opcode::SessionInit, json!({ "location": "RU" }));
+        server.respond(Opcode::Ping, json!({}));
+        server.respond(Opcode::Log, json!({}));
+
+        tokio::spawn(accept_loop(protocol, listener, shared, server.shutdown.clone()));
+        debug!("Mock сервер ({:?}) запущен на {}", protocol, addr);
+
+        Ok(server)
+    }
+
+    pub fn protocol(&self) -> MockProtocol {
+        self.protocol
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn websocket_uri(&self) -> String {
+        format!("ws://{}/websocket", self.addr)
+    }
+
+    /**
+     * Билдер клиента, уже настроенный на этот сервер
+     */
+    pub fn client_builder(&self) -> MaxClientBuilder {
+        match self.protocol {
+            MockProtocol::Web => MaxClientBuilder::new()
+                .mode(ClientMode::Web)
+                .websocket_uri(self.websocket_uri()),
+            MockProtocol::Mobile => MaxClientBuilder::new()
+                .mode(ClientMode::Mobile)
+                .tls(TlsConfig::Disabled)
+                .mobile_host(self.addr.ip().to_string(), self.addr.port()),
+        }
+    }
+
+    /**
+     * Обработчик опкода. Возвращает payload ответа или None, чтобы не отвечать.
+     * Payload с полем "error" клиент получит как ошибку API
+     */
+    pub fn on<F>(&self, opcode: impl Into<u16>, handler: F)
+    where
+        F: Fn(&Request) -> Option<Value> + Send + Sync + 'static,
+    {
+        self.shared.handlers.lock().unwrap().insert(opcode.into(), Arc::new(handler));
+    }
+
+    /**
+     * Фиксированный ответ на опкод
+     */
+    pub fn respond(&self, opcode: impl Into<u16>, payload: Value) {
+        self.on(opcode, move |_| Some(payload.clone()));
+    }
+
+    /**
+     * Ответ ошибкой API на опкод
+     */
+    pub fn respond_error(&self, opcode: impl Into<u16>, error: &str, message: &str) {
+        self.respond(opcode, json!({
+            "error": error,
+            "message": message,
+            "localizedMessage": message,
+        }));
+    }
+
+    /**
+     * Запросы с этим опкодом остаются без ответа
+     */
+    pub fn ignore(&self, opcode: impl Into<u16>) {
+        self.on(opcode, |_| None);
+    }
+
+    /**
+     * Отправляет незапрошенное событие (пуш) всем подключенным клиентам.
+     * Возвращает число клиентов, которым оно ушло
+     */
+    pub fn push(&self, opcode: impl Into<u16>, payload: Value) -> usize {
+        self.shared.broadcast(opcode.into(), payload)
+    }
+
+    /**
+     * Все полученные запросы в порядке прихода
+     */
+    pub fn requests(&self) -> Vec<Request> {
+        self.shared.requests.lock().unwrap().clone()
+    }
+
+    /**
+     * Ждет первый запрос с опкодом (в том числе уже полученный)
+     */
+    pub async fn wait_for_request(&self, opcode: impl Into<u16>) -> Option<Request> {
+        let opcode = opcode.into();
+
+        let wait = async {
+            loop {
+                let notified = self.shared.request_notify.notified();
+                tokio::pin!(notified);
+                notified.as_mut().enable();
+
+                let found = self.shared.requests.lock().unwrap()
+                    .iter()
+                    .find(|r| r.opcode == opcode)
+                    .cloned();
+                if let Some(request) = found {
+                    return request;
+                }
+
+                notified.await;
+            }
+        };
+
+        tokio::time::timeout(WAIT_TIMEOUT, wait).await.ok()
+    }
+
+    /**
+     * Обработчик обычного HTTP-запроса по пути (только MockProtocol::Web).
+     * На остальные пути, кроме WebSocket, сервер отвечает 400
+     */
+    pub fn on_http<F>(&self, path: &str, handler: F)
+    where
+        F: Fn(&HttpExchange) -> HttpReply + Send + Sync + 'static,
+    {
+        self.shared.http_handlers.lock().unwrap().insert(path.to_string(), Arc::new(handler));
+    }
+
+    pub fn http_url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    /**
+     * Все HTTP-запросы к обработчикам on_http в порядке прихода
+     */
+    pub fn http_requests(&self) -> Vec<HttpExchange> {
+        self.shared.http_requests.lock().unwrap().clone()
+    }
+
+    /**
+     * Эмуляция загрузки файлов Max: слоты PHOTO_UPLOAD / VIDEO_UPLOAD / FILE_UPLOAD
+     * указывают на /upload/photo, /upload/video и /upload/file этого сервера.
+     * Видео и файлы принимаются кусками с Content-Range, после последнего куска
+     * сервер присылает NOTIF_ATTACH. Собранные файлы доступны через uploaded
+     */
+    pub fn mock_uploads(&self) {
+        self.respond(Opcode::PhotoUpload, json!({ "url": self.http_url("/upload/photo") }));
+        self.respond(Opcode::VideoUpload, json!({
+            "info": [{ "url": self.http_url("/upload/video"), "videoId": 101, "token": "video-token" }],
+        }));
+        self.respond(Opcode::FileUpload, json!({
+            "info": [{ "url": self.http_url("/upload/file"), "fileId": 202 }],
+        }));
+
+        self.on_http("/upload/photo", |_| {
+            HttpReply::json(json!({ "photos": { "1": { "token": "photo-token" } } }))
+        });
+
+        for (path, attach) in [
+            ("/upload/video", json!({ "videoId": 101 })),
+            ("/upload/file", json!({ "fileId": 202 })),
+        ] {
+            let uploads = self.shared.uploads.clone();
+            let connections = Arc::downgrade(&self.shared);
+            self.on_http(path, move |exchange| {
+                let Some((start, end, total)) = exchange.content_range() else {
+                    return HttpReply::new(400, "Content-Range required");
+                };
+
+                let complete = {
+                    let mut store = uploads.lock().unwrap();
+                    let file = store.files.entry(exchange.path.clone()).or_default();
+                    let end = (start as usize + exchange.body.len()).max(file.len());
+                    file.resize(end, 0);
+                    file[start as usize..start as usize + exchange.body.len()].copy_from_slice(&exchange.body);
+                    file.len() as u64 == total
+                };
+
+                if complete {
+                    if let Some(shared) = connections.upgrade() {
+                        shared.broadcast(Opcode::NotifAttach.into(), attach.clone());
+                    }
+                }
+                HttpReply::new(200, format!("{}-{}/{}", start, end, total))
+            });
+        }
+    }
+
+    /**
+     * Раздает content по пути с поддержкой Range: bytes=N- (ответ 206 или 416)
+     */
+    pub fn serve_download(&self, path: &str, content: impl Into<Bytes>) {
+        let content: Bytes = content.into();
+        self.on_http(path, move |exchange| {
+            let total = content.len();
+            let start = exchange
+                .header("range")
+                .and_then(|range| range.strip_prefix("bytes="))
+                .and_then(|range| range.split_once('-'))
+                .and_then(|(start, _)| start.parse::<usize>().ok());
+
+            match start {
+                None => HttpReply::new(200, content.clone()),
+                Some(start) if start >= total => {
+                    HttpReply::new(416, "").header("Content-Range", format!("bytes */{}", total))
+                }
+                Some(start) => HttpReply::new(206, content.slice(start..))
+                    .header("Content-Range", format!("bytes {}-{}/{}", start, total - 1, total)),
+            }
+        });
+    }
+
+    /**
+     * Содержимое, собранное mock_uploads по пути загрузки
+     */
+    pub fn uploaded(&self, path: &str) -> Option<Vec<u8>> {
+        self.shared.uploads.lock().unwrap().files.get(path).cloned()
+    }
+
+    /**
+     * Разрывает все текущие соединения (для проверки реконнекта)
+     */
+    pub fn disconnect_all(&self) {
+        let _ = self.shared.kick_tx.send(());
+        self.shared.connections.lock().unwrap().clear();
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.shutdown.cancel();
+    }
+}
+
+/**
+ * Identity для тестов
+ */
+pub fn identity() -> Identity {
+    Identity {
+        device_id: "mock-device".to_string(),
+        mt_instance: "mock-instance".to_string(),
+        user_agent: UserAgent {
+            device_type: "ANDROID".to_string(),
+            app_version: "25.12.1".to_string(),
+            os_version: "Android 14".to_string(),
+            timezone: "Europe/Moscow".to_string(),
+            screen: "xxhdpi 440dpi 1080x2400".to_string(),
+            push_device_type: None,
+            arch: None,
+            locale: "ru".to_string(),
+            build_number: 6498,
+            device_name: "Mock".to_string(),
+            device_locale: "ru".to_string(),
+            release: None,
+            header_user_agent: None,
+        },
+    }
+}
+
+async fn accept_loop(
+    protocol: MockProtocol,
+    listener: TcpListener,
+    shared: Arc<Shared>,
+    shutdown: CancellationToken,
+) {
+    loop {
+        let tcp = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((tcp, _)) => tcp,
+                Err(e) => {
+                    warn!("Mock: ошибка accept: {}", e);
+                    continue;
+                }
+            },
+            _ = shutdown.cancelled() => return,
+        };
+
+        let shared = shared.clone();
+        let shutdown = shutdown.clone();
+        match protocol {
+            MockProtocol::Mobile => tokio::spawn(serve_mobile(tcp, shared, shutdown)),
+            MockProtocol::Web => tokio::spawn(serve_web(tcp, shared, shutdown)),
+        };
+    }
+}
+
+/* Кодек симметричен, поэтому сервер читает кадры клиента как Response и пишет ответы как Request */
+async fn serve_mobile(tcp: TcpStream, shared: Arc<Shared>, shutdown: CancellationToken) {
+    let mut framed = Framed::new(tcp, MobileCodec::new());
+    let mut pushes = shared.register();
+    let mut kick = shared.kick_tx.subscribe();
+
+    loop {
+        let outgoing = tokio::select! {
+            frame = framed.next() => match frame {
+                Some(Ok(packet)) => {
+                    let request = Request {
+                        ver: packet.ver,
+                        cmd: packet.cmd,
+                        seq: packet.seq,
+                        opcode: packet.opcode,
+                        payload: packet.payload,
+                    };
+                    match shared.handle(request) {
+                        Some(resp) => resp,
+                        None => continue,
+                    }
+                }
+                Some(Err(e)) => {
+                    warn!("Mock: ошибка чтения кадра: {}", e);
+                    return;
+                }
+                None => return,
+            },
+            Some(push) = pushes.recv() => push,
+            _ = kick.recv() => return,
+            _ = shutdown.cancelled() => return,
+        };
+
+        let frame = Request {
+            ver: outgoing.ver,
+            cmd: outgoing.cmd,
+            seq: outgoing.seq,
+            opcode: outgoing.opcode,
+            payload: outgoing.payload,
+        };
+        if framed.send(frame).await.is_err() {
+            return;
+        }
+    }
+}
+
+async fn serve_web(tcp: TcpStream, shared: Arc<Shared>, shutdown: CancellationToken) {
+    let service = service_fn(move |mut req: hyper::Request<hyper::body::Incoming>| {
+        let shared = shared.clone();
+        let shutdown = shutdown.clone();
+        async move {
+            let http_handler = shared.http_handlers.lock().unwrap().get(req.uri().path()).cloned();
+            if let Some(handler) = http_handler {
+                return Ok::<_, Infallible>(serve_http(req, &shared, handler).await);
+            }
+
+            let response = match WebSocket::upgrade(&mut req) {
+                Ok((response, upgrade)) => {
+                    tokio::spawn(async move {
+                        match upgrade.await {
+                            Ok(ws) => web_session(ws, shared, shutdown).await,
+                            Err(e) => warn!("Mock: ошибка upgrade: {}", e),
+                        }
+                    });
+                    response.map(|_| Full::new(Bytes::new()))
+                }
+                Err(e) => {
+                    warn!("Mock: не WebSocket запрос: {}", e);
+                    let mut response = hyper::Response::new(Full::new(Bytes::new()));
+                    *response.status_mut() = http::StatusCode::BAD_REQUEST;
+                    response
+                }
+            };
+            Ok(response)
+        }
+    });
+
+    if let Err(e) = http1::Builder::new()
+        .serve_connection(TokioIo::new(tcp), service)
+        .with_upgrades()
+        .await
+    {
+        debug!("Mock: HTTP соединение закрыто: {}", e);
+    }
+}
+
+async fn serve_http(
+    req: hyper::Request<hyper::body::Incoming>,
+    shared: &Shared,
+    handler: HttpHandler,
+) -> hyper::Response<Full<Bytes>> {
+    let (parts, body) = req.into_parts();
+    let body = match body.collect().await {
+        Ok(body) => body.to_bytes(),
+        Err(e) => {
+            warn!("Mock: ошибка чтения HTTP тела: {}", e);
+            Bytes::new()
+        }
+    };
+
+    let exchange = HttpExchange {
+        method: parts.method.to_string(),
+        path: parts.uri.path().to_string(),
+        headers: parts
+            .headers
+            .iter()
+            .filter_map(|(name, value)| Some((name.as_str().to_string(), value.to_str().ok()?.to_string())))
+            .collect(),
+        body,
+    };
+    debug!("Mock HTTP {} {} ({} байт)", exchange.method, exchange.path, exchange.body.len());
+    shared.http_requests.lock().unwrap().push(exchange.clone());
+
+    let reply = handler(&exchange);
+    let mut response = hyper::Response::new(Full::new(reply.body));
+    *response.status_mut() = http::StatusCode::from_u16(reply.status).unwrap_or(http::StatusCode::INTERNAL_SERVER_ERROR);
+    for (name, value) in reply.headers {
+        if let (Ok(name), Ok(value)) = (
+            http::HeaderName::from_bytes(name.as_bytes()),
+            http::HeaderValue::from_str(&value),
+        ) {
+            response.headers_mut().append(name, value);
+        }
+    }
+    response
+}
+
+async fn web_session(mut ws: WebSocket, shared: Arc<Shared>, shutdown: CancellationToken) {
+    let mut pushes = shared.register();
+    let mut kick = shared.kick_tx.subscribe();
+
+    loop {
+        let outgoing = tokio::select! {
+            frame = ws.next() => match frame {
+                Some(frame) if frame.opcode == OpCode::Text => {
+                    let request = match serde_json::from_slice::<Request>(&frame.payload) {
+                        Ok(request) => request,
+                        Err(e) => {
+                            warn!("Mock: некорректный JSON: {}", e);
+                            continue;
+                        }
+                    };
+                    match shared.handle(request) {
+                        Some(resp) => resp,
+                        None => continue,
+                    }
+                }
+                Some(_) => continue,
+                None => return,
+            },
+            Some(push) = pushes.recv() => push,
+            _ = kick.recv() => return,
+            _ = shutdown.cancelled() => return,
+        };
+
+        let json = match serde_json::to_string(&outgoing) {
+            Ok(json) => json,
+            Err(_) => continue,
+        };
+        if ws.send(FrameView::text(json)).await.is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use crate::errors::Error;
+use crate::models::{Request, Response};
+
+use bytes::{Buf, BufMut, BytesMut};
+use lz4_flex::block::DecompressError;
+use rmpv::decode::read_value;
+use rmpv::Value as MsgPackValue;
+use serde_json::{Map, Value as JsonValue};
+use tokio_util::codec::{Decoder, Encoder};
+
+/**
+ * Заголовок кадра мобильного протокола (10 байт, big endian):
+ *
+ * | ver: u8 | cmd: u8 | seq: u16 | opcode: u16 | flags: u8 | len: u24 |
+ *
+ * Ненулевой flags означает, что payload сжат LZ4 (block, без префикса размера).
+ * Payload - MessagePack. seq на проводе 16-битный, поэтому клиент
+ * заворачивает счетчик seq в диапазон 1..=u16::MAX
+ */
+pub const HEADER_LEN: usize = 10;
+/* Лимит несжатого payload, общий для отправки и приема */
+pub const MAX_PAYLOAD_LEN: usize = 0xFFFFFF;
+pub const FLAG_LZ4: u8 = 1;
+/* Начальный размер буфера распаковки */
+const MIN_DECOMPRESS_LEN: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHeader {
+    pub ver: u8,
+    pub cmd: u8,
+    pub seq: u16,
+    pub opcode: u16,
+    pub flags: u8,
+    pub len: usize,
+}
+
+impl FrameHeader {
+    pub fn parse(buf: &[u8; HEADER_LEN]) -> Self {
+        let packed_len = u32::from_be_bytes([buf[6], buf[7], buf[8], buf[9]]);
+        Self {
+            ver: buf[0],
+            cmd: buf[1],
+            seq: u16::from_be_bytes([buf[2], buf[3]]),
+            opcode: u16::from_be_bytes([buf[4], buf[5]]),
+            flags: (packed_len >> 24) as u8,
+            len: (packed_len & 0xFFFFFF) as usize,
+        }
+    }
+
+    pub fn write(&self, dst: &mut BytesMut) {
+        dst.put_u8(self.ver);
+        dst.put_u8(self.cmd);
+        dst.put_u16(self.seq);
+        dst.put_u16(self.opcode);
+        dst.put_u32(((self.flags as u32) << 24) | (self.len as u32 & 0xFFFFFF));
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct MobileCodec {
+    compression_threshold: Option<usize>,
+    /* Буфер распаковки LZ4, переиспользуется между кадрами */
+    decompress_buf: Vec<u8>,
+}
+
+impl MobileCodec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /**
+     * Сжимать исходящие payload больше threshold байт (None - не сжимать)
+     */
+    pub fn with_compression(mut self, threshold: Option<usize>) -> Self {
+        self.compression_threshold = threshold;
+        self
+    }
+}
+
+impl Decoder for MobileCodec {
+    type Item = Response;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Response>, Error> {
+        if src.len() < HEADER_LEN {
+            return Ok(None);
+        }
+
+        let mut raw_header = [0u8; HEADER_LEN];
+        raw_header.copy_from_slice(&src[..HEADER_LEN]);
+        let header = FrameHeader::parse(&raw_header);
+
+        if src.len() < HEADER_LEN + header.len {
+            src.reserve(HEADER_LEN + header.len - src.len());
+            return Ok(None);
+        }
+
+        src.advance(HEADER_LEN);
+        let payload_buf = src.split_to(header.len);
+
+        let payload = if header.len == 0 {
+            JsonValue::Null
+        } else if header.flags != 0 {
+            let len = decompress(&payload_buf, &mut self.decompress_buf)?;
+            decode_payload(&self.decompress_buf[..len])?
+        } else {
+            decode_payload(&payload_buf)?
+        };
+
+        Ok(Some(Response {
+            ver: header.ver,
+            cmd: header.cmd,
+            seq: header.seq as u64,
+            opcode: header.opcode,
+            payload,
+        }))
+    }
+}
+
+impl Encoder<Request> for MobileCodec {
+    type Error = Error;
+
+    fn encode(&mut self, request: Request, dst: &mut BytesMut) -> Result<(), Error> {
+        let mut payload_bytes = rmp_serde::to_vec_named(&request.payload)
+            .map_err(|e| Error::SendFailed(format!("MsgPack encode error: {}", e)))?;
+
+        if payload_bytes.len() > MAX_PAYLOAD_LEN {
+            return Err(Error::SendFailed("Payload too large".into()));
+        }
+
+        let mut flags = 0;
+        if let Some(threshold) = self.compression_threshold {
+            if payload_bytes.len() > threshold {
+                let compressed = lz4_flex::block::compress(&payload_bytes);
+                if compressed.len() < payload_bytes.len() {
+                    payload_bytes = compressed;
+                    flags = FLAG_LZ4;
+                }
+            }
+        }
+
+        let header = FrameHeader {
+            ver: request.ver,
+            cmd: request.cmd,
+            seq: request.seq as u16,
+            opcode: request.opcode,
+            flags,
+            len: payload_bytes.len(),
+        };
+
+        dst.reserve(HEADER_LEN + payload_bytes.len());
+        header.write(dst);
+        dst.put_slice(&payload_bytes);
+
+        Ok(())
+    }
+}
+
+/**
+ * Распаковывает LZ4 block в buf и возвращает длину данных. Несжатый размер в кадре
+ * не передается, поэтому буфер растет по мере необходимости, но не больше MAX_PAYLOAD_LEN
+ */
+fn decompress(input: &[u8], buf: &mut Vec<u8>) -> Result<usize, Error> {
+    let mut size = buf
+        .len()
+        .max(input.len().saturating_mul(4))
+        .clamp(MIN_DECOMPRESS_LEN, MAX_PAYLOAD_LEN);
+
+    loop {
+        if buf.len() < size {
+            buf.resize(size, 0);
+        }
+
+        match lz4_flex::block::decompress_into(input, buf) {
+            Ok(len) => return Ok(len),
+            Err(DecompressError::OutputTooSmall { expected, .. }) if size < MAX_PAYLOAD_LEN => {
+                size = size.saturating_mul(2).max(expected).min(MAX_PAYLOAD_LEN);
+            }
+            Err(e) => return Err(Error::Decode(format!("LZ4 error: {}", e))),
+        }
+    }
+}
+
+fn decode_payload(bytes: &[u8]) -> Result<JsonValue, Error> {
+    if bytes.is_empty() {
+        return Ok(JsonValue::Null);
+    }
+
+    let mut buf = bytes;
+    let mp_value = read_value(&mut buf)
+        .map_err(|e| Error::Decode(format!("MsgPack decode error (rmpv): {}", e)))?;
+
+    Ok(msgpack_to_json(mp_value))
+}
+
+fn msgpack_to_json(val: MsgPackValue) -> JsonValue {
+    match val {
+        MsgPackValue::Nil => JsonValue::Null,
+        MsgPackValue::Boolean(b) => JsonValue::Bool(b),
+        MsgPackValue::Integer(i) => {
+            const MAX_SAFE_INT: u64 = 9_007_199_254_740_991;
+            if let Some(v) = i.as_u64() {
+                if v > MAX_SAFE_INT {
+                    JsonValue::String(v.to_string())
+                } else {
+                    JsonValue::Number(v.into())
+                }
+            }
+            else if let Some(v) = i.as_i64() {
+                 if v < -(MAX_SAFE_INT as i64) {
+                     JsonValue::String(v.to_string())
+                 } else {
+                     JsonValue::Number(v.into())
+                 }
+            } 
+            else {
+                JsonValue::Null
+            }
+        }
+        MsgPackValue::F32(f) => JsonValue::Number(serde_json::Number::from_f64(f as f64).unwrap_or_else(|| 0.into())),
+        MsgPackValue::F64(f) => JsonValue::Number(serde_json::Number::from_f64(f).unwrap_or_else(|| 0.into())),
+        MsgPackValue::String(s) => JsonValue::String(s.into_str().unwrap_or_default()),
+        MsgPackValue::Binary(b) => JsonValue::String(String::from_utf8_lossy(&b).to_string()),
+        MsgPackValue::Array(vec) => {
+            JsonValue::Array(vec.into_iter().map(msgpack_to_json).collect())
+        },
+        MsgPackValue::Map(vec) => {
+            let mut map = Map::new();
+            for (k, v) in vec {
+                let key_str = match k {
+                    MsgPackValue::String(s) => s.into_str().unwrap_or_default(),
+                    MsgPackValue::Integer(i) => i.to_string(),
+                    MsgPackValue::Boolean(b) => b.to_string(),
+                    _ => "unknown".to_string(),
+                };
+                map.insert(key_str, msgpack_to_json(v));
+            }
+            JsonValue::Object(map)
+        }
+        MsgPackValue::Ext(_, _) => JsonValue::Null,
+    }
+}
@@ -1,27 +1,26 @@
-use super::{TransportFactory, TransportReader, TransportWriter};
-use crate::models::{Request, Response};
+use super::{
+    codec::MobileCodec, proxy::{self, ProxyConfig}, tls::TlsConfig, BoxedTransport, TransportConnector, TransportFactory, TransportReader,
+    TransportWriter,
+};
+use crate::builder::ClientConfig;
+use crate::models::{Identity, Request, Response};
 use crate::errors::{ClientResult, Error};
 
 use async_trait::async_trait;
-use bytes::{BufMut, BytesMut};
-use tokio::io::{AsyncRead, AsyncWrite, AsyncReadExt, AsyncWriteExt, ReadBuf};
+use futures_util::{SinkExt, StreamExt};
+use log::info;
+use serde_json::{json, Value};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf, ReadHalf, WriteHalf};
 use tokio::net::TcpStream;
-use tokio_rustls::{client::TlsStream, TlsConnector};
+use tokio_rustls::client::TlsStream;
+use tokio_util::codec::{FramedRead, FramedWrite};
 use std::pin::Pin;
 use std::task::{Context, Poll};
-use std::{sync::Arc};
-use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
-use rustls::pki_types::pem::PemObject;
-use rustls::{ClientConfig, RootCertStore};
 
 
-use serde_json::{Map, Value as JsonValue};
-use rmpv::{Value as MsgPackValue};
-use rmpv::decode::read_value;
-
 pub enum MobileStream {
     Plain(TcpStream),
-    Tls(TlsStream<TcpStream>),
+    Tls(Box<TlsStream<TcpStream>>),
 }
 
 impl AsyncRead for MobileStream {
@@ -64,119 +63,84 @@ impl AsyncWrite for MobileStream {
     }
 }
 
-fn msgpack_to_json(val: MsgPackValue) -> JsonValue {
-    match val {
-        MsgPackValue::Nil => JsonValue::Null,
-        MsgPackValue::Boolean(b) => JsonValue::Bool(b),
-        MsgPackValue::Integer(i) => {
-            const MAX_SAFE_INT: u64 = 9_007_199_254_740_991;
-            if let Some(v) = i.as_u64() {
-                if v > MAX_SAFE_INT {
-                    JsonValue::String(v.to_string())
-                } else {
-                    JsonValue::Number(v.into())
-                }
-            }
-            else if let Some(v) = i.as_i64() {
-                 if v < -(MAX_SAFE_INT as i64) {
-                     JsonValue::String(v.to_string())
-                 } else {
-                     JsonValue::Number(v.into())
-                 }
-            } 
-            else {
-                JsonValue::Null
-            }
-        }
-        MsgPackValue::F32(f) => JsonValue::Number(serde_json::Number::from_f64(f as f64).unwrap_or_else(|| 0.into())),
-        MsgPackValue::F64(f) => JsonValue::Number(serde_json::Number::from_f64(f).unwrap_or_else(|| 0.into())),
-        MsgPackValue::String(s) => JsonValue::String(s.into_str().unwrap_or_default()),
-        MsgPackValue::Binary(b) => JsonValue::String(String::from_utf8_lossy(&b).to_string()),
-        MsgPackValue::Array(vec) => {
-            JsonValue::Array(vec.into_iter().map(msgpack_to_json).collect())
-        },
-        MsgPackValue::Map(vec) => {
-            let mut map = Map::new();
-            for (k, v) in vec {
-                let key_str = match k {
-                    MsgPackValue::String(s) => s.into_str().unwrap_or_default(),
-                    MsgPackValue::Integer(i) => i.to_string(),
-                    MsgPackValue::Boolean(b) => b.to_string(),
-                    _ => "unknown".to_string(),
-                };
-                map.insert(key_str, msgpack_to_json(v));
-            }
-            JsonValue::Object(map)
-        }
-        MsgPackValue::Ext(_, _) => JsonValue::Null,
-    }
-}
-
 pub struct MobileTransport {
     stream: MobileStream,
+    compression_threshold: Option<usize>,
 }
 
 impl MobileTransport {
     pub async fn connect_tls(host: &str, port: u16, cert_path: Option<&str>, key_path: Option<&str>) -> ClientResult<Self> {
-        let addr = format!("{}:{}", host, port);
-        let tcp = TcpStream::connect(&addr).await
-            .map_err(|e| Error::ConnectionFailed(format!("TCP Error: {}", e)))?;
-
-        let config = if let (Some(path_1), Some(path_2)) = (cert_path, key_path) {
-            trust_certificate(path_1, path_2)
-            .map_err(|e| Error::ConnectionFailed(format!("Cert load error: {}", e)))?
+        let tls = if let (Some(cert_path), Some(key_path)) = (cert_path, key_path) {
+            TlsConfig::ClientCert { cert_path: cert_path.into(), key_path: key_path.into() }
         } else {
-            Arc::new(create_tls_config())
+            TlsConfig::WebpkiRoots
         };
 
-        let connector = TlsConnector::from(config);
-        let domain = ServerName::try_from(host.to_string())
-            .map_err(|_| Error::ConnectionFailed("Invalid DNS name".into()))?;
-
-        let tls_stream = connector.connect(domain, tcp).await
-            .map_err(|e| Error::ConnectionFailed(format!("TLS Error: {}", e)))?;
-
-        Ok(Self { stream: MobileStream::Tls(tls_stream) })
+        Self::connect_with_tls(host, port, &tls, None).await
     }
-}
 
-fn trust_certificate(
-    certs_file: &str,
-    key_file: &str
-) -> Result<Arc<ClientConfig>, String> {
-    let certs: Vec<CertificateDer> =
-    CertificateDer::pem_file_iter(certs_file)
-        .unwrap()
-        .map(|c| c.unwrap())
-        .collect();
+    /**
+     * Подключение с TLS по настройкам tls (TlsConfig::Disabled - обычный TCP)
+     */
+    pub async fn connect_with_tls(host: &str, port: u16, tls: &TlsConfig, proxy: Option<&ProxyConfig>) -> ClientResult<Self> {
+        if !tls.is_enabled() {
+            return Self::connect_plain(host, port, proxy).await;
+        }
+
+        let tcp = proxy::dial(proxy, host, port).await?;
+        let tls_stream = tls.connect(host, tcp).await?;
 
-    let key = PrivateKeyDer::from_pem_file(key_file).unwrap();
+        Ok(Self::from_stream(MobileStream::Tls(Box::new(tls_stream))))
+    }
 
-    let mut root_store = RootCertStore::empty();
-    root_store.add_parsable_certificates(certs.clone());
+    /**
+     * Подключение без TLS: локальный openmax-server, отладочные прокси
+     */
+    pub async fn connect_plain(host: &str, port: u16, proxy: Option<&ProxyConfig>) -> ClientResult<Self> {
+        let tcp = proxy::dial(proxy, host, port).await?;
+        Ok(Self::from_stream(MobileStream::Plain(tcp)))
+    }
 
-    let config = ClientConfig::builder()
-        .with_root_certificates(root_store)
-        .with_client_auth_cert(certs, key)
-        .unwrap();
+    pub fn from_stream(stream: MobileStream) -> Self {
+        Self { stream, compression_threshold: None }
+    }
 
-    Ok(Arc::new(config))
+    /**
+     * Включает LZ4-сжатие исходящих кадров с payload больше threshold байт
+     */
+    pub fn with_compression(mut self, threshold: Option<usize>) -> Self {
+        self.compression_threshold = threshold;
+        self
+    }
 }
 
-fn create_tls_config() -> ClientConfig {
-    let mut root_store = rustls::RootCertStore::empty();
-    
-    root_store.extend(
-        webpki_roots::TLS_SERVER_ROOTS
-            .iter()
-            .cloned()
-    );
-    
-    let config = ClientConfig::builder()
-        .with_root_certificates(root_store)
-        .with_no_client_auth();
+/**
+ * Подключение к мобильному API (бинарные кадры + msgpack поверх TLS или TCP)
+ */
+pub struct MobileConnector;
 
-    config
+#[async_trait]
+impl TransportConnector for MobileConnector {
+    async fn connect(&self, config: &ClientConfig) -> ClientResult<BoxedTransport> {
+        info!("Подключение Mobile {}...", if config.tls.is_enabled() { "TCP/TLS" } else { "TCP" });
+        let transport = MobileTransport::connect_with_tls(
+            &config.mobile_host,
+            config.mobile_port,
+            &config.tls,
+            config.proxy.as_ref(),
+        ).await?
+            .with_compression(config.compression_threshold);
+        Ok(transport.boxed())
+    }
+
+    fn handshake_payload(&self, identity: &Identity) -> Value {
+        json!({
+            "clientSessionId": 1,
+            "mt_instanceid": identity.mt_instance,
+            "userAgent": identity.user_agent,
+            "deviceId": identity.device_id,
+        })
+    }
 }
 
 impl TransportFactory for MobileTransport {
@@ -185,104 +149,36 @@ impl TransportFactory for MobileTransport {
 
     fn split(self) -> (Self::Writer, Self::Reader) {
         let (reader, writer) = tokio::io::split(self.stream);
-        (MobileWriter { writer }, MobileReader { reader })
+        let codec = MobileCodec::new().with_compression(self.compression_threshold);
+        (
+            MobileWriter { writer: FramedWrite::new(writer, codec.clone()) },
+            MobileReader { reader: FramedRead::new(reader, codec) },
+        )
     }
 }
 
 pub struct MobileWriter {
-    writer: tokio::io::WriteHalf<MobileStream>,
+    writer: FramedWrite<WriteHalf<MobileStream>, MobileCodec>,
 }
 
 #[async_trait]
 impl TransportWriter for MobileWriter {
     async fn send(&mut self, request: Request) -> ClientResult<()> {
-        let payload_bytes = rmp_serde::to_vec_named(&request.payload)
-            .map_err(|e| Error::SendFailed(format!("MsgPack encode error: {}", e)))?;
-
-        let payload_len = payload_bytes.len();
-        if payload_len > 0xFFFFFF {
-            return Err(Error::SendFailed("Payload too large".into()));
-        }
-
-        let mut buffer = BytesMut::with_capacity(10 + payload_len);
-        buffer.put_u8(request.ver as u8);
-        buffer.put_u8(request.cmd as u8);
-        buffer.put_u16(request.seq as u16);
-        buffer.put_u16(request.opcode);
-        
-        let packed_len = (payload_len as u32) & 0xFFFFFF; 
-        buffer.put_u32(packed_len);
-        buffer.put_slice(&payload_bytes);
-
-        self.writer.write_all(&buffer).await
-            .map_err(|e| Error::SendFailed(e.to_string()))?;
-        Ok(())
+        /* Ошибка записи в сокет - обрыв соединения, как и в WebSocket */
+        self.writer.send(request).await.map_err(|e| match e {
+            Error::IoError(e) => Error::SendFailed(e.to_string()),
+            e => e,
+        })
     }
 }
 
 pub struct MobileReader {
-    reader: tokio::io::ReadHalf<MobileStream>,
+    reader: FramedRead<ReadHalf<MobileStream>, MobileCodec>,
 }
 
 #[async_trait]
 impl TransportReader for MobileReader {
     async fn next_message(&mut self) -> ClientResult<Option<Response>> {
-        let mut header = [0u8; 10];
-        match self.reader.read_exact(&mut header).await {
-            Ok(_) => {},
-            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
-            Err(e) => return Err(Error::ConnectionFailed(e.to_string())),
-        };
-
-        let ver = header[0];
-        let cmd = u16::from_be_bytes([header[1], header[2]]);
-        let seq = header[3] as u64;
-        let opcode = u16::from_be_bytes([header[4], header[5]]);
-        let packed_len_raw = u32::from_be_bytes([header[6], header[7], header[8], header[9]]);
-
-        let comp_flag = packed_len_raw >> 24;
-        let payload_len = (packed_len_raw & 0xFFFFFF) as usize;
-
-        if payload_len == 0 {
-            return Ok(Some(Response {
-                ver: ver, cmd: cmd as u8, seq, opcode, payload: JsonValue::Null,
-            }));
-        }
-        
-        let mut payload_buf = vec![0u8; payload_len];
-        self.reader.read_exact(&mut payload_buf).await
-            .map_err(|e| Error::ConnectionFailed(format!("Payload read err: {}", e)))?;
-        
-        let final_payload_bytes = if comp_flag != 0 {
-            let max_size = 5 * 1024 * 1024; 
-            lz4_flex::block::decompress(&payload_buf, max_size)
-                .map_err(|e| Error::ApiResponse(serde_json::json!({"error": "LZ4 error", "details": e.to_string()})))?
-        } else {
-            payload_buf
-        };
-        
-        if final_payload_bytes.is_empty() {
-             return Ok(Some(Response {
-                ver: ver, cmd: cmd as u8, seq, opcode, payload: JsonValue::Null,
-            }));
-        }
-        
-        let mut buf = &final_payload_bytes[..];
-        
-        let mp_value = read_value(&mut buf)
-            .map_err(|e| Error::ApiResponse(serde_json::json!({
-                "error": "MsgPack decode error (rmpv)", 
-                "details": e.to_string()
-            })))?;
-        
-        let payload_value = msgpack_to_json(mp_value);
-        
-        Ok(Some(Response {
-            ver: ver,
-            cmd: cmd as u8,
-            seq,
-            opcode,
-            payload: payload_value,
-        }))
+        self.reader.next().await.transpose()
     }
 }
@@ -1,14 +1,43 @@
+pub mod codec;
 pub mod mobile;
+pub mod proxy;
+pub mod record;
+pub mod replay;
+pub mod tls;
 pub mod web;
 
-use crate::models::{Request, Response};
+use crate::builder::ClientConfig;
+use crate::models::{Identity, Request, Response};
 use crate::errors::ClientResult;
 use async_trait::async_trait;
+use serde_json::Value;
+
+pub type BoxedTransport = (Box<dyn TransportWriter>, Box<dyn TransportReader>);
 
 pub trait TransportFactory: Send {
     type Reader: TransportReader;
     type Writer: TransportWriter;
     fn split(self) -> (Self::Writer, Self::Reader);
+
+    fn boxed(self) -> BoxedTransport
+    where
+        Self: Sized,
+        Self::Writer: 'static,
+        Self::Reader: 'static,
+    {
+        let (writer, reader) = self.split();
+        (Box::new(writer), Box::new(reader))
+    }
+}
+
+/**
+ * Способ подключения для ClientMode: открывает транспорт по настройкам клиента
+ * и формирует payload Handshake (opcode 6) для своего протокола
+ */
+#[async_trait]
+pub trait TransportConnector: Send + Sync {
+    async fn connect(&self, config: &ClientConfig) -> ClientResult<BoxedTransport>;
+    fn handshake_payload(&self, identity: &Identity) -> Value;
 }
 
 #[async_trait]
@@ -0,0 +1,202 @@
+use super::{TransportReader, TransportWriter};
+use crate::errors::ClientResult;
+use crate::models::{Request, Response};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, LineWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot};
+
+/**
+ * Пакет в записи трафика: исходящий запрос или входящий ответ/пуш
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum RecordedPacket {
+    Request(Request),
+    Response(Response),
+}
+
+/**
+ * Строка JSONL-файла записи: время в миллисекундах и пакет
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordEntry {
+    pub time: i64,
+    #[serde(flatten)]
+    pub packet: RecordedPacket,
+}
+
+enum Command {
+    Write(RecordEntry),
+    Flush(oneshot::Sender<()>),
+}
+
+/**
+ * Пишет весь трафик клиента в JSONL-файл, по строке на пакет.
+ * Подключается через MaxClientBuilder::recorder или MaxClient::set_recorder.
+ *
+ * Запись идет в отдельном потоке, record только кладет пакет в очередь.
+ * Значения полей *token (token, photoToken, ...) по умолчанию заменяются на "<redacted>",
+ * чтобы файл записи не давал доступа к аккаунту; keep_tokens отключает это
+ */
+pub struct TrafficRecorder {
+    tx: mpsc::UnboundedSender<Command>,
+    redact_tokens: bool,
+}
+
+impl TrafficRecorder {
+    /* Дописывает в конец файла, если он уже есть */
+    pub fn create(path: impl AsRef<Path>) -> ClientResult<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        std::thread::Builder::new()
+            .name("rumax-recorder".into())
+            .spawn(move || write_loop(rx, LineWriter::new(file)))?;
+
+        Ok(Self { tx, redact_tokens: true })
+    }
+
+    /* Писать токены как есть (для отладки авторизации на своем аккаунте) */
+    pub fn keep_tokens(mut self) -> Self {
+        self.redact_tokens = false;
+        self
+    }
+
+    pub fn record(&self, mut packet: RecordedPacket) {
+        if self.redact_tokens {
+            match &mut packet {
+                RecordedPacket::Request(request) => redact_tokens(&mut request.payload),
+                RecordedPacket::Response(response) => redact_tokens(&mut response.payload),
+            }
+        }
+
+        let entry = RecordEntry {
+            time: Utc::now().timestamp_millis(),
+            packet,
+        };
+        if self.tx.send(Command::Write(entry)).is_err() {
+            warn!("Поток записи трафика завершен, пакет пропущен");
+        }
+    }
+
+    /**
+     * Ждет, пока все пакеты, переданные в record, окажутся в файле
+     */
+    pub async fn flush(&self) {
+        let (done_tx, done_rx) = oneshot::channel();
+        if self.tx.send(Command::Flush(done_tx)).is_ok() {
+            let _ = done_rx.await;
+        }
+    }
+
+    /**
+     * Читает файл записи целиком
+     */
+    pub fn load(path: impl AsRef<Path>) -> ClientResult<Vec<RecordEntry>> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut entries = Vec::new();
+
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            entries.push(serde_json::from_str(&line)?);
+        }
+
+        Ok(entries)
+    }
+}
+
+fn write_loop(mut rx: mpsc::UnboundedReceiver<Command>, mut out: LineWriter<File>) {
+    while let Some(command) = rx.blocking_recv() {
+        match command {
+            Command::Write(entry) => {
+                let line = match serde_json::to_string(&entry) {
+                    Ok(line) => line,
+                    Err(e) => {
+                        warn!("Не удалось сериализовать пакет для записи: {}", e);
+                        continue;
+                    }
+                };
+                if let Err(e) = writeln!(out, "{}", line) {
+                    warn!("Не удалось записать трафик: {}", e);
+                }
+            }
+            Command::Flush(done) => {
+                if let Err(e) = out.flush() {
+                    warn!("Не удалось записать трафик: {}", e);
+                }
+                let _ = done.send(());
+            }
+        }
+    }
+}
+
+const REDACTED: &str = "<redacted>";
+
+/* Заменяет строковые значения полей token, photoToken и т.п. на любой глубине */
+fn redact_tokens(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, field) in map.iter_mut() {
+                if field.is_string() && key.to_ascii_lowercase().ends_with("token") {
+                    *field = Value::String(REDACTED.into());
+                } else {
+                    redact_tokens(field);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact_tokens),
+        _ => {}
+    }
+}
+
+pub(crate) struct RecordingWriter {
+    inner: Box<dyn TransportWriter>,
+    recorder: Arc<TrafficRecorder>,
+}
+
+impl RecordingWriter {
+    pub(crate) fn new(inner: Box<dyn TransportWriter>, recorder: Arc<TrafficRecorder>) -> Self {
+        Self { inner, recorder }
+    }
+}
+
+#[async_trait]
+impl TransportWriter for RecordingWriter {
+    async fn send(&mut self, request: Request) -> ClientResult<()> {
+        self.recorder.record(RecordedPacket::Request(request.clone()));
+        self.inner.send(request).await
+    }
+}
+
+pub(crate) struct RecordingReader {
+    inner: Box<dyn TransportReader>,
+    recorder: Arc<TrafficRecorder>,
+}
+
+impl RecordingReader {
+    pub(crate) fn new(inner: Box<dyn TransportReader>, recorder: Arc<TrafficRecorder>) -> Self {
+        Self { inner, recorder }
+    }
+}
+
+#[async_trait]
+impl TransportReader for RecordingReader {
+    async fn next_message(&mut self) -> ClientResult<Option<Response>> {
+        let message = self.inner.next_message().await?;
+        if let Some(resp) = &message {
+            self.recorder.record(RecordedPacket::Response(resp.clone()));
+        }
+        Ok(message)
+    }
+}
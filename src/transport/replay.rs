@@ -0,0 +1,168 @@
+use super::record::{RecordEntry, RecordedPacket, TrafficRecorder};
+use super::{TransportFactory, TransportReader, TransportWriter};
+use crate::errors::{ClientResult, Error};
+use crate::models::{Opcode, Request, Response};
+
+use async_trait::async_trait;
+use log::{debug, trace};
+use serde_json::json;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::Path;
+use tokio::sync::mpsc;
+
+/**
+ * Транспорт, проигрывающий запись TrafficRecorder.
+ *
+ * Ответы отдаются только после того, как клиент отправил соответствующий запрос
+ * (с тем же opcode), seq ответов переписывается на seq клиента. Пуши отдаются
+ * в исходном порядке. Пинги из записи пропускаются, на пинги клиента отвечаем сами.
+ * Подключается через MaxClient::connect_with_transport
+ */
+pub struct ReplayTransport {
+    entries: Vec<RecordEntry>,
+    keep_open: bool,
+}
+
+impl ReplayTransport {
+    pub fn new(entries: Vec<RecordEntry>) -> Self {
+        Self { entries, keep_open: false }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> ClientResult<Self> {
+        Ok(Self::new(TrafficRecorder::load(path)?))
+    }
+
+    /**
+     * Не закрывать соединение после конца записи (по умолчанию - EOF)
+     */
+    pub fn keep_open(mut self) -> Self {
+        self.keep_open = true;
+        self
+    }
+}
+
+impl TransportFactory for ReplayTransport {
+    type Reader = ReplayReader;
+    type Writer = ReplayWriter;
+
+    fn split(self) -> (Self::Writer, Self::Reader) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (
+            ReplayWriter { tx },
+            ReplayReader {
+                script: self.entries.into_iter().map(|e| e.packet).collect(),
+                sent: rx,
+                seq_map: HashMap::new(),
+                skipped: HashSet::new(),
+                outbox: VecDeque::new(),
+                keep_open: self.keep_open,
+            },
+        )
+    }
+}
+
+pub struct ReplayWriter {
+    tx: mpsc::UnboundedSender<Request>,
+}
+
+#[async_trait]
+impl TransportWriter for ReplayWriter {
+    async fn send(&mut self, request: Request) -> ClientResult<()> {
+        trace!("REPLAY Send {} (seq: {})", Opcode::describe(request.opcode), request.seq);
+        self.tx
+            .send(request)
+            .map_err(|_| Error::SendFailed("Replay transport is closed".to_string()))
+    }
+}
+
+pub struct ReplayReader {
+    script: VecDeque<RecordedPacket>,
+    sent: mpsc::UnboundedReceiver<Request>,
+    /* seq запроса в записи -> seq запроса клиента */
+    seq_map: HashMap<u64, u64>,
+    /* seq пингов из записи, ответы на них не проигрываются */
+    skipped: HashSet<u64>,
+    outbox: VecDeque<Response>,
+    keep_open: bool,
+}
+
+impl ReplayReader {
+    fn pong(request: &Request) -> Response {
+        Response {
+            ver: request.ver,
+            cmd: 1,
+            seq: request.seq,
+            opcode: request.opcode,
+            payload: json!({}),
+        }
+    }
+
+    /* Ждет следующий запрос клиента, отвечая на пинги */
+    async fn next_sent(&mut self) -> Option<Request> {
+        let request = self.sent.recv().await?;
+        if request.opcode == u16::from(Opcode::Ping) {
+            self.outbox.push_back(Self::pong(&request));
+        }
+        Some(request)
+    }
+}
+
+#[async_trait]
+impl TransportReader for ReplayReader {
+    async fn next_message(&mut self) -> ClientResult<Option<Response>> {
+        loop {
+            if let Some(resp) = self.outbox.pop_front() {
+                return Ok(Some(resp));
+            }
+
+            let Some(packet) = self.script.pop_front() else {
+                if !self.keep_open {
+                    debug!("Запись проиграна до конца");
+                    return Ok(None);
+                }
+                match self.next_sent().await {
+                    Some(_) => continue,
+                    None => return Ok(None),
+                }
+            };
+
+            match packet {
+                RecordedPacket::Request(recorded) if recorded.opcode == u16::from(Opcode::Ping) => {
+                    self.skipped.insert(recorded.seq);
+                }
+                RecordedPacket::Request(recorded) => {
+                    let Some(actual) = self.next_sent().await else {
+                        return Ok(None);
+                    };
+                    if actual.opcode == u16::from(Opcode::Ping) {
+                        /* Пинг клиента не из записи: ждем записанный запрос дальше */
+                        self.script.push_front(RecordedPacket::Request(recorded));
+                        continue;
+                    }
+                    if actual.opcode != recorded.opcode {
+                        return Err(Error::Protocol(format!(
+                            "Replay: ожидался запрос {}, клиент отправил {}",
+                            Opcode::describe(recorded.opcode),
+                            Opcode::describe(actual.opcode),
+                        )));
+                    }
+
+                    self.seq_map.insert(recorded.seq, actual.seq);
+                }
+                RecordedPacket::Response(mut resp) => {
+                    /* cmd 0 - пуш от сервера, seq не переписываем */
+                    if resp.cmd != 0 {
+                        if self.skipped.contains(&resp.seq) {
+                            continue;
+                        }
+                        if let Some(seq) = self.seq_map.get(&resp.seq) {
+                            resp.seq = *seq;
+                        }
+                    }
+
+                    return Ok(Some(resp));
+                }
+            }
+        }
+    }
+}
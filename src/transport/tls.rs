@@ -0,0 +1,117 @@
+use crate::errors::{ClientResult, Error};
+
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use rustls::{ClientConfig, RootCertStore};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::net::TcpStream;
+use tokio_rustls::{client::TlsStream, TlsConnector};
+
+/**
+ * Настройки TLS для мобильного и WebSocket транспортов
+ */
+#[derive(Clone, Default)]
+pub enum TlsConfig {
+    /* Корневые сертификаты из webpki-roots */
+    #[default]
+    WebpkiRoots,
+    /* Собственный набор корневых сертификатов (например, CA стенда) */
+    Roots(Arc<RootCertStore>),
+    /* PEM-бандл доверенных CA без клиентского ключа (например, самоподписанный openmax-server) */
+    CaFile(PathBuf),
+    /* Сертификат (он же доверенный корень) и ключ клиента в PEM */
+    ClientCert {
+        cert_path: PathBuf,
+        key_path: PathBuf,
+    },
+    /* Без TLS: мобильный транспорт работает по обычному TCP, WebSocket - только ws:// */
+    Disabled,
+}
+
+impl TlsConfig {
+    pub fn client_config(&self) -> ClientResult<Arc<ClientConfig>> {
+        match self {
+            TlsConfig::WebpkiRoots => Ok(Arc::new(create_tls_config())),
+            TlsConfig::Roots(roots) => Ok(Arc::new(
+                ClientConfig::builder()
+                    .with_root_certificates(roots.clone())
+                    .with_no_client_auth(),
+            )),
+            TlsConfig::CaFile(ca_path) => trust_ca_bundle(ca_path)
+                .map_err(|e| Error::ConnectionFailed(format!("CA load error: {}", e))),
+            TlsConfig::ClientCert { cert_path, key_path } => trust_certificate(cert_path, key_path)
+                .map_err(|e| Error::ConnectionFailed(format!("Cert load error: {}", e))),
+            TlsConfig::Disabled => Err(Error::ConnectionFailed("TLS is disabled".to_string())),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !matches!(self, TlsConfig::Disabled)
+    }
+
+    /**
+     * TLS поверх уже открытого TCP (в том числе туннеля через прокси)
+     */
+    pub async fn connect(&self, host: &str, tcp: TcpStream) -> ClientResult<TlsStream<TcpStream>> {
+        let connector = TlsConnector::from(self.client_config()?);
+        let domain = ServerName::try_from(host.to_string())
+            .map_err(|_| Error::ConnectionFailed("Invalid DNS name".into()))?;
+
+        connector.connect(domain, tcp).await
+            .map_err(|e| Error::ConnectionFailed(format!("TLS Error: {}", e)))
+    }
+}
+
+fn trust_ca_bundle(ca_file: &PathBuf) -> Result<Arc<ClientConfig>, String> {
+    let certs: Vec<CertificateDer> = CertificateDer::pem_file_iter(ca_file)
+        .map_err(|e| format!("{}: {}", ca_file.display(), e))?
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("{}: {}", ca_file.display(), e))?;
+
+    let mut root_store = RootCertStore::empty();
+    let (added, _) = root_store.add_parsable_certificates(certs);
+    if added == 0 {
+        return Err(format!("{}: no valid certificates", ca_file.display()));
+    }
+
+    let config = ClientConfig::builder()
+        .with_root_certificates(root_store)
+        .with_no_client_auth();
+
+    Ok(Arc::new(config))
+}
+
+fn trust_certificate(certs_file: &PathBuf, key_file: &PathBuf) -> Result<Arc<ClientConfig>, String> {
+    let certs: Vec<CertificateDer> = CertificateDer::pem_file_iter(certs_file)
+        .map_err(|e| format!("{}: {}", certs_file.display(), e))?
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("{}: {}", certs_file.display(), e))?;
+
+    let key = PrivateKeyDer::from_pem_file(key_file)
+        .map_err(|e| format!("{}: {}", key_file.display(), e))?;
+
+    let mut root_store = RootCertStore::empty();
+    root_store.add_parsable_certificates(certs.clone());
+
+    let config = ClientConfig::builder()
+        .with_root_certificates(root_store)
+        .with_client_auth_cert(certs, key)
+        .map_err(|e| e.to_string())?;
+
+    Ok(Arc::new(config))
+}
+
+fn create_tls_config() -> ClientConfig {
+    let mut root_store = RootCertStore::empty();
+
+    root_store.extend(
+        webpki_roots::TLS_SERVER_ROOTS
+            .iter()
+            .cloned()
+    );
+
+    ClientConfig::builder()
+        .with_root_certificates(root_store)
+        .with_no_client_auth()
+}
@@ -1,5 +1,10 @@
-use super::{TransportFactory, TransportReader, TransportWriter};
-use crate::models::{Request, Response};
+use super::{
+    proxy::ProxyConfig, tls::TlsConfig, BoxedTransport, TransportConnector, TransportFactory, TransportReader,
+    TransportWriter,
+};
+use crate::builder::ClientConfig;
+use crate::constants::Constants;
+use crate::models::{Identity, Opcode, Request, Response};
 use crate::errors::{ClientResult, Error};
 
 use async_trait::async_trait;
@@ -7,8 +12,12 @@ use futures_util::{
     stream::{SplitSink, SplitStream},
     SinkExt, StreamExt,
 };
-use log::{trace, warn};
-use yawc::{FrameView, WebSocket};
+use http::header::HeaderValue;
+use log::{info, trace, warn};
+use serde_json::{json, Value};
+use tokio_rustls::TlsConnector;
+use url::Url;
+use yawc::{CompressionLevel, FrameView, HttpRequestBuilder, Options, WebSocket};
 
 pub struct WebTransport {
     ws: WebSocket,
@@ -20,6 +29,80 @@ impl WebTransport {
     }
 }
 
+/**
+ * Подключение к WebSocket API (как web.max.ru)
+ */
+pub struct WebConnector;
+
+impl WebConnector {
+    /* yawc не умеет прокси: открываем туннель и TLS сами, затем делаем WS handshake поверх */
+    async fn connect_via_proxy(
+        uri: Url,
+        proxy: &ProxyConfig,
+        tls: &TlsConfig,
+        options: Options,
+        req_builder: HttpRequestBuilder,
+    ) -> ClientResult<WebSocket> {
+        let host = uri.host_str()
+            .ok_or_else(|| Error::ConnectionFailed("Websocket URI has no host".to_string()))?
+            .to_string();
+        let port = uri.port_or_known_default().unwrap_or(443);
+
+        let tcp = proxy.connect(&host, port).await?;
+
+        let result = if uri.scheme() == "wss" {
+            let stream = tls.connect(&host, tcp).await?;
+            WebSocket::handshake_with_request(uri, stream, options, req_builder).await
+        } else {
+            WebSocket::handshake_with_request(uri, tcp, options, req_builder).await
+        };
+
+        result.map_err(|e| Error::ConnectionFailed(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl TransportConnector for WebConnector {
+    async fn connect(&self, config: &ClientConfig) -> ClientResult<BoxedTransport> {
+        info!("Подключение к WebSocket...");
+        let req_builder = HttpRequestBuilder::new()
+            .header("Origin", HeaderValue::from_static(Constants::ORIGIN_HEADER))
+            .header("User-Agent", HeaderValue::from_static(Constants::USER_AGENT));
+
+        let uri: Url = config.websocket_uri.parse()
+            .map_err(|e| Error::ConnectionFailed(format!("Invalid websocket URI {}: {}", config.websocket_uri, e)))?;
+        let options = Options::default().with_compression_level(CompressionLevel::fast());
+
+        if uri.scheme() == "wss" && !config.tls.is_enabled() {
+            return Err(Error::ConnectionFailed(format!("TLS is disabled, but websocket URI is {}", uri)));
+        }
+
+        let ws = match &config.proxy {
+            Some(proxy) => Self::connect_via_proxy(uri, proxy, &config.tls, options, req_builder).await?,
+            None => {
+                let mut builder = WebSocket::connect(uri).with_options(options);
+                if config.tls.is_enabled() {
+                    builder = builder.with_connector(TlsConnector::from(config.tls.client_config()?));
+                }
+                builder
+                    .with_request(req_builder)
+                    .await
+                    .map_err(|e| Error::ConnectionFailed(e.to_string()))?
+            }
+        };
+
+        info!("WebSocket подключен.");
+        Ok(WebTransport::new(ws).boxed())
+    }
+
+    fn handshake_payload(&self, identity: &Identity) -> Value {
+        json!({
+            "deviceId": identity.device_id,
+            "userAgent": identity.user_agent,
+        })
+    }
+}
+
 impl TransportFactory for WebTransport {
     type Reader = WebReader;
     type Writer = WebWriter;
@@ -40,7 +123,7 @@ impl TransportWriter for WebWriter {
         let json = serde_json::to_string(&request)
             .map_err(|e| Error::SendFailed(format!("JSON serialization error: {}", e)))?;
 
-        trace!("WEB Send (seq: {}): {}", request.seq, json);
+        trace!("WEB Send {} (seq: {}): {}", Opcode::describe(request.opcode), request.seq, json);
 
         self.writer
             .send(FrameView::text(json))
@@ -66,7 +149,7 @@ impl TransportReader for WebReader {
                     Err(e) => {
                         let body_str = String::from_utf8_lossy(body_bytes);
                         warn!("JSON Error: {} | Body: {}", e, body_str);
-                        Err(Error::ApiResponse(serde_json::json!({"error": e.to_string()})))
+                        Err(Error::Decode(format!("JSON decode error: {}", e)))
                     }
                 }
             }
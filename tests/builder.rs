@@ -0,0 +1,120 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use rumax::builder::{ClientConfig, MaxClientBuilder};
+use rumax::constants::Constants;
+use rumax::retry::{RateLimit, RetryPolicy};
+use rumax::transport::proxy::{ProxyConfig, ProxyKind};
+use rumax::transport::tls::TlsConfig;
+use rumax::ClientMode;
+use rustls::RootCertStore;
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+}
+
+fn install_crypto_provider() {
+    let _ = rustls::crypto::ring::default_provider().install_default();
+}
+
+#[test]
+fn client_config_defaults() {
+    let config = ClientConfig::default();
+
+    assert_eq!(config.websocket_uri, Constants::WEBSOCKET_URI);
+    assert_eq!(config.mobile_host, Constants::MOBILE_HOST);
+    assert_eq!(config.mobile_port, Constants::MOBILE_PORT);
+    assert!(matches!(config.tls, TlsConfig::WebpkiRoots));
+    assert_eq!(config.request_timeout, Constants::DEFAULT_TIMEOUT);
+    assert_eq!(config.ping_interval, Constants::PING_INTERVAL);
+    assert_eq!(config.event_capacity, Constants::EVENT_CHANNEL_CAPACITY);
+    assert!(!config.traffic_events);
+    assert!(matches!(config.mode, ClientMode::Web));
+    assert_eq!(config.compression_threshold, None);
+    assert!(config.auto_reconnect);
+    assert_eq!(config.reconnect_max_attempts, None);
+    assert!(config.recorder.is_none());
+    assert!(config.proxy.is_none());
+    assert_eq!(config.retry.max_retries, 0);
+    assert_eq!(config.send_rate_limit, None);
+    assert!(config.photo_preprocessor.is_none());
+}
+
+#[test]
+fn builder_settings_round_trip_into_config() {
+    let builder = MaxClientBuilder::new()
+        .websocket_uri("ws://127.0.0.1:8080/websocket")
+        .mobile_host("127.0.0.1", 4433)
+        .tls(TlsConfig::Disabled)
+        .request_timeout(Duration::from_secs(3))
+        .ping_interval(Duration::from_secs(5))
+        .event_capacity(128)
+        .traffic_events(true)
+        .mode(ClientMode::Mobile)
+        .compression_threshold(Some(512))
+        .auto_reconnect(false)
+        .reconnect_max_attempts(4)
+        .proxy(ProxyConfig::socks5("10.0.0.1", 1080).with_auth("user", "pass"))
+        .retry_policy(RetryPolicy::enabled())
+        .send_rate_limit(0, Duration::from_millis(250))
+        .photo_preprocessor(rumax::media::StripExif);
+    let config = builder.config();
+
+    assert_eq!(config.websocket_uri, "ws://127.0.0.1:8080/websocket");
+    assert_eq!((config.mobile_host.as_str(), config.mobile_port), ("127.0.0.1", 4433));
+    assert!(!config.tls.is_enabled());
+    assert_eq!(config.request_timeout, Duration::from_secs(3));
+    assert_eq!(config.ping_interval, Duration::from_secs(5));
+    assert_eq!(config.event_capacity, 128);
+    assert!(config.traffic_events);
+    assert!(matches!(config.mode, ClientMode::Mobile));
+    assert_eq!(config.compression_threshold, Some(512));
+    assert!(!config.auto_reconnect);
+    assert_eq!(config.reconnect_max_attempts, Some(4));
+    let proxy = config.proxy.as_ref().unwrap();
+    assert_eq!(proxy.kind, ProxyKind::Socks5);
+    assert_eq!((proxy.host.as_str(), proxy.port), ("10.0.0.1", 1080));
+    assert_eq!(proxy.username.as_deref(), Some("user"));
+    assert_eq!(config.retry.max_retries, Constants::RETRY_MAX_ATTEMPTS);
+    /* burst меньше 1 не имеет смысла */
+    assert_eq!(config.send_rate_limit, Some(RateLimit { burst: 1, interval: Duration::from_millis(250) }));
+    assert!(config.photo_preprocessor.is_some());
+
+    assert_eq!(MaxClientBuilder::new().event_capacity(0).config().event_capacity, 1);
+}
+
+#[tokio::test]
+async fn builder_config_reaches_client() {
+    let client = MaxClientBuilder::new().tls(TlsConfig::Disabled).mode(ClientMode::Mobile).build();
+    assert!(!client.is_connected().await);
+    assert!(client.get_identity().await.is_none());
+}
+
+#[test]
+fn tls_config_construction() {
+    install_crypto_provider();
+
+    assert!(TlsConfig::default().is_enabled());
+    assert!(TlsConfig::WebpkiRoots.client_config().is_ok());
+    assert!(TlsConfig::Roots(Arc::new(RootCertStore::empty())).client_config().is_ok());
+
+    assert!(!TlsConfig::Disabled.is_enabled());
+    assert!(TlsConfig::Disabled.client_config().is_err());
+
+    assert!(TlsConfig::CaFile(fixture("localhost.cert.pem")).client_config().is_ok());
+    assert!(TlsConfig::CaFile(fixture("missing.pem")).client_config().is_err());
+    /* В файле ключа нет сертификатов */
+    assert!(TlsConfig::CaFile(fixture("localhost.key.pem")).client_config().is_err());
+
+    let client_cert = TlsConfig::ClientCert {
+        cert_path: fixture("localhost.cert.pem"),
+        key_path: fixture("localhost.key.pem"),
+    };
+    assert!(client_cert.client_config().is_ok());
+    let swapped = TlsConfig::ClientCert {
+        cert_path: fixture("localhost.key.pem"),
+        key_path: fixture("localhost.cert.pem"),
+    };
+    assert!(swapped.client_config().is_err());
+}
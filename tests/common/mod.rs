@@ -0,0 +1,56 @@
+/* Общие помощники для тестов загрузки, скачивания, отправки медиа и outbox */
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+
+use rumax::models::Request;
+use rumax::testing::{self, MockProtocol, MockServer};
+use rumax::MaxClient;
+use serde_json::{json, Value};
+use tempfile::TempDir;
+
+/* Ответ на MSG_SEND: сообщение с cid и текстом из запроса, id совпадает с cid */
+pub fn echo_message(request: &Request) -> Option<Value> {
+    let cid = request.payload["message"]["cid"].clone();
+    Some(json!({ "message": { "id": cid, "cid": cid, "text": request.payload["message"]["text"] } }))
+}
+
+/* Сервер с заглушками загрузки и подключенный к нему клиент */
+pub async fn start() -> (MockServer, MaxClient) {
+    let server = MockServer::start(MockProtocol::Web).await.unwrap();
+    server.mock_uploads();
+    let client = server.client_builder().build();
+    client.connect(testing::identity()).await.unwrap();
+    (server, client)
+}
+
+/* Файл во временной папке, папка удаляется вместе с TempDir */
+pub fn temp_file(dir: &TempDir, name: &str, content: &[u8]) -> PathBuf {
+    let path = dir.path().join(name);
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+pub fn content(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+/* Content-Range запросов загрузки на path */
+pub fn content_ranges(server: &MockServer, path: &str) -> Vec<(u64, u64, u64)> {
+    server
+        .http_requests()
+        .iter()
+        .filter(|r| r.path == path)
+        .filter_map(|r| r.content_range())
+        .collect()
+}
+
+/* Заголовки Range запросов скачивания с path */
+pub fn range_headers(server: &MockServer, path: &str) -> Vec<Option<String>> {
+    server
+        .http_requests()
+        .iter()
+        .filter(|r| r.path == path)
+        .map(|r| r.header("range").map(str::to_string))
+        .collect()
+}
@@ -0,0 +1,199 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use rumax::builder::ClientConfig;
+use rumax::errors::ClientResult;
+use rumax::models::{Identity, Opcode, Request, Response, UserAgent};
+use rumax::transport::{BoxedTransport, TransportConnector, TransportReader, TransportWriter};
+use rumax::events::{ConnectionState, Event};
+use rumax::{ClientMode, MaxClient};
+use serde_json::{json, Value};
+use tokio::sync::{mpsc, oneshot};
+
+/* In-memory транспорт: на каждый запрос сразу отвечает эхом payload */
+struct EchoWriter {
+    tx: mpsc::UnboundedSender<Response>,
+}
+
+#[async_trait]
+impl TransportWriter for EchoWriter {
+    async fn send(&mut self, request: Request) -> ClientResult<()> {
+        let _ = self.tx.send(Response {
+            ver: request.ver,
+            cmd: 1,
+            seq: request.seq,
+            opcode: request.opcode,
+            payload: request.payload,
+        });
+        Ok(())
+    }
+}
+
+struct EchoReader {
+    rx: mpsc::UnboundedReceiver<Response>,
+    close_rx: oneshot::Receiver<()>,
+}
+
+#[async_trait]
+impl TransportReader for EchoReader {
+    async fn next_message(&mut self) -> ClientResult<Option<Response>> {
+        tokio::select! {
+            resp = self.rx.recv() => Ok(resp),
+            _ = &mut self.close_rx => Ok(None),
+        }
+    }
+}
+
+fn echo_transport() -> (Box<dyn TransportWriter>, Box<dyn TransportReader>, oneshot::Sender<()>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let (close_tx, close_rx) = oneshot::channel();
+    (Box::new(EchoWriter { tx }), Box::new(EchoReader { rx, close_rx }), close_tx)
+}
+
+/* Ридер, который сразу отдает EOF: сервер закрывает соединение до ответа на Handshake */
+struct EofReader;
+
+#[async_trait]
+impl TransportReader for EofReader {
+    async fn next_message(&mut self) -> ClientResult<Option<Response>> {
+        Ok(None)
+    }
+}
+
+/* Первые healthy подключений получают эхо-транспорт, остальные - EOF */
+struct FlakyConnector {
+    healthy: usize,
+    connects: AtomicUsize,
+    close_tx: Mutex<Option<oneshot::Sender<()>>>,
+}
+
+impl FlakyConnector {
+    fn new(healthy: usize) -> Self {
+        FlakyConnector { healthy, connects: AtomicUsize::new(0), close_tx: Mutex::new(None) }
+    }
+
+    fn connects(&self) -> usize {
+        self.connects.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl TransportConnector for FlakyConnector {
+    async fn connect(&self, _config: &ClientConfig) -> ClientResult<BoxedTransport> {
+        if self.connects.fetch_add(1, Ordering::SeqCst) < self.healthy {
+            let (writer, reader, close_tx) = echo_transport();
+            *self.close_tx.lock().unwrap() = Some(close_tx);
+            Ok((writer, reader))
+        } else {
+            let (writer, _, _) = echo_transport();
+            Ok((writer, Box::new(EofReader)))
+        }
+    }
+
+    fn handshake_payload(&self, identity: &Identity) -> Value {
+        json!({ "deviceId": identity.device_id })
+    }
+}
+
+fn identity() -> Identity {
+    Identity {
+        device_id: "device".to_string(),
+        mt_instance: "instance".to_string(),
+        user_agent: UserAgent {
+            device_type: "WEB".to_string(),
+            app_version: "25.12.1".to_string(),
+            os_version: "Linux".to_string(),
+            timezone: "Europe/Moscow".to_string(),
+            screen: "1920x1080 1.0x".to_string(),
+            push_device_type: None,
+            arch: None,
+            locale: "ru".to_string(),
+            build_number: 1,
+            device_name: "Firefox".to_string(),
+            device_locale: "ru".to_string(),
+            release: None,
+            header_user_agent: None,
+        },
+    }
+}
+
+#[tokio::test]
+async fn connect_with_transport_sends_handshake_and_requests() {
+    let (writer, reader, _close_tx) = echo_transport();
+    let client = MaxClient::new();
+
+    let handshake = client.connect_with_transport(identity(), writer, reader).await.unwrap();
+
+    assert_eq!(handshake.opcode, u16::from(Opcode::SessionInit));
+    assert_eq!(handshake.payload["deviceId"], "device");
+    assert!(client.is_connected().await);
+
+    let resp = client.call(Opcode::ChatInfo.into(), json!({ "chatIds": [1] })).await.unwrap();
+    assert_eq!(resp.payload, json!({ "chatIds": [1] }));
+}
+
+#[tokio::test]
+async fn custom_transport_is_not_reconnected_after_eof() {
+    let (writer, reader, close_tx) = echo_transport();
+    let client = MaxClient::new();
+    let mut events = client.subscribe();
+
+    client.connect_with_transport(identity(), writer, reader).await.unwrap();
+
+    /* Ридер получает EOF */
+    close_tx.send(()).unwrap();
+
+    loop {
+        match events.recv().await.unwrap() {
+            Event::ConnectionState(ConnectionState::Disconnected) => break,
+            Event::ConnectionState(ConnectionState::Reconnecting { .. }) => {
+                panic!("custom transport must not reconnect")
+            }
+            _ => {}
+        }
+    }
+    assert!(!client.is_connected().await);
+}
+
+#[tokio::test]
+async fn failed_handshake_does_not_start_reconnect() {
+    let connector = std::sync::Arc::new(FlakyConnector::new(0));
+    let client = MaxClient::builder().mode(ClientMode::Custom(connector.clone())).build();
+
+    assert!(client.connect(identity()).await.is_err());
+
+    /* Первая попытка реконнекта была бы через RECONNECT_MIN_DELAY (1 с) */
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    assert_eq!(connector.connects(), 1);
+    assert!(!client.is_connected().await);
+}
+
+#[tokio::test]
+async fn eof_on_handshake_counts_one_connect_per_attempt() {
+    let connector = std::sync::Arc::new(FlakyConnector::new(1));
+    let client = MaxClient::builder()
+        .mode(ClientMode::Custom(connector.clone()))
+        .reconnect_max_attempts(2)
+        .build();
+    client.connect(identity()).await.unwrap();
+    let mut events = client.subscribe();
+
+    connector.close_tx.lock().unwrap().take().unwrap().send(()).unwrap();
+
+    tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            if let Event::ConnectionState(ConnectionState::Failed { .. }) = events.recv().await.unwrap() {
+                break;
+            }
+        }
+    })
+    .await
+    .expect("reconnect did not fail");
+
+    /* Первое подключение и по одному на каждую из двух попыток, новых циклов нет */
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    assert_eq!(connector.connects(), 3);
+    assert!(!client.is_connected().await);
+}
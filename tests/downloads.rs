@@ -0,0 +1,187 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rumax::errors::{DownloadError, Error};
+use rumax::models::{Attachment, DownloadOptions, Opcode};
+use rumax::testing::HttpReply;
+use serde_json::json;
+
+mod common;
+
+use common::{content, range_headers, start};
+
+fn sha256(data: &[u8]) -> String {
+    ring::digest::digest(&ring::digest::SHA256, data)
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[tokio::test]
+async fn download_streams_to_writer_with_progress_and_checksum() {
+    let (server, client) = start().await;
+    let data = content(5000);
+    server.serve_download("/media/a", data.clone());
+
+    let progress = Arc::new(Mutex::new(Vec::new()));
+    let seen = progress.clone();
+    let options = DownloadOptions::new()
+        .sha256(&sha256(&data))
+        .on_progress(move |p| seen.lock().unwrap().push((p.transferred, p.total)));
+
+    let mut out = Vec::new();
+    let size = client.download(&server.http_url("/media/a"), &mut out, &options).await.unwrap();
+
+    assert_eq!(size, 5000);
+    assert_eq!(out, data);
+    assert_eq!(progress.lock().unwrap().last(), Some(&(5000, Some(5000))));
+
+    let options = DownloadOptions::new().sha256(&sha256(b"other"));
+    let result = client.download(&server.http_url("/media/a"), &mut Vec::new(), &options).await;
+    assert!(matches!(result, Err(Error::Download(DownloadError::ChecksumMismatch { .. }))), "{:?}", result);
+
+    let options = DownloadOptions::new().size(4000);
+    let result = client.download(&server.http_url("/media/a"), &mut Vec::new(), &options).await;
+    assert!(
+        matches!(result, Err(Error::Download(DownloadError::SizeMismatch { expected: 4000, actual: 5000 }))),
+        "{:?}",
+        result
+    );
+}
+
+#[tokio::test]
+async fn partial_file_is_resumed_with_range() {
+    let (server, client) = start().await;
+    let data = content(3000);
+    server.serve_download("/media/b", data.clone());
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("resume.bin");
+    std::fs::write(&path, &data[..1200]).unwrap();
+
+    let options = DownloadOptions::new().resume(true).sha256(&sha256(&data));
+    let size = client.download_to_path(&server.http_url("/media/b"), &path, &options).await.unwrap();
+
+    assert_eq!(size, 3000);
+    assert_eq!(std::fs::read(&path).unwrap(), data);
+    assert_eq!(range_headers(&server, "/media/b"), vec![Some("bytes=1200-".to_string())]);
+
+    /* Файл уже целиком скачан: сервер отвечает 416 */
+    let size = client.download_to_path(&server.http_url("/media/b"), &path, &options).await.unwrap();
+    assert_eq!(size, 3000);
+    assert_eq!(std::fs::read(&path).unwrap(), data);
+}
+
+#[tokio::test]
+async fn existing_file_is_overwritten_by_default() {
+    let (server, client) = start().await;
+    let data = content(3000);
+    server.serve_download("/media/new", data.clone());
+
+    /* Старый файл с тем же именем не должен склеиться с новым содержимым */
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("stale.bin");
+    std::fs::write(&path, vec![0xEE; 1200]).unwrap();
+
+    let size = client
+        .download_to_path(&server.http_url("/media/new"), &path, &DownloadOptions::new())
+        .await
+        .unwrap();
+
+    assert_eq!(size, 3000);
+    assert_eq!(std::fs::read(&path).unwrap(), data);
+    assert_eq!(range_headers(&server, "/media/new"), vec![None]);
+}
+
+#[tokio::test]
+async fn dropped_connections_continue_from_written_offset() {
+    let (server, client) = start().await;
+    let data = Arc::new(content(2500));
+    let calls = Arc::new(AtomicUsize::new(0));
+    let (body, counter) = (data.clone(), calls.clone());
+
+    /* Каждый ответ обрывается после 1000 байт, второй запрос падает с 503 */
+    server.on_http("/media/c", move |exchange| {
+        if counter.fetch_add(1, Ordering::SeqCst) == 1 {
+            return HttpReply::new(503, "");
+        }
+        let start: usize = exchange
+            .header("range")
+            .and_then(|r| r.strip_prefix("bytes="))
+            .and_then(|r| r.trim_end_matches('-').parse().ok())
+            .unwrap_or(0);
+        let end = (start + 1000).min(body.len());
+        HttpReply::new(206, body[start..end].to_vec())
+            .header("Content-Range", format!("bytes {}-{}/{}", start, body.len() - 1, body.len()))
+    });
+
+    let options = DownloadOptions::new().retry_delay(Duration::from_millis(10));
+    let mut out = Vec::new();
+    client.download(&server.http_url("/media/c"), &mut out, &options).await.unwrap();
+
+    assert_eq!(out, *data);
+    assert_eq!(
+        range_headers(&server, "/media/c"),
+        vec![
+            None,
+            Some("bytes=1000-".to_string()),
+            Some("bytes=1000-".to_string()),
+            Some("bytes=2000-".to_string()),
+        ]
+    );
+
+    server.on_http("/media/down", |_| HttpReply::new(502, ""));
+    let options = options.max_retries(1);
+    let result = client.download(&server.http_url("/media/down"), &mut Vec::new(), &options).await;
+    match result {
+        Err(Error::Download(DownloadError::Interrupted { offset: 0, source })) => {
+            assert!(matches!(*source, DownloadError::Status { status: 502 }));
+        }
+        other => panic!("expected interrupted download, got {:?}", other),
+    }
+
+    server.on_http("/media/missing", |_| HttpReply::new(404, ""));
+    let result = client.download(&server.http_url("/media/missing"), &mut Vec::new(), &options).await;
+    assert!(matches!(result, Err(Error::Download(DownloadError::Status { status: 404 }))), "{:?}", result);
+}
+
+#[tokio::test]
+async fn attachments_are_resolved_and_downloaded() {
+    let (server, client) = start().await;
+    server.serve_download("/photo/original", b"photo".to_vec());
+    server.serve_download("/video/480", b"low".to_vec());
+    server.serve_download("/video/1080", b"high".to_vec());
+    server.serve_download("/file/report", b"report".to_vec());
+    server.respond(Opcode::VideoPlay, json!({
+        "MP4_480": server.http_url("/video/480"),
+        "MP4_1080": server.http_url("/video/1080"),
+        "cache": false,
+    }));
+    server.respond(Opcode::FileDownload, json!({ "url": server.http_url("/file/report"), "unsafe": false }));
+
+    let cases = [
+        (json!({ "_type": "PHOTO", "photoId": 1, "baseUrl": server.http_url("/photo/original") }), &b"photo"[..]),
+        (json!({ "_type": "VIDEO", "videoId": 2 }), &b"high"[..]),
+        (json!({ "_type": "FILE", "fileId": 3, "name": "report.txt", "size": 6 }), &b"report"[..]),
+    ];
+    let dir = tempfile::tempdir().unwrap();
+    for (attach, expected) in cases {
+        let attach: Attachment = serde_json::from_value(attach).unwrap();
+        let path = dir.path().join(format!("{:?}", attach.kind));
+
+        client
+            .download_attachment(5, 10, &attach, &path, &DownloadOptions::new())
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), expected);
+    }
+
+    let video = server.wait_for_request(Opcode::VideoPlay).await.unwrap();
+    assert_eq!(video.payload, json!({ "chatId": 5, "messageId": 10, "videoId": 2 }));
+
+    let sticker: Attachment = serde_json::from_value(json!({ "_type": "STICKER" })).unwrap();
+    let result = client.attachment_url(5, 10, &sticker).await;
+    assert!(matches!(result, Err(Error::Download(DownloadError::NoUrl(_)))), "{:?}", result);
+}
@@ -0,0 +1,89 @@
+use std::time::Duration;
+
+use rumax::errors::{ApiError, Error, ErrorCodes};
+use rumax::models::Opcode;
+use rumax::testing::{self, MockProtocol, MockServer};
+use serde_json::json;
+
+#[tokio::test]
+async fn api_error_is_parsed() {
+    let server = MockServer::start(MockProtocol::Mobile).await.unwrap();
+    server.respond(Opcode::ChatInfo, json!({
+        "error": "chat.not.found",
+        "message": "Chat not found",
+        "localizedMessage": "Чат не найден",
+        "title": "Ошибка",
+    }));
+
+    let client = server.client_builder().build();
+    client.connect(testing::identity()).await.unwrap();
+
+    match client.get_chats(vec![1]).await {
+        Err(Error::Api(e)) => {
+            assert_eq!(e.code, "chat.not.found");
+            assert_eq!(e.message.as_deref(), Some("Chat not found"));
+            assert_eq!(e.localized_message.as_deref(), Some("Чат не найден"));
+            assert_eq!(e.title.as_deref(), Some("Ошибка"));
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn expired_token_is_auth_expired() {
+    let server = MockServer::start(MockProtocol::Web).await.unwrap();
+    server.respond_error(Opcode::Login, "login.token", "Token expired");
+
+    let codes = ErrorCodes { auth_expired: vec!["login.token".into()], ..ErrorCodes::default() };
+    let client = server.client_builder().error_codes(codes).build();
+    client.connect(testing::identity()).await.unwrap();
+    client.set_token("stale".to_string()).await;
+
+    assert!(matches!(client.sync().await, Err(Error::AuthExpired(_))));
+}
+
+#[tokio::test]
+async fn unconfigured_code_stays_api_error() {
+    let server = MockServer::start(MockProtocol::Web).await.unwrap();
+    server.respond_error(Opcode::Login, "login.token", "Token expired");
+
+    let client = server.client_builder().build();
+    client.connect(testing::identity()).await.unwrap();
+    client.set_token("stale".to_string()).await;
+
+    assert!(matches!(client.sync().await, Err(Error::Api(e)) if e.code == "login.token"));
+}
+
+#[test]
+fn rate_limit_classification() {
+    let codes = ErrorCodes {
+        rate_limited: vec!["test.rate_limited".into()],
+        transient: vec!["test.transient".into()],
+        ..ErrorCodes::default()
+    };
+
+    let flood = codes.classify(json!({ "error": "test.flood", "retryAfter": 3 }));
+    match flood {
+        Error::FloodWait { error, retry_after } => {
+            assert_eq!(error.code, "test.flood");
+            assert_eq!(retry_after, Duration::from_secs(3));
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+
+    assert!(matches!(codes.classify(json!({ "error": "test.rate_limited" })), Error::RateLimited(_)));
+    assert!(matches!(codes.classify(json!({ "error": "test.transient" })), Error::Transient(_)));
+    /* Без настроенного кода и без задержки - обычная ошибка API */
+    assert!(matches!(codes.classify(json!({ "error": "test.flood" })), Error::Api(_)));
+    assert!(matches!(Error::from_api(json!({ "error": "test.rate_limited" })), Error::Api(_)));
+
+    let unknown = ApiError::from_payload(json!({ "message": "boom" }));
+    assert_eq!(unknown.code, "unknown");
+    assert_eq!(unknown.to_string(), "boom (unknown)");
+}
+
+#[test]
+fn error_implements_std_error() {
+    let error: Box<dyn std::error::Error> = Box::new(Error::Decode("bad frame".into()));
+    assert_eq!(error.to_string(), "Ошибка декодирования: bad frame");
+}
@@ -0,0 +1,135 @@
+use std::time::Duration;
+
+use rumax::events::Event;
+use rumax::models::{Opcode, Response};
+use rumax::testing::{self, MockProtocol, MockServer};
+use serde_json::{json, Value};
+
+fn push(opcode: Opcode, payload: Value) -> Event {
+    Event::from_push(Response { ver: 11, cmd: 0, seq: 0, opcode: opcode.into(), payload })
+}
+
+#[test]
+fn notif_message_decodes_new_edited_and_removed() {
+    let message = |status: Value| json!({ "chatId": "-100", "message": { "id": "55", "text": "hi", "status": status } });
+
+    match push(Opcode::NotifMessage, message(Value::Null)) {
+        Event::NewMessage { chat_id, message } => {
+            assert_eq!(chat_id, -100);
+            assert_eq!(message.id, 55);
+            assert_eq!(message.text, "hi");
+        }
+        other => panic!("unexpected event {:?}", other),
+    }
+    assert!(matches!(
+        push(Opcode::NotifMessage, message(json!("EDITED"))),
+        Event::MessageEdited { chat_id: -100, .. }
+    ));
+    match push(Opcode::NotifMessage, message(json!("REMOVED"))) {
+        Event::MessageDeleted { chat_id, message_ids } => {
+            assert_eq!(chat_id, -100);
+            assert_eq!(message_ids, vec![55]);
+        }
+        other => panic!("unexpected event {:?}", other),
+    }
+}
+
+#[test]
+fn notif_typing_mark_and_presence() {
+    assert!(matches!(
+        push(Opcode::NotifTyping, json!({ "chatId": 1, "userId": "2" })),
+        Event::Typing { chat_id: 1, user_id: 2 }
+    ));
+    assert!(matches!(
+        push(Opcode::NotifMark, json!({ "chatId": 1, "userId": 2, "mark": 1700000000000i64 })),
+        Event::ReadMark { chat_id: 1, user_id: 2, mark: 1700000000000 }
+    ));
+    match push(Opcode::NotifPresence, json!({ "userId": 3, "presence": { "seen": 10, "status": "ON" } })) {
+        Event::Presence { user_id, presence } => {
+            assert_eq!(user_id, 3);
+            assert_eq!(presence.seen, Some(10));
+            assert_eq!(presence.status.as_deref(), Some("ON"));
+        }
+        other => panic!("unexpected event {:?}", other),
+    }
+}
+
+#[test]
+fn notif_chat_delete_and_reactions() {
+    match push(Opcode::NotifChat, json!({ "chat": { "id": 9, "title": "Team" } })) {
+        Event::ChatUpdated { chat } => {
+            assert_eq!(chat.id, 9);
+            assert_eq!(chat.title.as_deref(), Some("Team"));
+        }
+        other => panic!("unexpected event {:?}", other),
+    }
+    match push(Opcode::NotifMsgDelete, json!({ "chatId": 9, "messageIds": ["1", 2, null] })) {
+        Event::MessageDeleted { chat_id, message_ids } => {
+            assert_eq!(chat_id, 9);
+            assert_eq!(message_ids, vec![1, 2]);
+        }
+        other => panic!("unexpected event {:?}", other),
+    }
+    let reactions = json!({
+        "chatId": 9,
+        "messageId": "77",
+        "totalCount": 3,
+        "counters": [{ "reaction": "👍", "count": 3 }],
+        "yourReaction": "👍",
+    });
+    match push(Opcode::NotifMsgReactionsChanged, reactions) {
+        Event::ReactionChanged { chat_id, message_id, reaction_info } => {
+            assert_eq!((chat_id, message_id), (9, 77));
+            assert_eq!(reaction_info.total_count, 3);
+            assert_eq!(reaction_info.counters[0].count, 3);
+            assert_eq!(reaction_info.your_reaction.as_deref(), Some("👍"));
+        }
+        other => panic!("unexpected event {:?}", other),
+    }
+}
+
+#[test]
+fn unknown_or_malformed_push_is_raw() {
+    let cases = [
+        (u16::from(Opcode::NotifAttach), json!({ "fileId": 1 })),
+        (9999, json!({ "anything": true })),
+        (u16::from(Opcode::NotifTyping), json!({ "chatId": 1 })),
+        (u16::from(Opcode::NotifMessage), json!({ "chatId": 1, "message": { "text": "no id" } })),
+    ];
+    for (opcode, payload) in cases {
+        match Event::from_push(Response { ver: 11, cmd: 0, seq: 0, opcode, payload: payload.clone() }) {
+            Event::Raw(resp) => {
+                assert_eq!(resp.opcode, opcode);
+                assert_eq!(resp.payload, payload);
+            }
+            other => panic!("expected Raw for {}, got {:?}", opcode, other),
+        }
+    }
+}
+
+async fn traffic_events_seen(enabled: bool) -> usize {
+    let server = MockServer::start(MockProtocol::Web).await.unwrap();
+    server.respond(Opcode::Log, json!({}));
+    let client = server.client_builder().traffic_events(enabled).build();
+    client.connect(testing::identity()).await.unwrap();
+    let mut events = client.subscribe();
+
+    client.send_and_wait(Opcode::Log, json!({ "events": [] }), 0).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let mut seen = 0;
+    while let Ok(event) = events.try_recv() {
+        match event {
+            Event::Request(r) if r.opcode == u16::from(Opcode::Log) => seen += 1,
+            Event::Response(r) if r.opcode == u16::from(Opcode::Log) => seen += 1,
+            _ => {}
+        }
+    }
+    seen
+}
+
+#[tokio::test]
+async fn request_and_response_events_are_opt_in() {
+    assert_eq!(traffic_events_seen(false).await, 0);
+    assert_eq!(traffic_events_seen(true).await, 2);
+}
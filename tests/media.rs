@@ -0,0 +1,274 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rumax::constants::Constants;
+use rumax::errors::{ClientResult, Error, UploadError};
+use rumax::media::{self, image, PhotoData, PhotoPreprocessor, ResizePhoto, StripExif};
+use rumax::models::{MessageBuilder, Opcode, UploadOptions};
+use rumax::testing::{self, MockProtocol, MockServer};
+use rumax::MaxClient;
+use serde_json::json;
+
+mod common;
+
+use common::{echo_message, temp_file};
+
+async fn start() -> (MockServer, MaxClient) {
+    let (server, client) = common::start().await;
+    server.on(Opcode::MsgSend, echo_message);
+    (server, client)
+}
+
+fn sent_attaches(server: &MockServer) -> serde_json::Value {
+    let request = server
+        .requests()
+        .into_iter()
+        .rfind(|r| r.opcode == u16::from(Opcode::MsgSend))
+        .expect("MSG_SEND not sent");
+    request.payload["message"]["attaches"].clone()
+}
+
+#[tokio::test]
+async fn send_photo_uploads_and_attaches_token() {
+    let (server, client) = start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let path = temp_file(&dir, "cat.jpg", b"\xFF\xD8\xFFjpeg");
+
+    let message = client
+        .send_photo(MessageBuilder::new(5).text("Смотри"), &path)
+        .await
+        .unwrap();
+
+    assert_eq!(message.text, "Смотри");
+    assert_eq!(
+        sent_attaches(&server),
+        json!([{ "_type": "PHOTO", "photoToken": "photo-token" }])
+    );
+    let upload = server.http_requests().into_iter().find(|r| r.path == "/upload/photo").unwrap();
+    assert!(upload.header("content-type").unwrap().starts_with("multipart/form-data"));
+}
+
+#[tokio::test]
+async fn send_video_retries_until_attachment_is_ready() {
+    let (server, client) = start().await;
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let counter = attempts.clone();
+    server.on(Opcode::MsgSend, move |request| {
+        if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+            return Some(json!({ "error": "attachment.not.ready", "message": "Not ready" }));
+        }
+        echo_message(request)
+    });
+
+    let content = vec![7u8; 1024];
+    let dir = tempfile::tempdir().unwrap();
+    let path = temp_file(&dir, "clip.mp4", &content);
+
+    /* NOTIF_ATTACH со строковым videoId будит повтор раньше ATTACH_RETRY_DELAY */
+    let started = Instant::now();
+    let ready = async {
+        server.wait_for_request(Opcode::MsgSend).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        server.push(Opcode::NotifAttach, json!({ "videoId": "101" }));
+    };
+    let (sent, _) = tokio::join!(client.send_video(MessageBuilder::new(5), &path), ready);
+    sent.unwrap();
+    assert!(started.elapsed() < Constants::ATTACH_RETRY_DELAY, "{:?}", started.elapsed());
+
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    assert_eq!(server.uploaded("/upload/video").unwrap(), content);
+    assert_eq!(
+        sent_attaches(&server),
+        json!([{ "_type": "VIDEO", "videoId": 101, "token": "video-token" }])
+    );
+
+    let cids: Vec<_> = server
+        .requests()
+        .into_iter()
+        .filter(|r| r.opcode == u16::from(Opcode::MsgSend))
+        .map(|r| r.payload["message"]["cid"].clone())
+        .collect();
+    assert_eq!(cids[0], cids[1]);
+}
+
+#[tokio::test]
+async fn send_file_reports_upload_failure() {
+    let (server, client) = start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let path = temp_file(&dir, "report.pdf", b"%PDF-1.4");
+
+    client.send_file(MessageBuilder::new(5), &path).await.unwrap();
+    assert_eq!(sent_attaches(&server), json!([{ "_type": "FILE", "fileId": 202 }]));
+    assert_eq!(server.uploaded("/upload/file").unwrap(), b"%PDF-1.4");
+
+    server.on_http("/upload/file", |_| testing::HttpReply::new(500, "boom"));
+    let options = UploadOptions::new().max_retries(0);
+    assert!(client.send_file_with(MessageBuilder::new(5), &path, &options).await.is_err());
+}
+
+#[test]
+fn mime_is_sniffed_from_content() {
+    let cases: [(&[u8], &str); 11] = [
+        (b"\xFF\xD8\xFF\xE0\0\x10JFIF", "image/jpeg"),
+        (b"GIF89a\x01\0\x01\0", "image/gif"),
+        (b"RIFF\x24\0\0\0WEBPVP8 ", "image/webp"),
+        (b"\0\0\0\x18ftypheic\0\0\0\0", "image/heic"),
+        (b"\0\0\0\x18ftypisom\0\0\x02\0", "video/mp4"),
+        (b"\0\0\0\x14ftypqt  \0\0\0\0", "video/quicktime"),
+        (b"\x1A\x45\xDF\xA3\x9F\x42\x86\x81\x01\x42\x82\x84webm", "video/webm"),
+        (b"ID3\x04\0\0\0\0\0\0", "audio/mpeg"),
+        (b"\xFF\xFB\x90\x64\0\0", "audio/mpeg"),
+        (b"OggS\0\x02\0\0", "audio/ogg"),
+        (b"%PDF-1.7\n", "application/pdf"),
+    ];
+    for (bytes, expected) in cases {
+        assert_eq!(media::sniff_mime(bytes), Some(expected));
+        assert_eq!(media::detect_mime(bytes, "no-extension"), Some(expected));
+    }
+
+    assert_eq!(
+        media::detect_mime(b"PK\x03\x04\x14\0", "report.docx"),
+        Some("application/vnd.openxmlformats-officedocument.wordprocessingml.document")
+    );
+    assert_eq!(media::detect_mime(b"PK\x03\x04\x14\0", "archive"), Some("application/zip"));
+    assert_eq!(media::detect_mime(b"plain text", "notes.txt"), Some("text/plain"));
+    assert_eq!(media::sniff_mime(b"plain text"), None);
+
+    /* Похоже на синхрослово MP3, но это BOM UTF-16LE, заполнение или недопустимый кадр */
+    assert_eq!(media::sniff_mime(b"\xFF\xFEh\0i\0"), None);
+    assert_eq!(media::sniff_mime(b"\xFF\xFF\xFF\xFF"), None);
+    assert_eq!(media::sniff_mime(b"\xFF\xFB\xF0\x64"), None);
+    assert_eq!(media::sniff_mime(b"\xFF\xFB\x9C\x64"), None);
+    assert_eq!(media::sniff_mime(b"\xFF\xFB"), None);
+    assert_eq!(media::detect_mime(b"\xFF\xFEh\0i\0", "notes.txt"), Some("text/plain"));
+}
+
+/* APP1 с EXIF (little-endian TIFF): GPSInfo и Orientation */
+fn exif_app1(orientation: u16) -> Vec<u8> {
+    let mut tiff = b"II*\0\x08\0\0\0\x02\0".to_vec();
+    tiff.extend_from_slice(b"\x25\x88\x04\0\x01\0\0\0\x26\0\0\0");
+    tiff.extend_from_slice(b"\x12\x01\x03\0\x01\0\0\0");
+    tiff.extend_from_slice(&orientation.to_le_bytes());
+    tiff.extend_from_slice(b"\0\0\0\0\0\0");
+
+    let mut segment = b"\xFF\xE1".to_vec();
+    segment.extend_from_slice(&(tiff.len() as u16 + 8).to_be_bytes());
+    segment.extend_from_slice(b"Exif\0\0");
+    segment.extend_from_slice(&tiff);
+    segment
+}
+
+/* SOI, сегменты заголовка, DQT и SOS со сжатыми данными */
+fn jpeg_with(segments: &[&[u8]]) -> Vec<u8> {
+    let mut jpeg = b"\xFF\xD8".to_vec();
+    segments.iter().for_each(|segment| jpeg.extend_from_slice(segment));
+    jpeg.extend_from_slice(b"\xFF\xDB\x00\x04\x01\x02\xFF\xDA\x00\x02\xAB\xCD\xFF\xD9");
+    jpeg
+}
+
+#[test]
+fn strip_exif_keeps_only_orientation() {
+    let jfif = &b"\xFF\xE0\x00\x06JFIF"[..];
+    let xmp = &b"\xFF\xE1\x00\x09http://"[..];
+    let strip = |bytes: Vec<u8>| StripExif.process(PhotoData { bytes, mime: "image/jpeg".into() }).unwrap().bytes;
+
+    /* EXIF без Orientation и XMP удаляются целиком */
+    assert_eq!(strip(jpeg_with(&[jfif, b"\xFF\xE1\x00\x08Exif\0\0", xmp])), jpeg_with(&[jfif]));
+    assert_eq!(strip(jpeg_with(&[jfif, &exif_app1(1)])), jpeg_with(&[jfif]));
+
+    /* От EXIF остается только Orientation */
+    let orientation = &b"\xFF\xE1\x00\x22Exif\0\0MM\0*\0\0\0\x08\0\x01\x01\x12\x00\x03\0\0\0\x01\0\x06\0\0\0\0\0\0"[..];
+    assert_eq!(strip(jpeg_with(&[jfif, &exif_app1(6), xmp])), jpeg_with(&[jfif, orientation]));
+
+    /* Байты заполнения 0xFF перед маркерами пропускаются */
+    assert_eq!(strip(jpeg_with(&[b"\xFF\xFF", jfif, b"\xFF\xFF\xFF", &exif_app1(1)])), jpeg_with(&[jfif]));
+
+    let png = PhotoData { bytes: b"\x89PNG\r\n\x1A\n".to_vec(), mime: "image/png".into() };
+    assert_eq!(StripExif.process(png.clone()).unwrap(), png);
+
+    /* Сегмент обрезан: отправлять как есть нельзя, EXIF остался бы на месте */
+    let broken = PhotoData { bytes: [&b"\xFF\xD8"[..], jfif, &exif_app1(6)[..10]].concat(), mime: "image/jpeg".into() };
+    let result = StripExif.process(broken);
+    assert!(matches!(result, Err(Error::Upload(UploadError::Preprocess(_)))), "{:?}", result);
+}
+
+fn encode(image: image::DynamicImage, format: image::ImageFormat) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    image.write_to(&mut std::io::Cursor::new(&mut bytes), format).unwrap();
+    bytes
+}
+
+#[test]
+fn resize_photo_scales_rotates_and_reencodes() {
+    let decode = |photo: &PhotoData| image::load_from_memory(&photo.bytes).unwrap();
+
+    let png = encode(image::DynamicImage::new_rgba8(400, 200), image::ImageFormat::Png);
+    let resized = ResizePhoto::new(100).process(PhotoData { bytes: png, mime: "image/png".into() }).unwrap();
+    assert_eq!(resized.mime, "image/jpeg");
+    assert_eq!((decode(&resized).width(), decode(&resized).height()), (100, 50));
+
+    /* Orientation 6: поворот на 90 градусов применяется к пикселям */
+    let jpeg = encode(image::DynamicImage::new_rgb8(40, 20), image::ImageFormat::Jpeg);
+    let rotated = [&jpeg[..2], &exif_app1(6), &jpeg[2..]].concat();
+    let resized = ResizePhoto::default().process(PhotoData { bytes: rotated, mime: "image/jpeg".into() }).unwrap();
+    assert_eq!((decode(&resized).width(), decode(&resized).height()), (20, 40));
+
+    let gif = PhotoData { bytes: b"GIF89a\x01\0\x01\0".to_vec(), mime: "image/gif".into() };
+    assert_eq!(ResizePhoto::default().process(gif.clone()).unwrap(), gif);
+
+    let heic = PhotoData { bytes: b"\0\0\0\x18ftypheic\0\0\0\0".to_vec(), mime: "image/heic".into() };
+    let result = ResizePhoto::default().process(heic);
+    assert!(matches!(result, Err(Error::Upload(UploadError::UnsupportedType(_)))), "{:?}", result);
+}
+
+#[tokio::test]
+async fn send_media_sends_unsupported_photo_as_file() {
+    let heic = b"\0\0\0\x18ftypheic\0\0\0\0";
+    let dir = tempfile::tempdir().unwrap();
+    let path = temp_file(&dir, "e.heic", heic);
+
+    let (server, client) = start().await;
+    client.send_media(MessageBuilder::new(5), &path).await.unwrap();
+    assert_eq!(sent_attaches(&server), json!([{ "_type": "FILE", "fileId": 202 }]));
+    assert!(!server.http_requests().iter().any(|r| r.path == "/upload/photo"));
+
+    /* Preprocessor не перекодировал HEIC: тоже документом */
+    let server = MockServer::start(MockProtocol::Web).await.unwrap();
+    server.mock_uploads();
+    server.on(Opcode::MsgSend, echo_message);
+    let client = server.client_builder().photo_preprocessor(|photo: PhotoData| Ok(photo)).build();
+    client.connect(testing::identity()).await.unwrap();
+    client.send_media(MessageBuilder::new(5), &path).await.unwrap();
+    assert_eq!(sent_attaches(&server), json!([{ "_type": "FILE", "fileId": 202 }]));
+}
+
+#[tokio::test]
+async fn photo_preprocessor_runs_before_upload() {
+    let server = MockServer::start(MockProtocol::Web).await.unwrap();
+    server.mock_uploads();
+    server.on(Opcode::MsgSend, echo_message);
+    let client = server
+        .client_builder()
+        .photo_preprocessor(|photo: PhotoData| -> ClientResult<PhotoData> {
+            assert_eq!(photo.mime, "image/webp");
+            Ok(PhotoData { bytes: b"\xFF\xD8\xFFconverted".to_vec(), mime: "image/jpeg".into() })
+        })
+        .build();
+    client.connect(testing::identity()).await.unwrap();
+
+    /* Расширение не подсказывает тип: send_media определяет фото по содержимому */
+    let dir = tempfile::tempdir().unwrap();
+    let path = temp_file(&dir, "picture.bin", b"RIFF\x24\0\0\0WEBPVP8 data");
+    client.send_media(MessageBuilder::new(5), &path).await.unwrap();
+
+    assert_eq!(
+        sent_attaches(&server),
+        json!([{ "_type": "PHOTO", "photoToken": "photo-token" }])
+    );
+    let upload = server.http_requests().into_iter().find(|r| r.path == "/upload/photo").unwrap();
+    let body = String::from_utf8_lossy(&upload.body);
+    assert!(body.contains("filename=\"image.jpg\""), "{}", body);
+    assert!(body.contains("Content-Type: image/jpeg"), "{}", body);
+    assert!(body.contains("converted"), "{}", body);
+}
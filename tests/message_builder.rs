@@ -0,0 +1,68 @@
+use rumax::models::{MessageBuilder, Opcode, OutgoingAttachment};
+use rumax::testing::{self, MockProtocol, MockServer};
+use serde_json::json;
+
+#[test]
+fn formatting_offsets_are_utf16() {
+    let payload = MessageBuilder::new(7)
+        .text("Привет 👋 ")
+        .bold("жирный")
+        .text(" ")
+        .mention(42, "@Иван")
+        .text(" ")
+        .link("сайт", "https://max.ru")
+        .code("")
+        .payload(1);
+
+    assert_eq!(payload["chatId"], 7);
+    assert_eq!(payload["notify"], true);
+    assert_eq!(payload["message"]["text"], "Привет 👋 жирный @Иван сайт");
+    assert_eq!(payload["message"]["elements"], json!([
+        { "type": "STRONG", "from": 10, "length": 6 },
+        { "type": "USER_MENTION", "from": 17, "length": 5, "entityId": 42 },
+        { "type": "LINK", "from": 23, "length": 4, "attributes": { "url": "https://max.ru" } },
+    ]));
+    assert!(payload["message"].get("link").is_none());
+    assert!(payload.get("itemType").is_none());
+}
+
+#[test]
+fn reply_forward_attachments_and_delayed() {
+    let payload = MessageBuilder::new(1)
+        .reply_to(100)
+        .attach(OutgoingAttachment::Photo { photo_token: "tok".into() })
+        .attach(OutgoingAttachment::File { file_id: 5 })
+        .silent()
+        .delayed(1_700_000_000_000)
+        .payload(9);
+
+    assert_eq!(payload["message"]["cid"], 9);
+    assert_eq!(payload["message"]["link"], json!({ "type": "REPLY", "messageId": 100 }));
+    assert_eq!(payload["message"]["attaches"], json!([
+        { "_type": "PHOTO", "photoToken": "tok" },
+        { "_type": "FILE", "fileId": 5 },
+    ]));
+    assert_eq!(payload["notify"], false);
+    assert_eq!(payload["itemType"], "DELAYED");
+    assert_eq!(payload["delayedAttributes"]["timeToFire"], 1_700_000_000_000i64);
+
+    let forward = MessageBuilder::new(1).forward(2, 3).payload(1);
+    assert_eq!(forward["message"]["link"], json!({ "type": "FORWARD", "chatId": 2, "messageId": 3 }));
+}
+
+#[tokio::test]
+async fn send_uses_builder_payload() {
+    let server = MockServer::start(MockProtocol::Web).await.unwrap();
+    server.on(Opcode::MsgSend, |request| {
+        Some(json!({ "message": { "id": 1, "cid": request.payload["message"]["cid"], "text": "hi" } }))
+    });
+
+    let client = server.client_builder().build();
+    client.connect(testing::identity()).await.unwrap();
+
+    let message = client.send(MessageBuilder::new(3).italic("hi").cid(77)).await.unwrap();
+    assert_eq!(message.cid, Some(77));
+
+    let request = server.wait_for_request(Opcode::MsgSend).await.unwrap();
+    assert_eq!(request.payload["message"]["elements"][0]["type"], "EMPHASIZED");
+}
@@ -0,0 +1,176 @@
+use bytes::{BufMut, BytesMut};
+use rumax::models::{Request, Response};
+use rumax::transport::codec::{FrameHeader, MobileCodec, FLAG_LZ4, HEADER_LEN, MAX_PAYLOAD_LEN};
+use serde_json::json;
+use tokio_util::codec::{Decoder, Encoder};
+
+fn request(seq: u64, opcode: u16, payload: serde_json::Value) -> Request {
+    Request { ver: 10, cmd: 0, seq, opcode, payload }
+}
+
+fn round_trip(req: Request) -> Response {
+    let mut codec = MobileCodec::new();
+    let mut buf = BytesMut::new();
+    codec.encode(req, &mut buf).unwrap();
+    let resp = codec.decode(&mut buf).unwrap().expect("full frame");
+    assert!(buf.is_empty());
+    resp
+}
+
+#[test]
+fn round_trip_preserves_header_fields() {
+    let payload = json!({ "chatId": -68123, "text": "привет", "notify": true });
+    let resp = round_trip(request(42, 64, payload.clone()));
+
+    assert_eq!(resp.ver, 10);
+    assert_eq!(resp.cmd, 0);
+    assert_eq!(resp.seq, 42);
+    assert_eq!(resp.opcode, 64);
+    assert_eq!(resp.payload, payload);
+}
+
+#[test]
+fn round_trip_keeps_seq_above_255() {
+    for seq in [255, 256, 1000, 40_000, u16::MAX as u64] {
+        let resp = round_trip(request(seq, 1, json!({ "interactive": true })));
+        assert_eq!(resp.seq, seq);
+        assert_eq!(resp.cmd, 0);
+    }
+}
+
+#[test]
+fn header_layout_is_cmd_u8_seq_u16() {
+    let mut buf = BytesMut::new();
+    let req = Request { ver: 11, cmd: 3, seq: 0x1234, opcode: 0x0040, payload: json!(null) };
+    MobileCodec::new().encode(req, &mut buf).unwrap();
+
+    assert_eq!(&buf[..6], &[11, 3, 0x12, 0x34, 0x00, 0x40]);
+
+    let mut raw = [0u8; HEADER_LEN];
+    raw.copy_from_slice(&buf[..HEADER_LEN]);
+    let header = FrameHeader::parse(&raw);
+    assert_eq!(header.seq, 0x1234);
+    assert_eq!(header.flags, 0);
+    assert_eq!(header.len, buf.len() - HEADER_LEN);
+}
+
+#[test]
+fn decode_waits_for_complete_frame() {
+    let mut full = BytesMut::new();
+    MobileCodec::new().encode(request(7, 49, json!({ "chatId": 1, "backward": 40 })), &mut full).unwrap();
+
+    let mut codec = MobileCodec::new();
+    let mut partial = BytesMut::new();
+    for byte in full.iter().take(full.len() - 1) {
+        partial.put_u8(*byte);
+        assert!(codec.decode(&mut partial).unwrap().is_none());
+    }
+
+    partial.put_u8(full[full.len() - 1]);
+    let resp = codec.decode(&mut partial).unwrap().expect("full frame");
+    assert_eq!(resp.seq, 7);
+    assert_eq!(resp.payload["backward"], 40);
+}
+
+#[test]
+fn decode_splits_consecutive_frames() {
+    let mut buf = BytesMut::new();
+    MobileCodec::new().encode(request(1, 1, json!({})), &mut buf).unwrap();
+    MobileCodec::new().encode(request(2, 19, json!({ "token": "abc" })), &mut buf).unwrap();
+
+    let mut codec = MobileCodec::new();
+    assert_eq!(codec.decode(&mut buf).unwrap().unwrap().seq, 1);
+    assert_eq!(codec.decode(&mut buf).unwrap().unwrap().payload["token"], "abc");
+    assert!(codec.decode(&mut buf).unwrap().is_none());
+}
+
+#[test]
+fn decode_lz4_compressed_payload() {
+    let payload = json!({ "messages": vec!["повтор"; 64] });
+    let packed = rmp_serde::to_vec_named(&payload).unwrap();
+    let compressed = lz4_flex::block::compress(&packed);
+
+    let mut buf = BytesMut::new();
+    FrameHeader { ver: 10, cmd: 1, seq: 300, opcode: 49, flags: FLAG_LZ4, len: compressed.len() }.write(&mut buf);
+    buf.put_slice(&compressed);
+
+    let resp = MobileCodec::new().decode(&mut buf).unwrap().unwrap();
+    assert_eq!(resp.seq, 300);
+    assert_eq!(resp.cmd, 1);
+    assert_eq!(resp.payload, payload);
+}
+
+#[test]
+fn decode_empty_payload_as_null() {
+    let mut buf = BytesMut::new();
+    FrameHeader { ver: 10, cmd: 1, seq: 5, opcode: 1, flags: 0, len: 0 }.write(&mut buf);
+
+    let resp = MobileCodec::new().decode(&mut buf).unwrap().unwrap();
+    assert_eq!(resp.payload, serde_json::Value::Null);
+}
+
+#[test]
+fn compressed_round_trip_sets_flag() {
+    let payload = json!({ "events": vec![json!({ "event": "NAV", "type": "NAV" }); 200] });
+    let mut codec = MobileCodec::new().with_compression(Some(256));
+    let mut buf = BytesMut::new();
+    codec.encode(request(900, 5, payload.clone()), &mut buf).unwrap();
+
+    let mut raw = [0u8; HEADER_LEN];
+    raw.copy_from_slice(&buf[..HEADER_LEN]);
+    let header = FrameHeader::parse(&raw);
+    assert_eq!(header.flags, FLAG_LZ4);
+    assert!(header.len < rmp_serde::to_vec_named(&payload).unwrap().len());
+
+    let resp = codec.decode(&mut buf).unwrap().unwrap();
+    assert_eq!(resp.seq, 900);
+    assert_eq!(resp.payload, payload);
+}
+
+#[test]
+fn payload_below_threshold_is_not_compressed() {
+    let mut codec = MobileCodec::new().with_compression(Some(1024));
+    let mut buf = BytesMut::new();
+    codec.encode(request(1, 1, json!({ "interactive": true })), &mut buf).unwrap();
+
+    let mut raw = [0u8; HEADER_LEN];
+    raw.copy_from_slice(&buf[..HEADER_LEN]);
+    assert_eq!(FrameHeader::parse(&raw).flags, 0);
+    assert_eq!(codec.decode(&mut buf).unwrap().unwrap().payload["interactive"], true);
+}
+
+#[test]
+fn compressed_payload_limit_matches_in_both_directions() {
+    let mut codec = MobileCodec::new().with_compression(Some(1024));
+
+    /* Сжимается в килобайты, но после распаковки больше старых 5 МБ */
+    let large = json!({ "text": "a".repeat(8 * 1024 * 1024) });
+    let mut buf = BytesMut::new();
+    codec.encode(request(1, 64, large.clone()), &mut buf).unwrap();
+    assert_eq!(codec.decode(&mut buf).unwrap().unwrap().payload, large);
+
+    let too_large = json!({ "text": "a".repeat(MAX_PAYLOAD_LEN) });
+    let mut buf = BytesMut::new();
+    assert!(codec.encode(request(2, 64, too_large), &mut buf).is_err());
+    assert!(buf.is_empty());
+}
+
+#[test]
+fn decompressed_payload_over_limit_is_rejected() {
+    let mut codec = MobileCodec::new();
+    let packed = rmp_serde::to_vec_named(&json!({ "text": "a".repeat(MAX_PAYLOAD_LEN + 1) })).unwrap();
+    let compressed = lz4_flex::block::compress(&packed);
+
+    let mut buf = BytesMut::new();
+    FrameHeader { ver: 10, cmd: 1, seq: 1, opcode: 64, flags: FLAG_LZ4, len: compressed.len() }.write(&mut buf);
+    buf.put_slice(&compressed);
+    assert!(codec.decode(&mut buf).is_err());
+
+    /* Буфер распаковки переиспользуется: следующий кадр разбирается как обычно */
+    let payload = json!({ "messages": vec!["повтор"; 64] });
+    let compressed = lz4_flex::block::compress(&rmp_serde::to_vec_named(&payload).unwrap());
+    let mut buf = BytesMut::new();
+    FrameHeader { ver: 10, cmd: 1, seq: 2, opcode: 64, flags: FLAG_LZ4, len: compressed.len() }.write(&mut buf);
+    buf.put_slice(&compressed);
+    assert_eq!(codec.decode(&mut buf).unwrap().unwrap().payload, payload);
+}
@@ -0,0 +1,133 @@
+use std::time::Duration;
+
+use rumax::events::{ConnectionState, Event};
+use rumax::models::Opcode;
+use rumax::testing::{self, MockProtocol, MockServer};
+use serde_json::json;
+use tokio::sync::broadcast;
+use tokio::time::timeout;
+
+async fn next_event<F>(events: &mut broadcast::Receiver<Event>, mut matches: F) -> Event
+where
+    F: FnMut(&Event) -> bool,
+{
+    timeout(Duration::from_secs(5), async {
+        loop {
+            let event = events.recv().await.unwrap();
+            if matches(&event) {
+                return event;
+            }
+        }
+    })
+    .await
+    .expect("event not received")
+}
+
+async fn handshake_and_call(protocol: MockProtocol) {
+    let server = MockServer::start(protocol).await.unwrap();
+    server.respond(Opcode::ContactInfo, json!({
+        "contacts": [{ "id": 42, "names": [{ "name": "Test", "type": "ONEME" }] }],
+    }));
+
+    let client = server.client_builder().build();
+    client.connect(testing::identity()).await.unwrap();
+
+    let handshake = server.wait_for_request(Opcode::SessionInit).await.unwrap();
+    assert_eq!(handshake.payload["deviceId"], "mock-device");
+
+    let contacts = client.fetch_contacts(vec![42]).await.unwrap();
+    assert_eq!(contacts.len(), 1);
+    assert_eq!(contacts[0].id, 42);
+
+    let request = server.wait_for_request(Opcode::ContactInfo).await.unwrap();
+    assert_eq!(request.payload["contactIds"], json!([42]));
+}
+
+#[tokio::test]
+async fn web_handshake_and_call() {
+    handshake_and_call(MockProtocol::Web).await;
+}
+
+#[tokio::test]
+async fn mobile_handshake_and_call() {
+    handshake_and_call(MockProtocol::Mobile).await;
+}
+
+async fn push_is_decoded(protocol: MockProtocol) {
+    let server = MockServer::start(protocol).await.unwrap();
+    let client = server.client_builder().build();
+    let mut events = client.subscribe();
+    client.connect(testing::identity()).await.unwrap();
+
+    assert_eq!(server.push(Opcode::NotifTyping, json!({ "chatId": 1, "userId": 2 })), 1);
+
+    let event = next_event(&mut events, |e| matches!(e, Event::Typing { .. })).await;
+    match event {
+        Event::Typing { chat_id, user_id } => assert_eq!((chat_id, user_id), (1, 2)),
+        _ => unreachable!(),
+    }
+}
+
+#[tokio::test]
+async fn web_push_is_decoded() {
+    push_is_decoded(MockProtocol::Web).await;
+}
+
+#[tokio::test]
+async fn mobile_push_is_decoded() {
+    push_is_decoded(MockProtocol::Mobile).await;
+}
+
+#[tokio::test]
+async fn error_and_unhandled_opcodes_fail_the_call() {
+    let server = MockServer::start(MockProtocol::Mobile).await.unwrap();
+    server.respond_error(Opcode::ChatInfo, "chat.not.found", "Chat not found");
+
+    let client = server.client_builder().build();
+    client.connect(testing::identity()).await.unwrap();
+
+    assert!(client.get_chats(vec![1]).await.is_err());
+    assert!(client.fetch_contacts(vec![1]).await.is_err());
+}
+
+#[tokio::test]
+async fn ignored_opcode_times_out() {
+    let server = MockServer::start(MockProtocol::Web).await.unwrap();
+    server.ignore(Opcode::ChatInfo);
+
+    let client = server
+        .client_builder()
+        .request_timeout(Duration::from_millis(200))
+        .build();
+    client.connect(testing::identity()).await.unwrap();
+
+    assert!(client.get_chats(vec![1]).await.is_err());
+}
+
+#[tokio::test]
+async fn client_reconnects_after_server_drop() {
+    let server = MockServer::start(MockProtocol::Mobile).await.unwrap();
+    let client = server.client_builder().build();
+    let mut events = client.subscribe();
+    client.connect(testing::identity()).await.unwrap();
+
+    server.disconnect_all();
+
+    next_event(&mut events, |e| {
+        matches!(e, Event::ConnectionState(ConnectionState::Reconnecting { .. }))
+    })
+    .await;
+    next_event(&mut events, |e| {
+        matches!(e, Event::ConnectionState(ConnectionState::Connected))
+    })
+    .await;
+
+    let handshakes = server
+        .requests()
+        .iter()
+        .filter(|r| r.opcode == u16::from(Opcode::SessionInit))
+        .count();
+    assert_eq!(handshakes, 2);
+
+    client.disconnect().await;
+}
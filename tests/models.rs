@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+
+use rumax::models::{
+    self, AttachmentType, Call, Chat, Contact, LinkInfo, MembersPage, Message, Opcode, Response,
+    SearchResult, SyncResult,
+};
+use rumax::testing::{self, MockProtocol, MockServer};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+#[derive(Debug, Deserialize)]
+struct Ids {
+    #[serde(deserialize_with = "models::de_id")]
+    id: i64,
+    #[serde(default, deserialize_with = "models::de_opt_id")]
+    opt: Option<i64>,
+    #[serde(default, deserialize_with = "models::de_ids")]
+    list: Vec<i64>,
+    #[serde(default, deserialize_with = "models::de_id_map")]
+    map: HashMap<String, i64>,
+}
+
+fn ids(value: Value) -> Result<Ids, serde_json::Error> {
+    serde_json::from_value(value)
+}
+
+#[test]
+fn value_as_id_accepts_numbers_and_strings() {
+    assert_eq!(models::value_as_id(&json!(42)), Some(42));
+    assert_eq!(models::value_as_id(&json!(-100)), Some(-100));
+    assert_eq!(models::value_as_id(&json!("9007199254740993")), Some(9007199254740993));
+    assert_eq!(models::value_as_id(&json!("abc")), None);
+    assert_eq!(models::value_as_id(&json!(1.5)), None);
+    assert_eq!(models::value_as_id(&Value::Null), None);
+}
+
+#[test]
+fn id_deserializers() {
+    let parsed = ids(json!({
+        "id": "9007199254740993",
+        "opt": 7,
+        "list": [1, "2"],
+        "map": { "1": "1700000000000", "2": 5 },
+    }))
+    .unwrap();
+    assert_eq!(parsed.id, 9007199254740993);
+    assert_eq!(parsed.opt, Some(7));
+    assert_eq!(parsed.list, vec![1, 2]);
+    assert_eq!(parsed.map["1"], 1700000000000);
+    assert_eq!(parsed.map["2"], 5);
+
+    let parsed = ids(json!({ "id": 1 })).unwrap();
+    assert_eq!(parsed.opt, None);
+    assert!(parsed.list.is_empty() && parsed.map.is_empty());
+
+    assert_eq!(ids(json!({ "id": 1, "opt": null })).unwrap().opt, None);
+    assert_eq!(ids(json!({ "id": 1, "opt": "garbage" })).unwrap().opt, None);
+
+    assert!(ids(json!({ "id": "abc" })).is_err());
+    assert!(ids(json!({})).is_err());
+    assert!(ids(json!({ "id": 1, "list": [1, "x"] })).is_err());
+    assert!(ids(json!({ "id": 1, "map": { "1": true } })).is_err());
+}
+
+#[test]
+fn chat_accepts_string_ids() {
+    let chat: Chat = serde_json::from_value(json!({
+        "id": "-68000000000000001",
+        "type": "CHAT",
+        "title": "Team",
+        "owner": "10",
+        "admins": ["10", 11],
+        "participants": { "10": "1700000000000", "11": 1700000000001i64 },
+        "lastMessage": { "id": "5", "cid": "1700000000123", "text": "hi" },
+        "options": { "allCanPinMessage": true },
+        "unknownField": 1,
+    }))
+    .unwrap();
+
+    assert_eq!(chat.id, -68000000000000001);
+    assert_eq!(chat.kind.as_deref(), Some("CHAT"));
+    assert_eq!(chat.owner, Some(10));
+    assert_eq!(chat.admins, vec![10, 11]);
+    assert_eq!(chat.participants["10"], 1700000000000);
+    assert_eq!(chat.participants["11"], 1700000000001);
+    assert_eq!(chat.last_message.unwrap().cid, Some(1700000000123));
+    assert_eq!(chat.options["allCanPinMessage"], true);
+    assert_eq!(chat.extra["unknownField"], 1);
+}
+
+#[test]
+fn message_accepts_string_ids() {
+    let message: Message = serde_json::from_value(json!({
+        "id": "115000000000000001",
+        "time": 1700000000000i64,
+        "sender": "20",
+        "cid": "1700000000123",
+        "text": "hello",
+        "status": "EDITED",
+        "elements": [{ "type": "STRONG", "from": 0, "length": 5 }],
+        "attaches": [
+            { "_type": "PHOTO", "photoId": "30", "baseUrl": "https://i/1", "width": 10, "height": 20 },
+            { "_type": "FILE", "fileId": 31, "name": "a.pdf", "size": 100 },
+            { "_type": "SOMETHING_NEW" },
+        ],
+        "link": { "type": "FORWARD", "chatId": "-1", "messageId": "2", "message": { "id": 2 } },
+        "reactionInfo": { "totalCount": 1, "counters": [{ "reaction": "👍", "count": 1 }] },
+    }))
+    .unwrap();
+
+    assert_eq!(message.id, 115000000000000001);
+    assert_eq!(message.sender, Some(20));
+    assert_eq!(message.cid, Some(1700000000123));
+    assert_eq!(message.elements[0].kind, "STRONG");
+    assert_eq!(message.attaches[0].kind, AttachmentType::Photo);
+    assert_eq!(message.attaches[0].photo_id, Some(30));
+    assert_eq!(message.attaches[1].file_id, Some(31));
+    assert_eq!(message.attaches[2].kind, AttachmentType::Unknown);
+    let link = message.link.unwrap();
+    assert_eq!((link.chat_id, link.message_id), (Some(-1), Some(2)));
+    assert_eq!(message.reaction_info.unwrap().total_count, 1);
+}
+
+#[test]
+fn contacts_members_and_sync() {
+    let contact: Contact = serde_json::from_value(json!({
+        "id": "42",
+        "names": [{ "name": "Test", "type": "ONEME" }],
+        "photoId": "77",
+    }))
+    .unwrap();
+    assert_eq!((contact.id, contact.photo_id), (42, Some(77)));
+    assert_eq!(contact.display_name(), Some("Test"));
+
+    let page: MembersPage = serde_json::from_value(json!({
+        "members": [{ "contact": { "id": 1 }, "presence": { "seen": 5 }, "readMark": 9 }],
+        "marker": 100,
+    }))
+    .unwrap();
+    assert_eq!(page.members[0].contact.id, 1);
+    assert_eq!(page.members[0].presence.as_ref().unwrap().seen, Some(5));
+    assert_eq!(page.marker, Some(100));
+
+    let sync: SyncResult = serde_json::from_value(json!({
+        "profile": { "contact": { "id": "7" } },
+        "chats": [{ "id": 1 }],
+        "contacts": [{ "id": 2 }],
+        "presence": { "2": { "status": "ON" } },
+        "messages": { "1": [{ "id": 3 }] },
+        "time": 1700000000000i64,
+        "token": "new-token",
+    }))
+    .unwrap();
+    assert_eq!(sync.user_id(), Some(7));
+    assert_eq!(sync.chats[0].id, 1);
+    assert_eq!(sync.contacts[0].id, 2);
+    assert_eq!(sync.presence["2"].status.as_deref(), Some("ON"));
+    assert_eq!(sync.messages["1"][0].id, 3);
+    assert_eq!(sync.token.as_deref(), Some("new-token"));
+}
+
+#[test]
+fn search_link_info_and_call() {
+    let search: SearchResult = serde_json::from_value(json!({
+        "result": [{ "chatId": "5", "chat": { "id": 5 } }, { "contact": { "id": 6 } }],
+        "total": 2,
+        "marker": "next",
+    }))
+    .unwrap();
+    assert_eq!(search.result[0].chat_id, Some(5));
+    assert_eq!(search.result[1].contact.as_ref().unwrap().id, 6);
+    assert_eq!(search.marker, Some(json!("next")));
+
+    let info: LinkInfo = serde_json::from_value(json!({ "chat": { "id": "8", "link": "https://max.ru/join/x" } })).unwrap();
+    assert_eq!(info.chat.unwrap().id, 8);
+    assert!(info.contact.is_none());
+
+    let call: Call = serde_json::from_value(json!({ "conversationId": "c", "chatId": "3", "callerId": 4 })).unwrap();
+    assert_eq!((call.chat_id, call.caller_id), (Some(3), Some(4)));
+}
+
+#[test]
+fn response_take_and_take_opt() {
+    let response = |payload: Value| Response { ver: 11, cmd: 1, seq: 1, opcode: 0, payload };
+
+    let chat: Chat = response(json!({ "chat": { "id": 1 } })).take("chat").unwrap();
+    assert_eq!(chat.id, 1);
+    assert!(response(json!({})).take::<Chat>("chat").is_err());
+
+    assert_eq!(response(json!({ "chat": { "id": 2 } })).take_opt::<Chat>("chat").unwrap().unwrap().id, 2);
+    assert!(response(json!({})).take_opt::<Chat>("chat").unwrap().is_none());
+    assert!(response(json!({ "chat": null })).take_opt::<Chat>("chat").unwrap().is_none());
+    assert!(response(json!({ "chat": { "id": "x" } })).take_opt::<Chat>("chat").is_err());
+}
+
+#[tokio::test]
+async fn chat_mutations_tolerate_missing_chat() {
+    let server = MockServer::start(MockProtocol::Web).await.unwrap();
+    server.respond(Opcode::ChatUpdate, json!({}));
+    server.respond(Opcode::ChatMembersUpdate, json!({ "chat": { "id": 5, "admins": ["1"] } }));
+
+    let client = server.client_builder().build();
+    client.connect(testing::identity()).await.unwrap();
+
+    let updated = client.change_group_profile(5, Some("Title".into()), None).await.unwrap();
+    assert!(updated.is_none());
+
+    let chat = client.invite_users_to_group(5, vec![2], None).await.unwrap().unwrap();
+    assert_eq!((chat.id, chat.admins), (5, vec![1]));
+}
@@ -0,0 +1,181 @@
+use std::time::Duration;
+
+use rumax::constants::Constants;
+use rumax::events::Event;
+use rumax::models::Opcode;
+use rumax::outbox::OutboxStatus;
+use rumax::testing::{self, MockProtocol, MockServer};
+use rumax::MaxClient;
+use serde_json::json;
+use tokio::sync::broadcast;
+use tokio::time::timeout;
+
+mod common;
+
+use common::echo_message;
+
+async fn start_server() -> MockServer {
+    let server = MockServer::start(MockProtocol::Mobile).await.unwrap();
+    server.respond(Opcode::Login, json!({ "profile": { "contact": { "id": 1 } } }));
+    server.on(Opcode::MsgSend, echo_message);
+    server
+}
+
+async fn login(client: &MaxClient) {
+    client.connect(testing::identity()).await.unwrap();
+    client.set_token("token".to_string()).await;
+    client.sync().await.unwrap();
+}
+
+async fn next_status(events: &mut broadcast::Receiver<Event>) -> (i64, OutboxStatus) {
+    timeout(Duration::from_secs(5), async {
+        loop {
+            if let Event::Outbox { cid, status, .. } = events.recv().await.unwrap() {
+                return (cid, status);
+            }
+        }
+    })
+    .await
+    .expect("outbox event not received")
+}
+
+async fn wait_delivered(events: &mut broadcast::Receiver<Event>, expected: i64) {
+    loop {
+        if next_status(events).await == (expected, OutboxStatus::Delivered) {
+            return;
+        }
+    }
+}
+
+#[tokio::test]
+async fn messages_queued_offline_are_sent_after_login() {
+    let server = start_server().await;
+    let client = server.client_builder().build();
+    let mut events = client.subscribe();
+
+    let first = client.queue_message(1, "one".to_string(), None);
+    let second = client.queue_message(1, "two".to_string(), None);
+    assert_ne!(first, second);
+    assert_eq!(client.outbox_status(first), Some(OutboxStatus::Pending));
+
+    login(&client).await;
+
+    wait_delivered(&mut events, first).await;
+    wait_delivered(&mut events, second).await;
+    assert!(client.outbox().is_empty());
+
+    let texts: Vec<_> = server
+        .requests()
+        .into_iter()
+        .filter(|r| r.opcode == u16::from(Opcode::MsgSend))
+        .map(|r| r.payload["message"]["text"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(texts, ["one", "two"]);
+}
+
+#[tokio::test]
+async fn message_is_resent_with_same_cid_after_reconnect() {
+    let server = start_server().await;
+    server.ignore(Opcode::MsgSend);
+
+    let client = server.client_builder().build();
+    login(&client).await;
+    let mut events = client.subscribe();
+
+    let cid = client.queue_message(1, "hello".to_string(), None);
+    server.wait_for_request(Opcode::MsgSend).await.unwrap();
+    assert_eq!(client.outbox_status(cid), Some(OutboxStatus::Sent));
+
+    server.on(Opcode::MsgSend, echo_message);
+    server.disconnect_all();
+
+    wait_delivered(&mut events, cid).await;
+
+    let cids: Vec<_> = server
+        .requests()
+        .into_iter()
+        .filter(|r| r.opcode == u16::from(Opcode::MsgSend))
+        .map(|r| r.payload["message"]["cid"].as_i64().unwrap())
+        .collect();
+    assert_eq!(cids, [cid, cid]);
+    client.disconnect().await;
+}
+
+#[tokio::test]
+async fn rejected_message_can_be_retried() {
+    let server = start_server().await;
+    server.respond_error(Opcode::MsgSend, "chat.denied", "Access denied");
+
+    let client = server.client_builder().build();
+    login(&client).await;
+    let mut events = client.subscribe();
+
+    let cid = client.queue_message(1, "hello".to_string(), None);
+    loop {
+        if next_status(&mut events).await == (cid, OutboxStatus::Failed) {
+            break;
+        }
+    }
+    let failed = client.outbox();
+    assert_eq!(failed.len(), 1);
+    assert!(failed[0].error.as_deref().unwrap().contains("chat.denied"));
+
+    server.on(Opcode::MsgSend, echo_message);
+    assert!(client.retry_message(cid));
+    wait_delivered(&mut events, cid).await;
+    assert_eq!(client.outbox_status(cid), None);
+}
+
+#[tokio::test]
+async fn push_with_colliding_cid_from_other_chat_is_ignored() {
+    let server = start_server().await;
+    server.ignore(Opcode::MsgSend);
+
+    let client = server.client_builder().build();
+    login(&client).await;
+    let mut events = client.subscribe();
+
+    let cid = client.queue_message(1, "hello".to_string(), None);
+    server.wait_for_request(Opcode::MsgSend).await.unwrap();
+
+    /* Тот же cid, но другой чат или чужой отправитель */
+    server.push(Opcode::NotifMessage, json!({ "chatId": 2, "message": { "id": 10, "cid": cid, "sender": 1 } }));
+    server.push(Opcode::NotifMessage, json!({ "chatId": 1, "message": { "id": 11, "cid": cid, "sender": 5 } }));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(client.outbox_status(cid), Some(OutboxStatus::Sent));
+
+    server.push(Opcode::NotifMessage, json!({ "chatId": 1, "message": { "id": 12, "cid": cid, "sender": 1 } }));
+    wait_delivered(&mut events, cid).await;
+    assert_eq!(client.outbox_status(cid), None);
+    client.disconnect().await;
+}
+
+#[tokio::test]
+async fn unanswered_message_backs_off_and_fails() {
+    let server = start_server().await;
+    server.ignore(Opcode::MsgSend);
+
+    let client = server.client_builder().request_timeout(Duration::from_millis(100)).build();
+    login(&client).await;
+    let mut events = client.subscribe();
+
+    let cid = client.queue_message(1, "hello".to_string(), None);
+    timeout(Duration::from_secs(10), async {
+        while next_status(&mut events).await != (cid, OutboxStatus::Failed) {}
+    })
+    .await
+    .unwrap();
+
+    let sends: Vec<_> = server.requests().into_iter().filter(|r| r.opcode == u16::from(Opcode::MsgSend)).collect();
+    assert_eq!(sends.len(), Constants::OUTBOX_MAX_TIMEOUTS as usize);
+    let entry = &client.outbox()[0];
+    assert!(entry.error.as_deref().unwrap().contains("Таймаут"), "{:?}", entry.error);
+    client.disconnect().await;
+}
+
+#[test]
+fn message_can_be_queued_outside_runtime() {
+    let client = MaxClient::new();
+    let cid = client.queue_message(1, "offline".to_string(), None);
+    assert_eq!(client.outbox_status(cid), Some(OutboxStatus::Pending));
+}
@@ -0,0 +1,129 @@
+use std::time::Duration;
+
+use rumax::errors::Error;
+use rumax::events::Event;
+use rumax::models::Opcode;
+use rumax::testing::{self, MockProtocol, MockServer};
+use rumax::MaxClient;
+use serde_json::json;
+use tokio::time::timeout;
+
+/* Первый ping уходит сразу после подключения, поэтому ждем, пока pending опустеет */
+async fn assert_no_pending(client: &MaxClient) {
+    let drained = timeout(Duration::from_secs(5), async {
+        while client.pending_count().await > 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await;
+    assert!(drained.is_ok(), "pending requests left");
+}
+
+#[tokio::test]
+async fn read_marks_are_pipelined() {
+    let server = MockServer::start(MockProtocol::Mobile).await.unwrap();
+    server.respond(Opcode::ChatMark, json!({}));
+
+    let client = server.client_builder().build();
+    client.connect(testing::identity()).await.unwrap();
+
+    let mut handles = Vec::new();
+    for message_id in 1..=100 {
+        handles.push(client.read_message_no_wait(1, message_id).await.unwrap());
+    }
+    assert!(handles.windows(2).all(|w| w[0].seq() < w[1].seq()));
+
+    for handle in handles {
+        handle.await.unwrap();
+    }
+
+    let marks = server.requests().iter().filter(|r| r.opcode == u16::from(Opcode::ChatMark)).count();
+    assert_eq!(marks, 100);
+    assert_no_pending(&client).await;
+}
+
+#[tokio::test]
+async fn dropped_handle_reports_response_as_event() {
+    let server = MockServer::start(MockProtocol::Web).await.unwrap();
+    server.respond(Opcode::Log, json!({ "ok": true }));
+
+    let client = server.client_builder().traffic_events(true).build();
+    client.connect(testing::identity()).await.unwrap();
+    let mut events = client.subscribe();
+
+    let seq = client.send_no_wait(Opcode::Log, json!({ "events": [] }), 0).await.unwrap().seq();
+
+    let response = timeout(Duration::from_secs(5), async {
+        loop {
+            match events.recv().await.unwrap() {
+                Event::Response(resp) if resp.opcode == u16::from(Opcode::Log) => return resp,
+                _ => {}
+            }
+        }
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(response.seq, seq);
+    assert_no_pending(&client).await;
+}
+
+#[tokio::test]
+async fn dropped_handle_response_is_discarded_without_traffic_events() {
+    let server = MockServer::start(MockProtocol::Web).await.unwrap();
+    server.respond(Opcode::Log, json!({ "ok": true }));
+
+    let client = server.client_builder().build();
+    client.connect(testing::identity()).await.unwrap();
+    let mut events = client.subscribe();
+
+    for _ in 0..50 {
+        drop(client.send_no_wait(Opcode::Log, json!({ "events": [] }), 0).await.unwrap());
+    }
+    timeout(Duration::from_secs(5), async {
+        while server.requests().iter().filter(|r| r.opcode == u16::from(Opcode::Log)).count() < 50 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    while let Ok(event) = events.try_recv() {
+        assert!(!matches!(event, Event::Response(_)), "{:?}", event);
+    }
+}
+
+#[tokio::test]
+async fn awaited_handle_parses_errors() {
+    let server = MockServer::start(MockProtocol::Mobile).await.unwrap();
+    server.respond_error(Opcode::ChatMark, "chat.not.found", "Chat not found");
+
+    let client = server.client_builder().build();
+    client.connect(testing::identity()).await.unwrap();
+
+    let handle = client.read_message_no_wait(1, 1).await.unwrap();
+    assert_eq!(handle.opcode(), u16::from(Opcode::ChatMark));
+    assert!(matches!(handle.await, Err(Error::Api(e)) if e.code == "chat.not.found"));
+}
+
+#[tokio::test]
+async fn stale_handle_keeps_request_with_reused_seq() {
+    let server = MockServer::start(MockProtocol::Mobile).await.unwrap();
+    server.ignore(Opcode::Log);
+    server.respond(Opcode::ChatInfo, json!({ "chats": [] }));
+
+    let client = server.client_builder().ping_interval(Duration::from_secs(3600)).build();
+    client.connect(testing::identity()).await.unwrap();
+
+    let stale = client.send_no_wait(Opcode::Log, json!({}), 0).await.unwrap();
+    /* Прокручиваем 16-битный seq до номера перед stale */
+    let before_stale = if stale.seq() == 1 { u16::MAX as u64 } else { stale.seq() - 1 };
+    while client.send_no_wait(Opcode::Log, json!({}), 0).await.unwrap().seq() != before_stale {}
+    let fresh = client.send_no_wait(Opcode::ChatInfo, json!({ "chatIds": [1] }), 0).await.unwrap();
+    assert_eq!(fresh.seq(), stale.seq());
+
+    drop(stale);
+    assert_eq!(client.pending_opcodes().await, vec![u16::from(Opcode::ChatInfo)]);
+    assert!(fresh.await.unwrap().payload["chats"].is_array());
+}
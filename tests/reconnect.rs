@@ -0,0 +1,221 @@
+use std::net::{Shutdown, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use rumax::builder::ClientConfig;
+use rumax::errors::{ClientResult, ErrorCodes};
+use rumax::events::{ConnectionState, Event};
+use rumax::models::{Identity, Opcode};
+use rumax::outbox::OutboxStatus;
+use rumax::testing::{self, MockProtocol, MockServer};
+use rumax::transport::mobile::{MobileConnector, MobileStream, MobileTransport};
+use rumax::transport::{BoxedTransport, TransportConnector, TransportFactory};
+use rumax::ClientMode;
+use serde_json::{json, Value};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio::time::timeout;
+
+/* Состояния соединения до первого, для которого stop вернет true (включительно) */
+async fn states_until<F>(events: &mut broadcast::Receiver<Event>, stop: F) -> Vec<ConnectionState>
+where
+    F: Fn(&ConnectionState) -> bool,
+{
+    timeout(Duration::from_secs(10), async {
+        let mut states = Vec::new();
+        loop {
+            if let Event::ConnectionState(state) = events.recv().await.unwrap() {
+                let done = stop(&state);
+                states.push(state);
+                if done {
+                    return states;
+                }
+            }
+        }
+    })
+    .await
+    .expect("connection state not received")
+}
+
+fn login_count(server: &MockServer) -> usize {
+    server
+        .requests()
+        .iter()
+        .filter(|r| r.opcode == u16::from(Opcode::Login))
+        .count()
+}
+
+#[tokio::test]
+async fn reconnect_resyncs_with_saved_token() {
+    let server = MockServer::start(MockProtocol::Web).await.unwrap();
+    server.respond(Opcode::Login, json!({ "profile": { "contact": { "id": 7 } } }));
+
+    let client = server.client_builder().build();
+    client.set_token("saved-token".into()).await;
+    client.connect(testing::identity()).await.unwrap();
+    client.sync().await.unwrap();
+    let mut events = client.subscribe();
+
+    server.disconnect_all();
+    let states = states_until(&mut events, |s| matches!(s, ConnectionState::Connected)).await;
+
+    assert!(matches!(states[0], ConnectionState::Reconnecting { attempt: 1, .. }), "{:?}", states);
+    assert_eq!(login_count(&server), 2);
+    let login = server.requests().into_iter().rfind(|r| r.opcode == u16::from(Opcode::Login)).unwrap();
+    assert_eq!(login.payload["token"], "saved-token");
+    assert!(client.is_logged_in().await);
+
+    client.disconnect().await;
+}
+
+#[tokio::test]
+async fn rejected_token_stops_reconnect() {
+    let server = MockServer::start(MockProtocol::Web).await.unwrap();
+    let codes = ErrorCodes { auth_expired: vec!["login.token".into()], ..ErrorCodes::default() };
+    let client = server.client_builder().error_codes(codes).build();
+    client.set_token("expired-token".into()).await;
+    client.connect(testing::identity()).await.unwrap();
+    let mut events = client.subscribe();
+
+    server.respond_error(Opcode::Login, "login.token", "Token expired");
+    server.disconnect_all();
+    let states = states_until(&mut events, |s| {
+        matches!(s, ConnectionState::Connected | ConnectionState::Failed { .. })
+    })
+    .await;
+
+    match states.last() {
+        Some(ConnectionState::Failed { reason }) => assert!(reason.contains("login.token"), "{}", reason),
+        other => panic!("expected Failed, got {:?}", other),
+    }
+    assert_eq!(login_count(&server), 1);
+    assert!(!client.is_connected().await);
+}
+
+#[tokio::test]
+async fn reconnect_gives_up_after_max_attempts() {
+    let server = MockServer::start(MockProtocol::Web).await.unwrap();
+    let client = server.client_builder().reconnect_max_attempts(1).build();
+    client.connect(testing::identity()).await.unwrap();
+    let mut events = client.subscribe();
+
+    server.disconnect_all();
+    drop(server);
+    let states = states_until(&mut events, |s| {
+        matches!(s, ConnectionState::Connected | ConnectionState::Failed { .. })
+    })
+    .await;
+
+    assert_eq!(states.len(), 2, "{:?}", states);
+    assert!(matches!(states[0], ConnectionState::Reconnecting { attempt: 1, .. }));
+    assert!(matches!(states[1], ConnectionState::Failed { .. }));
+}
+
+#[tokio::test]
+async fn ping_error_response_keeps_connection() {
+    let server = MockServer::start(MockProtocol::Web).await.unwrap();
+    server.respond_error(Opcode::Ping, "error.internal", "Internal error");
+    let client = server.client_builder().ping_interval(Duration::from_millis(50)).build();
+    client.connect(testing::identity()).await.unwrap();
+    let mut events = client.subscribe();
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let pings = server.requests().iter().filter(|r| r.opcode == u16::from(Opcode::Ping)).count();
+    assert!(pings >= 3, "{}", pings);
+    assert!(client.is_connected().await);
+    while let Ok(event) = events.try_recv() {
+        assert!(!matches!(event, Event::ConnectionState(_)), "{:?}", event);
+    }
+    client.disconnect().await;
+}
+
+/*
+ * Прокси к mock-серверу, который не закрывает соединение, когда клиент закрыл запись:
+ * чтение у клиента остается открытым, и обрыв виден только по ошибке записи
+ */
+async fn half_open_relay(upstream: SocketAddr) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (client, _) = listener.accept().await.unwrap();
+        let server = TcpStream::connect(upstream).await.unwrap();
+        let (mut client_read, mut client_write) = client.into_split();
+        let (mut server_read, mut server_write) = server.into_split();
+        tokio::spawn(async move {
+            let _ = tokio::io::copy(&mut server_read, &mut client_write).await;
+            std::future::pending::<()>().await;
+        });
+        let _ = tokio::io::copy(&mut client_read, &mut server_write).await;
+        std::future::pending::<()>().await;
+    });
+    addr
+}
+
+/* Первое подключение идет через relay, а дубликат сокета позволяет закрыть запись клиента */
+struct HalfOpenConnector {
+    relay: SocketAddr,
+    socket: Mutex<Option<std::net::TcpStream>>,
+}
+
+#[async_trait]
+impl TransportConnector for HalfOpenConnector {
+    async fn connect(&self, config: &ClientConfig) -> ClientResult<BoxedTransport> {
+        if self.socket.lock().unwrap().is_some() {
+            return MobileConnector.connect(config).await;
+        }
+        let tcp = TcpStream::connect(self.relay).await?;
+        let std_tcp = tcp.into_std()?;
+        *self.socket.lock().unwrap() = Some(std_tcp.try_clone()?);
+        let tcp = TcpStream::from_std(std_tcp)?;
+        Ok(MobileTransport::from_stream(MobileStream::Plain(tcp)).boxed())
+    }
+
+    fn handshake_payload(&self, identity: &Identity) -> Value {
+        MobileConnector.handshake_payload(identity)
+    }
+}
+
+#[tokio::test]
+async fn mobile_write_failure_reconnects_and_keeps_outbox_pending() {
+    let server = MockServer::start(MockProtocol::Mobile).await.unwrap();
+    server.respond(Opcode::Login, json!({ "profile": { "contact": { "id": 1 } } }));
+    server.ignore(Opcode::MsgSend);
+
+    let connector = Arc::new(HalfOpenConnector { relay: half_open_relay(server.addr()).await, socket: Mutex::new(None) });
+    let client = server
+        .client_builder()
+        .mode(ClientMode::Custom(connector.clone()))
+        .ping_interval(Duration::from_millis(500))
+        .build();
+    client.set_token("token".into()).await;
+    client.connect(testing::identity()).await.unwrap();
+    client.sync().await.unwrap();
+    let mut events = client.subscribe();
+
+    connector.socket.lock().unwrap().as_ref().unwrap().shutdown(Shutdown::Write).unwrap();
+    let cid = client.queue_message(1, "hello".to_string(), None);
+
+    /* Отправка упала на записи: сообщение ждет нового соединения, а не Failed */
+    let status = timeout(Duration::from_secs(5), async {
+        loop {
+            match events.recv().await {
+                Ok(Event::Outbox { status, .. }) if status != OutboxStatus::Sent && status != OutboxStatus::Pending => {
+                    return status;
+                }
+                Ok(Event::ConnectionState(ConnectionState::Reconnecting { .. })) => return OutboxStatus::Pending,
+                _ => {}
+            }
+        }
+    })
+    .await
+    .expect("write failure not detected");
+    assert_eq!(status, OutboxStatus::Pending);
+    assert_eq!(client.outbox_status(cid), Some(OutboxStatus::Pending));
+
+    states_until(&mut events, |s| matches!(s, ConnectionState::Connected)).await;
+    assert!(client.is_logged_in().await);
+    assert_eq!(login_count(&server), 2);
+    client.disconnect().await;
+}
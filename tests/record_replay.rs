@@ -0,0 +1,148 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use rumax::events::Event;
+use rumax::models::Opcode;
+use rumax::testing::{self, MockProtocol, MockServer};
+use rumax::transport::record::{RecordedPacket, TrafficRecorder};
+use rumax::transport::replay::ReplayTransport;
+use rumax::transport::TransportFactory;
+use rumax::MaxClient;
+use serde_json::json;
+use tokio::time::timeout;
+
+fn capture_path(name: &str) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("rumax-{}-{}.jsonl", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+#[tokio::test]
+async fn recorded_session_replays_against_fresh_client() {
+    let path = capture_path("replay");
+
+    /* Записываем сессию с mock-сервером */
+    {
+        let server = MockServer::start(MockProtocol::Mobile).await.unwrap();
+        server.respond(Opcode::ContactInfo, json!({
+            "contacts": [{ "id": 7, "names": [{ "name": "Recorded", "type": "ONEME" }] }],
+        }));
+
+        let recorder = Arc::new(TrafficRecorder::create(&path).unwrap());
+        let client = server.client_builder().recorder(recorder.clone()).build();
+        let mut events = client.subscribe();
+
+        client.connect(testing::identity()).await.unwrap();
+        client.fetch_contacts(vec![7]).await.unwrap();
+        server.push(Opcode::NotifTyping, json!({ "chatId": 5, "userId": 7 }));
+
+        timeout(Duration::from_secs(5), async {
+            while !matches!(events.recv().await.unwrap(), Event::Typing { .. }) {}
+        })
+        .await
+        .unwrap();
+
+        client.disconnect().await;
+        recorder.flush().await;
+    }
+
+    let entries = TrafficRecorder::load(&path).unwrap();
+    assert!(matches!(&entries[0].packet, RecordedPacket::Request(r) if r.opcode == u16::from(Opcode::SessionInit)));
+    assert!(entries.iter().all(|e| e.time > 0));
+
+    /* Проигрываем запись новому клиенту без сети */
+    let client = MaxClient::builder().ping_interval(Duration::from_secs(3600)).build();
+    let mut events = client.subscribe();
+    let (writer, reader) = ReplayTransport::load(&path).unwrap().keep_open().boxed();
+
+    client.connect_with_transport(testing::identity(), writer, reader).await.unwrap();
+
+    let contacts = client.fetch_contacts(vec![7]).await.unwrap();
+    assert_eq!(contacts[0].id, 7);
+
+    timeout(Duration::from_secs(5), async {
+        loop {
+            if let Event::Typing { chat_id, user_id } = events.recv().await.unwrap() {
+                assert_eq!((chat_id, user_id), (5, 7));
+                break;
+            }
+        }
+    })
+    .await
+    .unwrap();
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn replay_rejects_unexpected_request() {
+    let path = capture_path("mismatch");
+
+    {
+        let server = MockServer::start(MockProtocol::Web).await.unwrap();
+        server.respond(Opcode::ChatInfo, json!({ "chats": [] }));
+
+        let recorder = Arc::new(TrafficRecorder::create(&path).unwrap());
+        let client = server.client_builder().recorder(recorder.clone()).build();
+        client.connect(testing::identity()).await.unwrap();
+        client.get_chats(vec![1]).await.unwrap();
+        client.disconnect().await;
+        recorder.flush().await;
+    }
+
+    let client = MaxClient::builder().request_timeout(Duration::from_millis(500)).build();
+    let (writer, reader) = ReplayTransport::load(&path).unwrap().boxed();
+    client.connect_with_transport(testing::identity(), writer, reader).await.unwrap();
+
+    /* В записи был CHAT_INFO, а не CONTACT_INFO */
+    assert!(client.fetch_contacts(vec![1]).await.is_err());
+
+    let _ = std::fs::remove_file(&path);
+}
+
+async fn record_login(path: &std::path::Path, recorder: TrafficRecorder) -> Vec<serde_json::Value> {
+    let server = MockServer::start(MockProtocol::Web).await.unwrap();
+    server.respond(Opcode::Login, json!({
+        "profile": { "contact": { "id": 1 } },
+        "token": "fresh-token",
+        "tokenAttrs": { "LOGIN": { "token": "login-token" } },
+    }));
+
+    let recorder = Arc::new(recorder);
+    let client = server.client_builder().recorder(recorder.clone()).build();
+    client.set_token("secret-token".into()).await;
+    client.connect(testing::identity()).await.unwrap();
+    client.sync().await.unwrap();
+    client.disconnect().await;
+    recorder.flush().await;
+
+    TrafficRecorder::load(path)
+        .unwrap()
+        .into_iter()
+        .filter_map(|entry| match entry.packet {
+            RecordedPacket::Request(r) if r.opcode == u16::from(Opcode::Login) => Some(r.payload),
+            RecordedPacket::Response(r) if r.opcode == u16::from(Opcode::Login) => Some(r.payload),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn recorder_redacts_tokens_by_default() {
+    let path = capture_path("redact");
+    let login = record_login(&path, TrafficRecorder::create(&path).unwrap()).await;
+
+    assert_eq!(login.len(), 2);
+    assert_eq!(login[0]["token"], "<redacted>");
+    assert_eq!(login[0]["chatsCount"], 40);
+    assert_eq!(login[1]["token"], "<redacted>");
+    assert_eq!(login[1]["tokenAttrs"]["LOGIN"]["token"], "<redacted>");
+    assert_eq!(login[1]["profile"]["contact"]["id"], 1);
+    let _ = std::fs::remove_file(&path);
+
+    let path = capture_path("keep-tokens");
+    let login = record_login(&path, TrafficRecorder::create(&path).unwrap().keep_tokens()).await;
+    assert_eq!(login[0]["token"], "secret-token");
+    assert_eq!(login[1]["token"], "fresh-token");
+    let _ = std::fs::remove_file(&path);
+}
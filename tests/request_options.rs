@@ -0,0 +1,112 @@
+use std::time::Duration;
+
+use rumax::errors::Error;
+use rumax::models::Opcode;
+use rumax::options::{Priority, RequestOptions};
+use rumax::testing::{self, MockProtocol, MockServer};
+use rumax::MaxClient;
+use serde_json::json;
+use tokio_util::sync::CancellationToken;
+
+/* Ожидающие ответа запросы, кроме ping: он уходит сразу после подключения */
+async fn pending_requests(client: &MaxClient) -> usize {
+    client
+        .pending_opcodes()
+        .await
+        .into_iter()
+        .filter(|opcode| *opcode != u16::from(Opcode::Ping))
+        .count()
+}
+
+#[tokio::test]
+async fn timeout_override_applies_to_api_methods() {
+    let server = MockServer::start(MockProtocol::Web).await.unwrap();
+    server.ignore(Opcode::ChatInfo);
+
+    let client = server.client_builder().build();
+    client.connect(testing::identity()).await.unwrap();
+
+    let timeout = Duration::from_millis(100);
+    let result = client
+        .with_options(RequestOptions::new().timeout(timeout))
+        .get_chats(vec![1])
+        .await;
+
+    assert!(matches!(result, Err(Error::RequestTimeout(t)) if t == timeout));
+    assert_eq!(pending_requests(&client).await, 0);
+}
+
+#[tokio::test]
+async fn cancelled_request_leaves_pending() {
+    let server = MockServer::start(MockProtocol::Mobile).await.unwrap();
+    server.ignore(Opcode::ChatInfo);
+
+    let client = server.client_builder().build();
+    client.connect(testing::identity()).await.unwrap();
+
+    let cancel = CancellationToken::new();
+    let request = {
+        let client = client.with_options(RequestOptions::new().cancel(cancel.clone()));
+        tokio::spawn(async move { client.get_chats(vec![1]).await })
+    };
+
+    server.wait_for_request(Opcode::ChatInfo).await.unwrap();
+    assert_eq!(pending_requests(&client).await, 1);
+
+    cancel.cancel();
+    assert!(matches!(request.await.unwrap(), Err(Error::Cancelled)));
+    assert_eq!(pending_requests(&client).await, 0);
+
+    /* Уже отмененный токен не отправляет запрос вовсе */
+    let result = client
+        .with_options(RequestOptions::new().cancel(cancel))
+        .get_chats(vec![1])
+        .await;
+    assert!(matches!(result, Err(Error::Cancelled)));
+    assert_eq!(server.requests().iter().filter(|r| r.opcode == u16::from(Opcode::ChatInfo)).count(), 1);
+}
+
+#[tokio::test]
+async fn dropped_request_leaves_pending() {
+    let server = MockServer::start(MockProtocol::Web).await.unwrap();
+    server.ignore(Opcode::ChatInfo);
+
+    let client = server.client_builder().build();
+    client.connect(testing::identity()).await.unwrap();
+
+    let _ = tokio::time::timeout(Duration::from_millis(100), client.get_chats(vec![1])).await;
+    assert_eq!(pending_requests(&client).await, 0);
+}
+
+#[tokio::test]
+async fn high_priority_passes_send_limit_first() {
+    let server = MockServer::start(MockProtocol::Mobile).await.unwrap();
+    server.respond(Opcode::MsgSend, json!({}));
+
+    let client = server
+        .client_builder()
+        .send_rate_limit(1, Duration::from_millis(200))
+        .build();
+    client.connect(testing::identity()).await.unwrap();
+
+    client.send_and_wait(Opcode::MsgSend, json!({ "text": "first" }), 0).await.unwrap();
+
+    let send = |text: &'static str, priority: Priority| {
+        let client = client.with_options(RequestOptions::new().priority(priority));
+        tokio::spawn(async move { client.send_and_wait(Opcode::MsgSend, json!({ "text": text }), 0).await })
+    };
+    let low = send("low", Priority::Low);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let high = send("high", Priority::High);
+
+    high.await.unwrap().unwrap();
+    low.await.unwrap().unwrap();
+
+    let texts: Vec<_> = server
+        .requests()
+        .into_iter()
+        .filter(|r| r.opcode == u16::from(Opcode::MsgSend))
+        .map(|r| r.payload["text"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(texts, ["first", "high", "low"]);
+}
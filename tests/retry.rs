@@ -0,0 +1,211 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rumax::errors::{Error, ErrorCodes};
+use rumax::models::Opcode;
+use rumax::retry::RetryPolicy;
+use rumax::testing::{self, MockProtocol, MockServer};
+use serde_json::json;
+
+/*
+ * Коды ошибок для тестов. Реальные коды сервера не зафиксированы, поэтому тесты проверяют
+ * поведение по классу ошибки из ErrorCodes, а не конкретные строки протокола
+ */
+const RATE_LIMITED: &str = "test.rate_limited";
+const TRANSIENT: &str = "test.transient";
+
+fn codes() -> ErrorCodes {
+    ErrorCodes {
+        rate_limited: vec![RATE_LIMITED.into()],
+        transient: vec![TRANSIENT.into()],
+        ..ErrorCodes::default()
+    }
+}
+
+/* Первые failures запросов получают error, остальные - пустой ответ */
+fn fail_first(server: &MockServer, opcode: Opcode, failures: usize, error: serde_json::Value) -> Arc<AtomicUsize> {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    server.on(opcode, move |_| {
+        if counter.fetch_add(1, Ordering::SeqCst) < failures {
+            Some(error.clone())
+        } else {
+            Some(json!({ "chats": [] }))
+        }
+    });
+    calls
+}
+
+#[tokio::test]
+async fn flood_wait_is_retried_after_server_delay() {
+    let server = MockServer::start(MockProtocol::Mobile).await.unwrap();
+    let calls = fail_first(&server, Opcode::MsgSend, 1, json!({ "error": RATE_LIMITED, "retryAfter": 0.2 }));
+
+    let client = server.client_builder().retry_policy(RetryPolicy::enabled()).build();
+    client.connect(testing::identity()).await.unwrap();
+
+    let started = Instant::now();
+    client.send_and_wait(Opcode::MsgSend, json!({}), 0).await.unwrap();
+
+    assert!(started.elapsed() >= Duration::from_millis(200));
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn transient_errors_retry_only_idempotent_opcodes() {
+    let server = MockServer::start(MockProtocol::Web).await.unwrap();
+    let reads = fail_first(&server, Opcode::ChatInfo, 2, json!({ "error": TRANSIENT }));
+    let writes = fail_first(&server, Opcode::ChatCreate, 2, json!({ "error": TRANSIENT }));
+
+    let policy = RetryPolicy {
+        base_delay: Duration::from_millis(10),
+        ..RetryPolicy::enabled()
+    };
+    let client = server.client_builder().retry_policy(policy).error_codes(codes()).build();
+    client.connect(testing::identity()).await.unwrap();
+
+    client.get_chats(vec![1]).await.unwrap();
+    assert_eq!(reads.load(Ordering::SeqCst), 3);
+
+    let result = client.send_and_wait(Opcode::ChatCreate, json!({}), 0).await;
+    assert!(matches!(result, Err(Error::Transient(_))));
+    assert_eq!(writes.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn disabled_policy_returns_rate_limit_error() {
+    let server = MockServer::start(MockProtocol::Mobile).await.unwrap();
+    let calls = fail_first(&server, Opcode::MsgSend, 1, json!({ "error": RATE_LIMITED }));
+
+    let client = server.client_builder().retry_policy(RetryPolicy::disabled()).error_codes(codes()).build();
+    client.connect(testing::identity()).await.unwrap();
+
+    let result = client.send_and_wait(Opcode::MsgSend, json!({}), 0).await;
+    assert!(matches!(result, Err(Error::RateLimited(_))));
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn send_rate_limit_spaces_messages() {
+    let server = MockServer::start(MockProtocol::Mobile).await.unwrap();
+    server.respond(Opcode::MsgSend, json!({}));
+    server.respond(Opcode::ChatInfo, json!({ "chats": [] }));
+
+    let client = server
+        .client_builder()
+        .send_rate_limit(1, Duration::from_millis(150))
+        .build();
+    client.connect(testing::identity()).await.unwrap();
+
+    let started = Instant::now();
+    for _ in 0..3 {
+        client.send_and_wait(Opcode::MsgSend, json!({}), 0).await.unwrap();
+    }
+    assert!(started.elapsed() >= Duration::from_millis(300));
+
+    /* Остальные опкоды лимит не затрагивает */
+    let started = Instant::now();
+    for _ in 0..3 {
+        client.get_chats(vec![1]).await.unwrap();
+    }
+    assert!(started.elapsed() < Duration::from_millis(150));
+}
+
+#[test]
+fn idempotent_opcodes_are_pinned() {
+    /* Расширение списка меняет, какие запросы повторяются после обрыва: только осознанно */
+    let idempotent: Vec<Opcode> = (0..=u16::MAX)
+        .filter_map(|code| Opcode::try_from(code).ok())
+        .filter(|opcode| opcode.is_idempotent())
+        .collect();
+
+    let mut expected = vec![
+        Opcode::Ping,
+        Opcode::Config,
+        Opcode::ContactInfo,
+        Opcode::ContactPresence,
+        Opcode::ContactList,
+        Opcode::ContactSearch,
+        Opcode::ContactInfoByPhone,
+        Opcode::ChatInfo,
+        Opcode::ChatHistory,
+        Opcode::ChatMark,
+        Opcode::ChatMedia,
+        Opcode::ChatsList,
+        Opcode::ChatCheckLink,
+        Opcode::ChatMembers,
+        Opcode::PublicSearch,
+        Opcode::MsgTyping,
+        Opcode::ChatSearch,
+        Opcode::MsgGet,
+        Opcode::MsgSearch,
+        Opcode::VideoChatHistory,
+        Opcode::VideoPlay,
+        Opcode::FileDownload,
+        Opcode::LinkInfo,
+        Opcode::SessionsInfo,
+        Opcode::MsgGetReactions,
+        Opcode::MsgGetDetailedReactions,
+    ];
+    expected.sort_by_key(|opcode| u16::from(*opcode));
+    assert_eq!(idempotent, expected);
+
+    for opcode in [Opcode::MsgSend, Opcode::MsgEdit, Opcode::MsgDelete, Opcode::ChatCreate, Opcode::Login] {
+        assert!(!opcode.is_idempotent(), "{:?}", opcode);
+    }
+}
+
+#[test]
+fn default_policy_does_not_retry() {
+    let flood = codes().classify(json!({ "error": RATE_LIMITED, "retryAfter": 0.1 }));
+    assert_eq!(RetryPolicy::default().delay_for(Opcode::MsgSend.into(), &flood, 0), None);
+    assert_eq!(
+        RetryPolicy::enabled().delay_for(Opcode::MsgSend.into(), &flood, 0),
+        Some(Duration::from_millis(100))
+    );
+}
+
+#[test]
+fn delay_depends_on_error_class() {
+    let policy = RetryPolicy::enabled();
+    let codes = codes();
+    let read = u16::from(Opcode::ChatInfo);
+    let write = u16::from(Opcode::MsgSend);
+
+    let transient = codes.classify(json!({ "error": TRANSIENT }));
+    assert!(policy.delay_for(read, &transient, 0).is_some());
+    assert_eq!(policy.delay_for(write, &transient, 0), None);
+
+    let rate_limited = codes.classify(json!({ "error": RATE_LIMITED }));
+    assert!(policy.delay_for(write, &rate_limited, 0).is_some());
+
+    /* Код не настроен: ошибка не повторяется даже для идемпотентного запроса */
+    let unknown = codes.classify(json!({ "error": "test.unknown" }));
+    assert!(matches!(unknown, Error::Api(_)));
+    assert_eq!(policy.delay_for(read, &unknown, 0), None);
+}
+
+#[tokio::test]
+async fn dropped_connection_is_retried_after_reconnect() {
+    let server = MockServer::start(MockProtocol::Web).await.unwrap();
+    server.ignore(Opcode::ChatInfo);
+
+    let client = server.client_builder().retry_policy(RetryPolicy::enabled()).build();
+    client.connect(testing::identity()).await.unwrap();
+
+    let request = client.get_chats(vec![1]);
+    let drop_connection = async {
+        server.wait_for_request(Opcode::ChatInfo).await.unwrap();
+        server.respond(Opcode::ChatInfo, json!({ "chats": [] }));
+        server.disconnect_all();
+    };
+    let (result, _) = tokio::join!(request, drop_connection);
+    result.unwrap();
+
+    let chat_infos = server.requests().iter().filter(|r| r.opcode == u16::from(Opcode::ChatInfo)).count();
+    assert_eq!(chat_infos, 2);
+    let handshakes = server.requests().iter().filter(|r| r.opcode == u16::from(Opcode::SessionInit)).count();
+    assert_eq!(handshakes, 2);
+    client.disconnect().await;
+}
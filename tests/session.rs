@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use rumax::models::Opcode;
+use rumax::session::{FileSessionStore, MemorySessionStore, SessionData, SessionStore, SyncMarkers};
+use rumax::testing::{self, MockProtocol, MockServer};
+use rumax::MaxClient;
+use serde_json::json;
+
+fn session() -> SessionData {
+    SessionData {
+        identity: Some(testing::identity()),
+        token: Some("saved-token".into()),
+        user_id: Some(7),
+        sync: SyncMarkers { chats_sync: 1, contacts_sync: 2, presence_sync: 3, drafts_sync: 4 },
+    }
+}
+
+#[tokio::test]
+async fn file_store_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = FileSessionStore::new(dir.path().join("session.json"));
+    assert!(store.load().await.unwrap().is_none());
+
+    store.save(&session()).await.unwrap();
+    let loaded = store.load().await.unwrap().unwrap();
+    assert_eq!(loaded.token.as_deref(), Some("saved-token"));
+    assert_eq!(loaded.user_id, Some(7));
+    assert_eq!(loaded.identity.unwrap().device_id, "mock-device");
+    assert_eq!(loaded.sync.drafts_sync, 4);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(store.path()).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    store.clear().await.unwrap();
+    assert!(store.load().await.unwrap().is_none());
+    store.clear().await.unwrap();
+}
+
+#[tokio::test]
+async fn from_store_restores_session() {
+    let store = Arc::new(MemorySessionStore::with_data(session()));
+    let client = MaxClient::from_store(store).await.unwrap();
+
+    assert_eq!(client.get_token().await.as_deref(), Some("saved-token"));
+    assert_eq!(client.get_identity().await.unwrap().device_id, "mock-device");
+}
+
+#[tokio::test]
+async fn session_is_saved_after_sync_and_cleared_on_logout() {
+    let server = MockServer::start(MockProtocol::Web).await.unwrap();
+    server.respond(Opcode::Login, json!({ "profile": { "contact": { "id": 9 } }, "time": 500 }));
+    server.respond(Opcode::Logout, json!({}));
+
+    let store = Arc::new(MemorySessionStore::with_data(SessionData {
+        user_id: None,
+        sync: SyncMarkers::default(),
+        ..session()
+    }));
+    let client = server.client_builder().build();
+    client.set_store(store.clone()).await.unwrap();
+
+    client.connect(testing::identity()).await.unwrap();
+    client.sync().await.unwrap();
+    let saved = store.load().await.unwrap().unwrap();
+    assert_eq!(saved.user_id, Some(9));
+    assert_eq!(saved.sync.chats_sync, 500);
+    assert_eq!(saved.token.as_deref(), Some("saved-token"));
+
+    /* disconnect сбрасывает токен в памяти, но не должен затирать сохраненный */
+    client.disconnect().await;
+    client.connect(testing::identity()).await.unwrap();
+    assert_eq!(store.load().await.unwrap().unwrap().token.as_deref(), Some("saved-token"));
+
+    client.logout().await.unwrap();
+    assert!(store.load().await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn accepted_login_with_unexpected_profile_still_logs_in() {
+    let server = MockServer::start(MockProtocol::Web).await.unwrap();
+    server.respond(Opcode::Login, json!({ "profile": { "contact": { "id": "11", "names": "bad" } }, "time": 700 }));
+
+    let store = Arc::new(MemorySessionStore::new());
+    let client = server.client_builder().build();
+    client.set_store(store.clone()).await.unwrap();
+    client.set_token("saved-token".into()).await;
+    client.connect(testing::identity()).await.unwrap();
+
+    /* Ответ не разбирается в SyncResult, но вход уже принят */
+    assert!(client.sync().await.is_err());
+    assert!(client.is_logged_in().await);
+    let saved = store.load().await.unwrap().unwrap();
+    assert_eq!(saved.user_id, Some(11));
+    assert_eq!(saved.sync.chats_sync, 700);
+
+    server.respond(Opcode::Login, json!({ "time": 800 }));
+    assert_eq!(client.sync().await.unwrap().user_id(), None);
+    assert!(client.is_logged_in().await);
+}
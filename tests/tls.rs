@@ -0,0 +1,105 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use rumax::models::Opcode;
+use rumax::testing::{self, MockProtocol, MockServer};
+use rumax::transport::tls::TlsConfig;
+use rumax::{ClientMode, MaxClient};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use serde_json::json;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::TlsAcceptor;
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+}
+
+/* TLS-терминатор с самоподписанным сертификатом localhost перед plain mock-сервером */
+async fn start_tls_terminator(backend: std::net::SocketAddr) -> u16 {
+    let certs: Vec<CertificateDer> = CertificateDer::pem_file_iter(fixture("localhost.cert.pem"))
+        .unwrap()
+        .map(|c| c.unwrap())
+        .collect();
+    let key = PrivateKeyDer::from_pem_file(fixture("localhost.key.pem")).unwrap();
+
+    let config = rustls::ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .unwrap();
+    let acceptor = TlsAcceptor::from(Arc::new(config));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    tokio::spawn(async move {
+        loop {
+            let (tcp, _) = listener.accept().await.unwrap();
+            let acceptor = acceptor.clone();
+            tokio::spawn(async move {
+                let Ok(mut tls) = acceptor.accept(tcp).await else {
+                    return;
+                };
+                let mut plain = TcpStream::connect(backend).await.unwrap();
+                let _ = tokio::io::copy_bidirectional(&mut tls, &mut plain).await;
+            });
+        }
+    });
+
+    port
+}
+
+#[tokio::test]
+async fn ca_file_trusts_self_signed_server() {
+    let server = MockServer::start(MockProtocol::Mobile).await.unwrap();
+    server.respond(Opcode::ChatInfo, json!({ "chats": [] }));
+    let port = start_tls_terminator(server.addr()).await;
+
+    let client = MaxClient::builder()
+        .mode(ClientMode::Mobile)
+        .mobile_host("localhost", port)
+        .tls(TlsConfig::CaFile(fixture("localhost.cert.pem")))
+        .build();
+
+    client.connect(testing::identity()).await.unwrap();
+    client.get_chats(vec![1]).await.unwrap();
+    client.disconnect().await;
+}
+
+#[tokio::test]
+async fn webpki_roots_reject_self_signed_server() {
+    let server = MockServer::start(MockProtocol::Mobile).await.unwrap();
+    let port = start_tls_terminator(server.addr()).await;
+
+    let client = MaxClient::builder()
+        .mode(ClientMode::Mobile)
+        .mobile_host("localhost", port)
+        .auto_reconnect(false)
+        .build();
+
+    assert!(client.connect(testing::identity()).await.is_err());
+}
+
+#[tokio::test]
+async fn missing_ca_file_is_an_error() {
+    let client = MaxClient::builder()
+        .mode(ClientMode::Mobile)
+        .mobile_host("localhost", 1)
+        .tls(TlsConfig::CaFile(fixture("missing.pem")))
+        .build();
+
+    assert!(client.connect(testing::identity()).await.is_err());
+}
+
+#[tokio::test]
+async fn disabled_tls_rejects_wss_uri() {
+    let client = MaxClient::builder()
+        .mode(ClientMode::Web)
+        .websocket_uri("wss://localhost:1/websocket")
+        .tls(TlsConfig::Disabled)
+        .build();
+
+    assert!(client.connect(testing::identity()).await.is_err());
+}
@@ -0,0 +1,190 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rumax::errors::{Error, UploadError};
+use rumax::models::{UploadOptions, UploadedFile, UploadedPhoto, UploadedVideo};
+use rumax::testing::HttpReply;
+use rumax::MaxClient;
+use serde_json::json;
+use tempfile::TempDir;
+use tokio::fs::File;
+
+mod common;
+
+use common::{content, content_ranges, start, temp_file};
+
+#[tokio::test]
+async fn video_is_uploaded_in_chunks_with_progress() {
+    let (server, client) = start().await;
+    let data = content(2500);
+    let dir = tempfile::tempdir().unwrap();
+    let path = temp_file(&dir, "chunks.mp4", &data);
+
+    let progress = Arc::new(Mutex::new(Vec::new()));
+    let seen = progress.clone();
+    let options = UploadOptions::new()
+        .chunk_size(1000)
+        .on_progress(move |p| seen.lock().unwrap().push((p.transferred, p.total.unwrap())));
+
+    let url = server.http_url("/upload/video");
+    let file = File::open(&path).await.unwrap();
+    let video = client
+        .upload_video_with(url, 101, "video-token".into(), file, "chunks.mp4".into(), &options)
+        .await
+        .unwrap();
+
+    assert_eq!(video, UploadedVideo { video_id: 101, token: "video-token".into() });
+    assert_eq!(server.uploaded("/upload/video").unwrap(), data);
+    assert_eq!(
+        content_ranges(&server, "/upload/video"),
+        vec![(0, 999, 2500), (1000, 1999, 2500), (2000, 2499, 2500)]
+    );
+    assert_eq!(
+        *progress.lock().unwrap(),
+        vec![(1000, 2500), (2000, 2500), (2500, 2500)]
+    );
+}
+
+#[tokio::test]
+async fn failed_chunk_is_retried_from_acknowledged_offset() {
+    let (server, client) = start().await;
+    let data = content(3000);
+    let dir = tempfile::tempdir().unwrap();
+    let path = temp_file(&dir, "retry.mp4", &data);
+
+    let received = Arc::new(Mutex::new(vec![0u8; 3000]));
+    let calls = Arc::new(AtomicUsize::new(0));
+    let (store, counter) = (received.clone(), calls.clone());
+    server.on_http("/upload/flaky", move |exchange| {
+        if counter.fetch_add(1, Ordering::SeqCst) == 1 {
+            return HttpReply::new(503, "busy");
+        }
+        let (start, end, total) = exchange.content_range().unwrap();
+        store.lock().unwrap()[start as usize..=end as usize].copy_from_slice(&exchange.body);
+        HttpReply::new(200, format!("{}-{}/{}", start, end, total))
+    });
+
+    let options = UploadOptions::new().chunk_size(1000).retry_delay(Duration::from_millis(10));
+    let file = File::open(&path).await.unwrap();
+    client
+        .upload_file_with(server.http_url("/upload/flaky"), 202, file, "retry.mp4".into(), &options)
+        .await
+        .unwrap();
+
+    assert_eq!(*received.lock().unwrap(), data);
+    assert_eq!(
+        content_ranges(&server, "/upload/flaky"),
+        vec![(0, 999, 3000), (1000, 1999, 3000), (1000, 1999, 3000), (2000, 2999, 3000)]
+    );
+}
+
+#[tokio::test]
+async fn interrupted_upload_resumes_from_offset() {
+    let (server, client) = start().await;
+    let data = content(2000);
+    let dir = tempfile::tempdir().unwrap();
+    let path = temp_file(&dir, "resume.bin", &data);
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    server.on_http("/upload/down", move |exchange| {
+        if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+            let (start, end, total) = exchange.content_range().unwrap();
+            return HttpReply::new(200, format!("{}-{}/{}", start, end, total));
+        }
+        HttpReply::new(502, "bad gateway")
+    });
+
+    let options = UploadOptions::new().chunk_size(1000).max_retries(1).retry_delay(Duration::from_millis(10));
+    let file = File::open(&path).await.unwrap();
+    let result = client
+        .upload_file_with(server.http_url("/upload/down"), 202, file, "resume.bin".into(), &options)
+        .await;
+    match result {
+        Err(Error::Upload(UploadError::Interrupted { offset, source })) => {
+            assert_eq!(offset, 1000);
+            assert!(matches!(*source, UploadError::Status { status: 502, .. }), "{:?}", source);
+        }
+        other => panic!("expected interrupted upload, got {:?}", other),
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+    let options = options.offset(1000);
+    let file = File::open(&path).await.unwrap();
+    let uploaded = client
+        .upload_file_with(server.http_url("/upload/file"), 202, file, "resume.bin".into(), &options)
+        .await
+        .unwrap();
+    assert_eq!(uploaded, UploadedFile { file_id: 202 });
+    assert_eq!(content_ranges(&server, "/upload/file"), vec![(1000, 1999, 2000)]);
+}
+
+#[tokio::test]
+async fn stuck_acknowledgement_is_counted_as_failure() {
+    let (server, client) = start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let path = temp_file(&dir, "stuck.bin", &content(2000));
+
+    /* Сервер всегда подтверждает только первые 1000 байт */
+    server.on_http("/upload/stuck", |_| HttpReply::new(200, "0-999/2000"));
+
+    let options = UploadOptions::new().chunk_size(1000).max_retries(2).retry_delay(Duration::from_millis(10));
+    let file = File::open(&path).await.unwrap();
+    let result = client
+        .upload_file_with(server.http_url("/upload/stuck"), 202, file, "stuck.bin".into(), &options)
+        .await;
+    match result {
+        Err(Error::Upload(UploadError::Interrupted { offset, source })) => {
+            assert_eq!(offset, 1000);
+            assert!(matches!(*source, UploadError::Stalled(_)), "{:?}", source);
+        }
+        other => panic!("expected interrupted upload, got {:?}", other),
+    }
+    /* Первый чанк, затем второй: попытка и два повтора */
+    assert_eq!(
+        content_ranges(&server, "/upload/stuck"),
+        vec![(0, 999, 2000), (1000, 1999, 2000), (1000, 1999, 2000), (1000, 1999, 2000)]
+    );
+}
+
+async fn upload_photo(client: &MaxClient, dir: &TempDir, url: String, name: &str) -> Result<UploadedPhoto, Error> {
+    let path = temp_file(dir, name, b"\x89PNG");
+    let file = File::open(&path).await.unwrap();
+    client.upload_photo(url, file, name.to_string(), None).await
+}
+
+#[tokio::test]
+async fn photo_upload_returns_typed_result_and_errors() {
+    let (server, client) = start().await;
+    let dir = tempfile::tempdir().unwrap();
+
+    let photo = upload_photo(&client, &dir, server.http_url("/upload/photo"), "ok.png").await.unwrap();
+    assert_eq!(photo, UploadedPhoto { photo_token: "photo-token".into() });
+
+    server.on_http("/upload/denied", |_| HttpReply::new(403, "forbidden"));
+    match upload_photo(&client, &dir, server.http_url("/upload/denied"), "a.png").await {
+        Err(Error::Upload(UploadError::Status { status, body })) => {
+            assert_eq!(status, 403);
+            assert_eq!(body, "forbidden");
+        }
+        other => panic!("expected status error, got {:?}", other),
+    }
+
+    server.on_http("/upload/html", |_| HttpReply::new(200, "<html>"));
+    let result = upload_photo(&client, &dir, server.http_url("/upload/html"), "b.png").await;
+    assert!(matches!(result, Err(Error::Upload(UploadError::Parse(_)))), "{:?}", result);
+
+    server.on_http("/upload/empty", |_| HttpReply::json(json!({ "photos": {} })));
+    let result = upload_photo(&client, &dir, server.http_url("/upload/empty"), "c.png").await;
+    assert!(matches!(result, Err(Error::Upload(UploadError::MissingToken))), "{:?}", result);
+
+    let result = upload_photo(&client, &dir, server.http_url("/upload/photo"), "d.txt").await;
+    assert!(matches!(result, Err(Error::Upload(UploadError::UnsupportedType(_)))), "{:?}", result);
+
+    /* HEIC сервер не принимает, без photo_preprocessor загрузка отклоняется */
+    let path = temp_file(&dir, "e.heic", b"\0\0\0\x18ftypheic\0\0\0\0");
+    let file = File::open(&path).await.unwrap();
+    let result = client.upload_photo(server.http_url("/upload/photo"), file, "e.heic".into(), None).await;
+    assert!(matches!(result, Err(Error::Upload(UploadError::UnsupportedType(ref mime))) if mime == "image/heic"), "{:?}", result);
+}